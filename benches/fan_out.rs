@@ -0,0 +1,145 @@
+//! Benchmarks sending one notification to a large number of subscriptions.
+//!
+//! Discord is replaced by a mock sender that takes as long as a typical request. Set `BENCH_DATABASE_URL` to a disposable database to also measure retrieving the subscriptions. It is migrated and seeded with guilds in a reserved range.
+
+use caelus_notifications::{
+    structures::notification::{matching_notifications, NotificationNotify, NotificationType},
+    utility::dispatcher::{Dispatcher, Urgency},
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use std::{env, time::Duration};
+use tokio::{runtime::Runtime, time::sleep};
+
+/// The number of subscriptions to one notification.
+const SUBSCRIPTIONS: u64 = 10_000;
+
+/// How long the mock sender takes to send a message.
+const SEND_DURATION: Duration = Duration::from_millis(1);
+
+/// The concurrency limits to compare.
+const CONCURRENCIES: [usize; 3] = [25, 50, 100];
+
+/// Seeded guilds start here, well above any real snowflake, so they are easy to remove.
+const SEED_GUILD_ID: i64 = i64::MAX - SUBSCRIPTIONS as i64;
+
+/// The offset seeded subscriptions use.
+const SEED_OFFSET: i16 = 5;
+
+fn notification_notify() -> NotificationNotify {
+    NotificationNotify {
+        r#type: NotificationType::DailyReset,
+        start_time: 0,
+        end_time: None,
+        time_until_start: SEED_OFFSET as u32,
+        shard_eruption: None,
+        travelling_spirit_name: None,
+        special_visit_entities: None,
+        travelling_spirit_details: None,
+        weekly_digest: None,
+        occurrences: None,
+        recurring_event: None,
+        maintenance_end: None,
+        game_update: None,
+        late: false,
+        queued_at: None,
+    }
+}
+
+async fn mock_send<N>(_: N) {
+    sleep(SEND_DURATION).await;
+}
+
+async fn seed(pool: &Pool<Postgres>) -> sqlx::Result<()> {
+    sqlx::migrate!().run(pool).await?;
+
+    sqlx::query("delete from notifications where guild_id >= $1;")
+        .bind(SEED_GUILD_ID)
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"insert into notifications (guild_id, "type", channel_id, role_id, "offset") select $1 + n, $2, $1 + n, $1 + n, $3 from generate_series(0, $4 - 1) as n;"#,
+    )
+    .bind(SEED_GUILD_ID)
+    .bind(NotificationType::DailyReset)
+    .bind(SEED_OFFSET)
+    .bind(SUBSCRIPTIONS as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+fn fan_out(criterion: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to create a runtime.");
+    let mut group = criterion.benchmark_group("fan_out");
+    group.sample_size(10);
+
+    for concurrency in CONCURRENCIES {
+        group.bench_with_input(
+            BenchmarkId::new("mock", concurrency),
+            &concurrency,
+            |bencher, &concurrency| {
+                bencher.to_async(&runtime).iter(|| async move {
+                    Dispatcher::new(concurrency, Duration::ZERO, Duration::ZERO)
+                        .fan_out(Urgency::Routine, 0..SUBSCRIPTIONS, |key| *key, mock_send)
+                        .await
+                })
+            },
+        );
+    }
+
+    let Ok(database_url) = env::var("BENCH_DATABASE_URL") else {
+        group.finish();
+        return;
+    };
+
+    let pool = runtime
+        .block_on(async {
+            let pool = PgPoolOptions::new().connect(&database_url).await?;
+            seed(&pool).await?;
+            Ok::<_, sqlx::Error>(pool)
+        })
+        .expect("Failed to seed the benchmark database.");
+
+    let notification_notify = notification_notify();
+
+    for concurrency in CONCURRENCIES {
+        group.bench_with_input(
+            BenchmarkId::new("database", concurrency),
+            &concurrency,
+            |bencher, &concurrency| {
+                bencher.to_async(&runtime).iter(|| async {
+                    let dispatcher = Dispatcher::new(concurrency, Duration::ZERO, Duration::ZERO);
+                    let mut after = None;
+
+                    loop {
+                        let page = matching_notifications(&pool, &notification_notify, after)
+                            .await
+                            .expect("Failed to retrieve notifications.");
+
+                        dispatcher
+                            .fan_out(
+                                Urgency::Routine,
+                                page.notifications,
+                                |notification| notification.channel_id.get(),
+                                mock_send,
+                            )
+                            .await;
+
+                        match page.next {
+                            Some(next) => after = Some(next),
+                            None => break,
+                        }
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, fan_out);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+-- The tables that predate managed migrations. Existing deployments already have them.
+create table if not exists notifications (
+    guild_id text not null,
+    "type" smallint not null,
+    channel_id text not null,
+    role_id text not null,
+    "offset" smallint not null default 0,
+    sendable boolean not null default true
+);
+
+create table if not exists travelling_spirits (
+    visit integer primary key,
+    "entity" text not null,
+    "start" timestamptz not null
+);
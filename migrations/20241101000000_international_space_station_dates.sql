@@ -0,0 +1,3 @@
+create table if not exists international_space_station_dates (
+    "date" date primary key
+);
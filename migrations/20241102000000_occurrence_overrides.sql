@@ -0,0 +1,12 @@
+-- A null "start" adds an occurrence, a null "new_start" removes one, and both together shift one.
+create table if not exists occurrence_overrides (
+    id serial primary key,
+    "type" smallint not null,
+    "start" timestamptz,
+    new_start timestamptz,
+    reason text,
+    check ("start" is not null or new_start is not null)
+);
+
+create index if not exists occurrence_overrides_start_idx on occurrence_overrides ("start");
+create index if not exists occurrence_overrides_new_start_idx on occurrence_overrides (new_start);
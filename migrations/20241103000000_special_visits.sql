@@ -0,0 +1,9 @@
+create table if not exists special_visits (
+    id serial primary key,
+    entities text[] not null,
+    "start" timestamptz not null,
+    "end" timestamptz not null,
+    check ("start" < "end")
+);
+
+create index if not exists special_visits_start_idx on special_visits ("start");
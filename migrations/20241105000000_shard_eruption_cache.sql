@@ -0,0 +1,4 @@
+create table if not exists shard_eruption_cache (
+    "date" date primary key,
+    data jsonb
+);
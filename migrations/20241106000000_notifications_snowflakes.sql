@@ -0,0 +1,4 @@
+alter table notifications
+    alter column guild_id type bigint using guild_id::bigint,
+    alter column channel_id type bigint using channel_id::bigint,
+    alter column role_id type bigint using role_id::bigint;
@@ -0,0 +1,5 @@
+create table if not exists notification_outbox (
+    id bigserial primary key,
+    payload jsonb not null,
+    created_at timestamptz not null default now()
+);
@@ -0,0 +1,7 @@
+create table if not exists user_subscriptions (
+    user_id bigint not null,
+    "type" smallint not null,
+    "offset" smallint not null default 0,
+    sendable boolean not null default true,
+    primary key (user_id, "type")
+);
@@ -0,0 +1,7 @@
+create table if not exists reminders (
+    user_id bigint not null,
+    "type" smallint not null,
+    "start" timestamptz not null,
+    message_url text not null,
+    primary key (user_id, "type", "start")
+);
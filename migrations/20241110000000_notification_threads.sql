@@ -0,0 +1,9 @@
+alter table notifications add column if not exists threaded boolean not null default false;
+
+create table if not exists notification_threads (
+    channel_id bigint not null,
+    "event" text not null,
+    thread_id bigint not null,
+    created_at timestamptz not null default now(),
+    primary key (channel_id, "event")
+);
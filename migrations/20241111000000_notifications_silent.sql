@@ -0,0 +1,3 @@
+alter table notifications
+    alter column role_id drop not null,
+    add column if not exists silent boolean not null default false;
@@ -0,0 +1 @@
+alter table notifications add column if not exists template text;
@@ -0,0 +1,4 @@
+alter table notifications
+    add column if not exists minimum_interval integer,
+    add column if not exists on_the_hour boolean not null default false,
+    add column if not exists last_occurrence bigint;
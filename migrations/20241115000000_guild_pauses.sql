@@ -0,0 +1,4 @@
+create table if not exists guild_pauses (
+    guild_id bigint primary key,
+    paused_until timestamptz not null
+);
@@ -0,0 +1 @@
+alter table notifications add column if not exists unsendable_reason text;
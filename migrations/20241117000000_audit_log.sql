@@ -0,0 +1,12 @@
+create table if not exists audit_log (
+    id bigserial primary key,
+    guild_id bigint,
+    channel_id bigint,
+    user_id bigint,
+    actor text not null,
+    "action" text not null,
+    reason text,
+    created_at timestamptz not null default now()
+);
+
+create index if not exists audit_log_guild_id on audit_log (guild_id, created_at);
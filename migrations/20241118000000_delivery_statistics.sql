@@ -0,0 +1,9 @@
+create table if not exists delivery_statistics (
+    "date" date not null,
+    "type" smallint not null,
+    sent integer not null default 0,
+    failed integer not null default 0,
+    skipped integer not null default 0,
+    coalesced integer not null default 0,
+    primary key ("date", "type")
+);
@@ -0,0 +1,4 @@
+create table if not exists shard_eruption_deliveries (
+    guild_id bigint primary key,
+    "date" date not null
+);
@@ -0,0 +1,14 @@
+-- Activities repeating through a season, such as quests or concerts. Occurrences are every repeat_interval minutes from first_occurrence each day in Sky time.
+create table if not exists seasonal_activities (
+    id serial primary key,
+    name text not null,
+    message text not null,
+    "start" timestamptz not null,
+    "end" timestamptz not null,
+    first_occurrence time not null default '00:00',
+    repeat_interval integer not null,
+    check ("start" < "end"),
+    check (repeat_interval > 0)
+);
+
+create index if not exists seasonal_activities_end_idx on seasonal_activities ("end");
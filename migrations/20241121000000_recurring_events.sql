@@ -0,0 +1,11 @@
+-- Seasonal activities and the AURORA concert are both recurring events, told apart by their notification type. A null "end" recurs indefinitely.
+alter table seasonal_activities rename to recurring_events;
+alter index if exists seasonal_activities_end_idx rename to recurring_events_end_idx;
+
+alter table recurring_events
+    add column if not exists "type" smallint not null default 10,
+    alter column "end" drop not null;
+
+-- The AURORA concert every 2 hours from midnight. To pause it or change its cadence, end this row and add another.
+insert into recurring_events ("type", name, message, "start", first_occurrence, repeat_interval)
+values (9, 'The AURORA concert', 'The AURORA concert is starting! Take your friends!', '2024-01-01 00:00:00-08', '00:00', 120);
@@ -0,0 +1,9 @@
+create table if not exists maintenance_windows (
+    id serial primary key,
+    "start" timestamptz not null,
+    "end" timestamptz not null,
+    reason text,
+    check ("start" < "end")
+);
+
+create index if not exists maintenance_windows_end_idx on maintenance_windows ("end");
@@ -0,0 +1,10 @@
+-- Populated by the main application when a new game version or patch notes are published.
+create table if not exists game_updates (
+    id serial primary key,
+    version text not null,
+    url text,
+    created_at timestamptz not null default now(),
+    notified_at timestamptz
+);
+
+create index if not exists game_updates_unnotified_idx on game_updates (created_at) where notified_at is null;
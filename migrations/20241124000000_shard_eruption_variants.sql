@@ -0,0 +1,5 @@
+-- Marks a day's shard eruption as a special variant, such as "memory", when the wind paths do not.
+create table if not exists shard_eruption_variants (
+    "date" date primary key,
+    variant text not null
+);
@@ -0,0 +1,8 @@
+create table if not exists shard_eruption_realm_roles (
+    guild_id bigint not null,
+    realm text not null,
+    role_id bigint not null,
+    -- Whether to mention this role instead of the subscription's, rather than as well.
+    replace boolean not null default false,
+    primary key (guild_id, realm)
+);
@@ -0,0 +1,5 @@
+-- The last minute each notification type was queued for, so a restarted scheduler does not queue it again.
+create table if not exists processed_minutes (
+    "type" smallint primary key,
+    minute timestamptz not null
+);
@@ -0,0 +1,12 @@
+create table if not exists email_subscriptions (
+    email text primary key,
+    -- Proves the holder of the address asked for the digest, and lets them manage it without an account.
+    token text not null unique,
+    -- The hour of the day, in Sky time, the digest is sent.
+    "hour" smallint not null,
+    -- The day of the week, counted from Monday, a weekly digest is sent. Digests without one are daily.
+    weekday smallint,
+    confirmed_at timestamptz,
+    last_sent_at timestamptz,
+    created_at timestamptz not null default now()
+);
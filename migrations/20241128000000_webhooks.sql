@@ -0,0 +1,11 @@
+-- Delivery targets outside Discord, such as Slack incoming webhooks. The platform decides how messages are formatted.
+create table if not exists webhooks (
+    id bigserial primary key,
+    platform smallint not null,
+    url text not null,
+    "type" smallint not null,
+    "offset" smallint not null default 0,
+    sendable boolean not null default true,
+    created_at timestamptz not null default now(),
+    unique (url, "type", "offset")
+);
@@ -0,0 +1,13 @@
+-- The rendered text of each notification as it was dispatched, for feeds. Only recent rows are kept.
+create table if not exists dispatched_notifications (
+    id bigserial primary key,
+    "type" smallint not null,
+    start_time bigint not null,
+    "offset" smallint not null,
+    "text" text not null,
+    dispatched_at timestamptz not null default now(),
+    unique ("type", start_time, "offset")
+);
+
+create index if not exists dispatched_notifications_type_dispatched_at on dispatched_notifications ("type", dispatched_at);
+create index if not exists dispatched_notifications_dispatched_at on dispatched_notifications (dispatched_at);
@@ -0,0 +1,14 @@
+-- The message each channel was sent for an occurrence, so a notification replayed from the outbox or reclaimed from the stream is not sent twice.
+create table if not exists notification_deliveries (
+    channel_id bigint not null,
+    "type" smallint not null,
+    start_time bigint not null,
+    "offset" smallint not null,
+    guild_id bigint not null,
+    message_id bigint not null,
+    delivered_at timestamptz not null default now(),
+    primary key (channel_id, "type", start_time, "offset")
+);
+
+create index if not exists notification_deliveries_occurrence on notification_deliveries ("type", start_time, "offset");
+create index if not exists notification_deliveries_delivered_at on notification_deliveries (delivered_at);
@@ -0,0 +1,3 @@
+-- Which notifications of an occurrence a subscription receives: 0 at its offset, 1 at the start, or 2 at both.
+alter table notifications
+    add column if not exists delivery_mode smallint not null default 0;
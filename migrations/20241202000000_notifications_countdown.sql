@@ -0,0 +1,3 @@
+-- Offsets a subscription counts down through, such as {1440, 60, 15, 0}. When any are set, they replace the offset and delivery mode.
+alter table notifications
+    add column if not exists countdown smallint[] not null default '{}';
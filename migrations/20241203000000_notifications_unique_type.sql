@@ -0,0 +1,68 @@
+-- A guild subscribes to each type at most once, which subscriptions are paged through by.
+-- Rows for the same type at different offsets are folded into one: a start and a lead become both, and anything else a countdown.
+-- Rows differing in anything but when they are sent cannot be folded, so they fail the migration to be merged by hand.
+do $$
+begin
+    if exists (
+        select 1
+        from notifications
+        group by guild_id, "type"
+        having count(distinct (channel_id, role_id, sendable, rich, threaded, silent, template, emoji, minimum_interval, on_the_hour)) > 1
+    ) then
+        raise exception 'Some guilds subscribe to a type more than once with different settings. Merge them by hand, then migrate again.';
+    end if;
+end $$;
+
+create temporary table folded_notifications on commit drop as
+with duplicated as (
+    select guild_id, "type", min(ctid) as kept
+    from notifications
+    group by guild_id, "type"
+    having count(*) > 1
+),
+offsets as (
+    select guild_id, "type", "offset" from notifications where cardinality(countdown) = 0 and delivery_mode in (0, 2)
+    union
+    select guild_id, "type", 0::smallint from notifications where cardinality(countdown) = 0 and delivery_mode in (1, 2)
+    union
+    select guild_id, "type", unnest(countdown) from notifications
+)
+select duplicated.guild_id, duplicated."type", duplicated.kept, array_agg(offsets."offset" order by offsets."offset" desc) as offsets
+from duplicated
+join offsets using (guild_id, "type")
+group by duplicated.guild_id, duplicated."type", duplicated.kept;
+
+do $$
+begin
+    if exists (select 1 from folded_notifications where cardinality(offsets) > 5) then
+        raise exception 'Some guilds subscribe to a type at more offsets than a countdown may have. Merge them by hand, then migrate again.';
+    end if;
+end $$;
+
+-- Their offsets are folded into the row kept. It is updated afterwards, as that moves it and changes its ctid.
+delete from notifications
+using folded_notifications
+where notifications.guild_id = folded_notifications.guild_id
+    and notifications."type" = folded_notifications."type"
+    and notifications.ctid <> folded_notifications.kept;
+
+update notifications
+set
+    "offset" = case
+        when cardinality(folded_notifications.offsets) = 1 then folded_notifications.offsets[1]
+        when cardinality(folded_notifications.offsets) = 2 and folded_notifications.offsets[2] = 0 then folded_notifications.offsets[1]
+        else 0
+    end,
+    delivery_mode = case
+        when cardinality(folded_notifications.offsets) = 2 and folded_notifications.offsets[2] = 0 then 2
+        else 0
+    end,
+    countdown = case
+        when cardinality(folded_notifications.offsets) = 1 then '{}'
+        when cardinality(folded_notifications.offsets) = 2 and folded_notifications.offsets[2] = 0 then '{}'
+        else folded_notifications.offsets
+    end
+from folded_notifications
+where notifications.ctid = folded_notifications.kept;
+
+create unique index if not exists notifications_guild_id_type_key on notifications (guild_id, "type");
@@ -0,0 +1,37 @@
+-- Tells every process to reload which types and offsets are subscribed to, whichever process or statement changed them.
+create or replace function notify_subscriptions_changed() returns trigger language plpgsql as $$
+begin
+    perform pg_notify('subscriptions_changed', '');
+    return null;
+end $$;
+
+create or replace trigger notifications_subscriptions_changed
+    after insert or delete on notifications
+    for each row execute function notify_subscriptions_changed();
+
+-- Only what decides whether a row is sent to at an offset, so recording deliveries does not reload the index.
+create or replace trigger notifications_subscriptions_updated
+    after update on notifications
+    for each row
+    when ((old."type", old."offset", old.delivery_mode, old.countdown, old.sendable) is distinct from (new."type", new."offset", new.delivery_mode, new.countdown, new.sendable))
+    execute function notify_subscriptions_changed();
+
+create or replace trigger user_subscriptions_subscriptions_changed
+    after insert or delete on user_subscriptions
+    for each row execute function notify_subscriptions_changed();
+
+create or replace trigger user_subscriptions_subscriptions_updated
+    after update on user_subscriptions
+    for each row
+    when ((old."type", old."offset", old.sendable) is distinct from (new."type", new."offset", new.sendable))
+    execute function notify_subscriptions_changed();
+
+create or replace trigger webhooks_subscriptions_changed
+    after insert or delete on webhooks
+    for each row execute function notify_subscriptions_changed();
+
+create or replace trigger webhooks_subscriptions_updated
+    after update on webhooks
+    for each row
+    when ((old."type", old."offset", old.sendable) is distinct from (new."type", new."offset", new.sendable))
+    execute function notify_subscriptions_changed();
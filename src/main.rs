@@ -1,87 +1,320 @@
-mod structures;
-mod utility;
-use anyhow::{Context, Result};
-use chrono::{Datelike, Timelike, Utc, Weekday};
+use anyhow::Result;
+use caelus_notifications::{structures, utility};
+use chrono::{DateTime, Timelike, Utc};
+use chrono_tz::Tz;
+use clap::Parser;
 use core::panic;
 use dotenvy::dotenv;
 use futures::FutureExt;
-use serenity::http::Http;
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
-use std::{env, time::Duration};
+use sqlx::{Connection, PgConnection, Pool, Postgres};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use structures::{
-    notification::{prepare_notification_to_send, NotificationNotify, NotificationType},
-    travelling_spirit::get_last_travelling_spirit,
+    catch_up::catch_up,
+    config::{Config, Role},
+    email_subscription::send_email_digests,
+    game_update::claim_game_updates,
+    guild_cleanup::clean_up_guilds,
+    international_space_station::get_international_space_station_schedule,
+    leader::{assume_leadership, elect, is_leader},
+    maintenance_window::get_maintenance_windows,
+    notification::{prepare_notification_to_send, publish_notification, NotificationNotify},
+    occurrence_override::get_occurrence_overrides,
+    outbox::{drain_outbox, spill_notification},
+    permission_check::check_permissions,
+    processed_minute::claim_processed_minute,
+    recurring_event::get_recurring_events,
+    reminder::send_reminders,
+    schedule::Schedule,
+    shard_eruption::{get_shard_eruption, refresh_shard_eruption, shard_eruption_correction},
+    special_visit::get_upcoming_special_visits,
+    subscriber_index::{is_subscribed, maintain_subscriber_index},
+    travelling_spirit::{
+        fetch_travelling_spirit_details, get_current_travelling_spirit,
+        get_upcoming_travelling_spirits,
+    },
+    travelling_spirit_import::import_travelling_spirits,
+};
+use tokio::time::sleep;
+use tracing_subscriber::{
+    fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
 };
-use tokio::{sync::mpsc, time::sleep};
 use utility::{
-    constants::{
-        INTERNATIONAL_SPACE_STATION_DATES, INTERNATIONAL_SPACE_STATION_PRIOR_DATES,
-        MAXIMUM_CHANNEL_CAPACITY,
-    },
-    functions::last_day_of_month,
-    wind_paths::shard_eruption,
+    admin,
+    alert::alert,
+    broker,
+    cli::Cli,
+    constants::{NOTIFY_HEALTHY_RUN, NOTIFY_PANIC_ALERT_THRESHOLD, NOTIFY_RESTART_MAXIMUM_BACKOFF},
+    credentials::reload_on_hangup,
+    dashboard,
+    database::{connect, connect_read_replica, monitor_pool, refresh},
+    discord_clients::DiscordClients,
+    dispatcher::Dispatcher,
+    email, error_reporting,
+    heartbeat::heartbeat,
+    interactions,
+    latency::check_latency,
+    log_filter, metrics, public_api,
+    queue::NotificationQueue,
+    social,
+    stream::{work, StreamPublisher},
 };
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
     dotenv().ok();
 
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
+    // Commands print their output to stdout, so their logs go to stderr.
+    let log_writer = if cli.command.is_some() {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+
+    // Errors become Sentry events and lesser levels breadcrumbs, once Sentry is initialised.
+    tracing_subscriber::registry()
+        .with(log_filter::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(log_writer))
+        .with(sentry::integrations::tracing::layer())
         .init();
 
-    let environment = env::var("RUST_ENV").unwrap_or("development".to_string());
-    let discord_token = env::var("DISCORD_TOKEN").context("Error retrieving DISCORD_TOKEN.")?;
-    let database_url = env::var("DATABASE_URL").context("Error retrieving DATABASE_URL.")?;
+    let config = Arc::new(Config::load()?);
+    let _sentry = error_reporting::init(&config);
+
+    let pool = connect(&config.database_url, &config.database).await?;
+
+    if let Some(command) = cli.command {
+        return command.run(&config, &pool).await;
+    }
+
+    if config.run_migrations {
+        // Migrations may outlast the statement timeout, so they run on a connection without one.
+        let mut connection = PgConnection::connect(&config.database_url).await?;
+        sqlx::migrate!().run(&mut connection).await?;
+        connection.close().await?;
+        tracing::info!("Applied database migrations.");
+    }
+
+    tokio::spawn(monitor_pool(pool.clone(), "database"));
+    connect_read_replica(config.database_read_url.as_deref(), &config.database).await?;
+    broker::connect(&config.nats).await?;
+    email::connect(&config.email)?;
+    social::connect(&config.social);
+
+    // Workers only send, so there is nothing for them to lead.
+    if config.role.schedules() {
+        if config.leader_election {
+            tokio::spawn(elect(pool.clone()));
+        } else {
+            assume_leadership();
+        }
+    }
+
+    let dispatcher = Arc::new(Dispatcher::new(
+        config.dispatcher.concurrency,
+        Duration::from_millis(config.dispatcher.jitter),
+        Duration::from_millis(config.dispatcher.start_jitter),
+    ));
+
+    let clients = Arc::new(DiscordClients::new(&config, dispatcher.clone()));
+    tokio::spawn(reload_on_hangup(clients.clone(), pool.clone()));
+
+    if config.admin.address.is_some() {
+        let admin_config = config.clone();
+        let admin_pool = pool.clone();
+        let admin_clients = clients.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = admin::serve(admin_config, admin_pool, admin_clients).await {
+                tracing::error!("The admin API stopped: {error:?}");
+            }
+        });
+    }
+
+    if config.dashboard.address.is_some() {
+        let dashboard_config = config.clone();
+        let dashboard_pool = pool.clone();
+        let dashboard_clients = clients.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) =
+                dashboard::serve(dashboard_config, dashboard_pool, dashboard_clients).await
+            {
+                tracing::error!("The dashboard API stopped: {error:?}");
+            }
+        });
+    }
+
+    if config.public_api.address.is_some() {
+        let public_api_config = config.clone();
+        let public_api_pool = pool.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = public_api::serve(public_api_config, public_api_pool).await {
+                tracing::error!("The public API stopped: {error:?}");
+            }
+        });
+    }
+
+    if config.interactions.address.is_some() {
+        let interactions_config = config.clone();
+        let interactions_pool = pool.clone();
 
-    let wind_paths_url = if environment == "production" {
-        env::var("WIND_PATHS_URL").context("Error retrieving WIND_PATHS_URL.")?
+        tokio::spawn(async move {
+            if let Err(error) = interactions::serve(interactions_config, interactions_pool).await {
+                tracing::error!("The interactions endpoint stopped: {error:?}");
+            }
+        });
+    }
+
+    if config.role.schedules() && email::is_enabled() {
+        tokio::spawn(send_email_digests(pool.clone(), config.clone()));
+    }
+
+    if config.role.schedules() && config.travelling_spirit.import_interval > 0 {
+        tokio::spawn(import_travelling_spirits(pool.clone(), config.clone()));
+    }
+
+    // Every role sends, so every role skips offsets nobody is subscribed to.
+    tokio::spawn(maintain_subscriber_index(pool.clone()));
+
+    let travelling_spirit_pool = pool.clone();
+
+    // Workers send too, so every role measures its own delivery latency.
+    tokio::spawn(check_latency(config.clone()));
+
+    if config.role == Role::Worker {
+        tokio::spawn(async move { work(&config, &clients, &pool, &dispatcher).await });
+        tokio::signal::ctrl_c().await?;
+        return Ok(());
+    }
+
+    let publisher = if config.role == Role::Scheduler {
+        Some(StreamPublisher::connect(&config.redis).await?)
     } else {
-        env::var("DEVELOPMENT_WIND_PATHS_URL")
-            .context("Error retrieving DEVELOPMENT_WIND_PATHS_URL.")?
+        None
     };
 
-    let pool = PgPoolOptions::new()
-        .max_connections(2)
-        .connect(&database_url)
-        .await?;
+    tokio::spawn(send_reminders(
+        clients.clone(),
+        pool.clone(),
+        dispatcher.clone(),
+    ));
+
+    if config.guild_cleanup_interval > 0 {
+        tokio::spawn(clean_up_guilds(
+            clients.clone(),
+            pool.clone(),
+            config.clone(),
+        ));
+    }
 
-    let travelling_spirit_pool = pool.clone();
-    let client = Http::new(&discord_token);
-    let (tx, mut rx) = mpsc::channel::<NotificationNotify>(MAXIMUM_CHANNEL_CAPACITY);
+    if config.permission_check_interval > 0 {
+        tokio::spawn(check_permissions(
+            clients.clone(),
+            pool.clone(),
+            config.clone(),
+        ));
+    }
+
+    let maximum_channel_capacity = config.maximum_channel_capacity;
+    let queue = Arc::new(NotificationQueue::new(
+        maximum_channel_capacity,
+        config.backpressure,
+    ));
+
+    let producer_queue = queue.clone();
+    let consumer_config = config.clone();
+    tokio::spawn(drain_outbox(pool.clone(), queue.clone()));
 
     tokio::spawn(async move {
+        let mut consecutive_failures = 0;
+
         loop {
-            let tx_clone = tx.clone();
+            let queue_clone = producer_queue.clone();
             let travelling_spirit_pool_clone = travelling_spirit_pool.clone();
-            let wind_paths_url_clone = wind_paths_url.clone();
+            let config_clone = config.clone();
+            let started = Instant::now();
 
             let result = panic::AssertUnwindSafe(async move {
-                if let Err(error) =
-                    notify(tx_clone, travelling_spirit_pool_clone, wind_paths_url_clone).await
-                {
-                    tracing::error!("Error in notifying: {error:?}");
-                }
+                notify(queue_clone, travelling_spirit_pool_clone, config_clone).await
             })
             .catch_unwind()
             .await;
 
-            if let Err(error) = result {
-                tracing::error!("Panic in notify function: {error:?}");
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(error)) => tracing::error!("Error in notifying: {error:?}"),
+                Err(error) => {
+                    tracing::error!("Panic in notify function: {error:?}");
+                    metrics::increment("notify_panics");
+                }
+            }
+
+            metrics::increment("notify_restarts");
+
+            // A long run means the failure was not part of a crash loop.
+            if started.elapsed() >= NOTIFY_HEALTHY_RUN {
+                consecutive_failures = 0;
             }
+
+            consecutive_failures += 1;
+
+            if consecutive_failures == NOTIFY_PANIC_ALERT_THRESHOLD {
+                alert(
+                    config.alert_webhook_url.as_deref(),
+                    &format!(
+                        "The notify task has failed {consecutive_failures} times in a row and is backing off."
+                    ),
+                )
+                .await;
+            }
+
+            let backoff = Duration::from_secs(2u64.saturating_pow(consecutive_failures))
+                .min(NOTIFY_RESTART_MAXIMUM_BACKOFF);
+
+            tracing::warn!(
+                restarts = metrics::get("notify_restarts"),
+                "Restarting the notify task in {backoff:?}."
+            );
+            sleep(backoff).await;
         }
     });
 
     tokio::spawn(async move {
-        while let Some(notification_notify) = rx.recv().await {
-            prepare_notification_to_send(&client, &pool, &notification_notify).await;
-            let queued = rx.len();
+        loop {
+            let notification_notify = queue.pop().await;
+            let r#type = notification_notify.r#type;
+
+            match &publisher {
+                Some(publisher) => {
+                    if let Err(error) = publisher.publish(&notification_notify).await {
+                        tracing::error!("Failed to publish notification: {error:?}");
+                        spill_notification(&pool, notification_notify).await;
+                    }
+                }
+                None => {
+                    prepare_notification_to_send(
+                        &clients,
+                        &pool,
+                        &dispatcher,
+                        &consumer_config,
+                        notification_notify,
+                    )
+                    .await
+                }
+            }
+
+            let queued = queue.len();
 
-            if queued == MAXIMUM_CHANNEL_CAPACITY {
+            if queued == queue.capacity() {
                 tracing::info!(
-                    "There are {} notifications queued in the channel. This might be a bottleneck. Most recent notification type sent: {}",
+                    "There are {} notifications queued. This might be a bottleneck. Most recent notification type sent: {}",
                     queued,
-                    notification_notify.r#type
+                    r#type
                 );
             }
         }
@@ -92,16 +325,84 @@ async fn main() -> Result<()> {
 }
 
 async fn notify(
-    tx: mpsc::Sender<NotificationNotify>,
+    queue: Arc<NotificationQueue>,
     pool: Pool<Postgres>,
-    wind_paths_url: String,
+    config: Arc<Config>,
 ) -> Result<()> {
-    let mut shard_data = shard_eruption(&wind_paths_url).await;
-    let mut travelling_spirit = get_last_travelling_spirit(&pool).await;
-    let mut travelling_spirit_start = travelling_spirit.start;
+    let today = Utc::now().with_timezone(&chrono_tz::America::Los_Angeles);
+
+    let mut schedule = Schedule {
+        shard_eruption: get_shard_eruption(&pool, &config, today.date_naive()).await,
+        travelling_spirits: vec![],
+        current_travelling_spirit: None,
+        special_visits: vec![],
+        international_space_station_schedule: get_international_space_station_schedule(
+            &pool,
+            today.date_naive(),
+            &config.international_space_station.dates,
+        )
+        .await,
+        occurrence_overrides: vec![],
+        recurring_events: vec![],
+        maintenance_windows: vec![],
+    };
 
-    let mut travelling_spirit_earliest_notification_time =
-        travelling_spirit_start - Duration::from_secs(900);
+    if refresh(
+        &mut schedule.travelling_spirits,
+        get_upcoming_travelling_spirits(&pool).await,
+        "travelling spirits",
+    ) {
+        fetch_travelling_spirit_details(&mut schedule.travelling_spirits, &config).await;
+    }
+
+    // A new environment starts without any, which is fine. They are looked for again daily.
+    if schedule.travelling_spirits.is_empty() {
+        tracing::info!("No upcoming travelling spirits are known. Travelling spirit notifications will be skipped.");
+    }
+
+    refresh(
+        &mut schedule.current_travelling_spirit,
+        get_current_travelling_spirit(&pool).await,
+        "current travelling spirit",
+    );
+
+    refresh(
+        &mut schedule.special_visits,
+        get_upcoming_special_visits(&pool).await,
+        "special visits",
+    );
+
+    refresh(
+        &mut schedule.recurring_events,
+        get_recurring_events(&pool).await,
+        "seasonal activities",
+    );
+
+    refresh(
+        &mut schedule.occurrence_overrides,
+        get_occurrence_overrides(&pool, today).await,
+        "occurrence overrides",
+    );
+
+    refresh(
+        &mut schedule.maintenance_windows,
+        get_maintenance_windows(&pool, today).await,
+        "maintenance windows",
+    );
+
+    // The loop starts at the next minute, so this one is scheduled here, after any missed while down are caught up on.
+    if is_leader() {
+        let now = today.with_second(0).unwrap().with_nanosecond(0).unwrap();
+        let late_notification_notifies = catch_up(&pool, &config, &mut schedule, now).await;
+        let notification_notifies = schedule.notifications(now, &config);
+
+        for notification_notify in late_notification_notifies
+            .into_iter()
+            .chain(claim_subscribed(&pool, &config, now, notification_notifies).await)
+        {
+            queue_at_start(&queue, &pool, notification_notify).await;
+        }
+    }
 
     loop {
         sleep(Duration::from_millis(
@@ -114,248 +415,201 @@ async fn notify(
             .with_nanosecond(0)
             .unwrap();
 
-        let (day, hour, minute) = (now.day(), now.hour(), now.minute());
-        let last_day_of_month = last_day_of_month(now);
-        let mut notification_notifies = vec![];
+        let (hour, minute) = (now.hour(), now.minute());
 
         if hour == 0 && minute == 0 {
             // Update the shard eruption.
-            shard_data = shard_eruption(&wind_paths_url).await;
+            schedule.shard_eruption = get_shard_eruption(&pool, &config, now.date_naive()).await;
 
-            // Update the travelling spirit.
+            // Update the travelling spirits.
             // It may seem unusual to do this every day, but it is not future-proof to check every 2 weeks only.
             // For example, Saluting Protector at 09/12/2024 was out of the usual 2-week rotation.
-            travelling_spirit = get_last_travelling_spirit(&pool).await;
-            travelling_spirit_start = travelling_spirit.start;
-
-            travelling_spirit_earliest_notification_time =
-                travelling_spirit_start - Duration::from_secs(900);
-        }
-
-        if let Some(ref shard) = shard_data {
-            // Find a start timestamp that is 10 minutes before the shard eruption.
-            let timestamps = shard.timestamps.iter().find(|dates| {
-                let time = dates.start.signed_duration_since(now);
-                (0..=10).contains(&time.num_minutes())
-            });
-
-            if let Some(dates) = timestamps {
-                let r#type = if shard.strong {
-                    NotificationType::ShardEruptionStrong
-                } else {
-                    NotificationType::ShardEruptionRegular
-                };
-
-                notification_notifies.push(NotificationNotify {
-                    r#type,
-                    start_time: dates.start.timestamp(),
-                    end_time: Some(dates.end.timestamp()),
-                    time_until_start: dates
-                        .start
-                        .signed_duration_since(now)
-                        .num_minutes()
-                        .try_into()
-                        .expect("Failed to create time_until_start for a shard eruption."),
-                    shard_eruption: Some(shard.clone()),
-                    travelling_spirit_name: None,
-                });
+            if refresh(
+                &mut schedule.travelling_spirits,
+                get_upcoming_travelling_spirits(&pool).await,
+                "travelling spirits",
+            ) {
+                fetch_travelling_spirit_details(&mut schedule.travelling_spirits, &config).await;
             }
-        }
 
-        if (hour == 23 && (45..=59).contains(&minute)) || (hour == 0 && minute == 0) {
-            let time_until_start = (60 - minute) % 60;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::DailyReset,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+            refresh(
+                &mut schedule.current_travelling_spirit,
+                get_current_travelling_spirit(&pool).await,
+                "current travelling spirit",
+            );
 
-        if (now.weekday() == Weekday::Sat && hour == 23 && (36..=59).contains(&minute))
-            || (now.weekday() == Weekday::Sun && hour == 0 && minute == 0)
-        {
-            let time_until_start = (60 - minute) % 60;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::EyeOfEden,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+            refresh(
+                &mut schedule.special_visits,
+                get_upcoming_special_visits(&pool).await,
+                "special visits",
+            );
 
-        if (INTERNATIONAL_SPACE_STATION_PRIOR_DATES.contains(&day)
-            && hour == 23
-            && (45..=59).contains(&minute))
-            || (INTERNATIONAL_SPACE_STATION_DATES.contains(&day) && hour == 0 && minute == 0)
-        {
-            let time_until_start = (60 - minute) % 60;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::InternationalSpaceStation,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+            refresh(
+                &mut schedule.recurring_events,
+                get_recurring_events(&pool).await,
+                "seasonal activities",
+            );
 
-        if now >= travelling_spirit_earliest_notification_time && now <= travelling_spirit_start {
-            let time_until_start = (travelling_spirit_start - now).num_minutes();
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::TravellingSpirit,
-                start_time: travelling_spirit_start.timestamp(),
-                end_time: None,
-                time_until_start: time_until_start
-                    .try_into()
-                    .expect("Failed to create time_until_start for a travelling spirit."),
-                shard_eruption: None,
-                travelling_spirit_name: Some(travelling_spirit.entity.clone()),
-            });
+            // Update the International Space Station schedule.
+            schedule.international_space_station_schedule =
+                get_international_space_station_schedule(
+                    &pool,
+                    now.date_naive(),
+                    &config.international_space_station.dates,
+                )
+                .await;
         }
 
+        let mut corrections = vec![];
+
+        // The wind paths occasionally correct the day's shard eruption after a schedule change.
         if minute == 0
-            || (10..=15).contains(&minute)
-            || (25..=30).contains(&minute)
-            || (40..=45).contains(&minute)
-            || (55..=59).contains(&minute)
+            && hour != 0
+            && refresh_shard_eruption(
+                &pool,
+                &config,
+                now.date_naive(),
+                &mut schedule.shard_eruption,
+            )
+            .await
         {
-            let time_until_start = match 15 - (minute % 15) {
-                15 => 0,
-                minute => minute,
-            };
-
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::Passage,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
+            corrections.push(shard_eruption_correction(
+                now,
+                schedule.shard_eruption.clone(),
+            ));
         }
 
-        if (((hour % 2) == 1) && (45..=59).contains(&minute)) || (((hour % 2) == 0) && minute == 0)
-        {
-            let time_until_start = (60 - minute) % 60;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::Aurora,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+        if minute == 0 {
+            // Overrides and maintenance windows may be added at short notice, so refresh them hourly.
+            refresh(
+                &mut schedule.occurrence_overrides,
+                get_occurrence_overrides(&pool, now).await,
+                "occurrence overrides",
+            );
 
-        if ((0..=5).contains(&minute) && (hour % 2) == 0)
-            || ((55..=59).contains(&minute) && (hour % 2) == 1)
-        {
-            let time_until_start = match hour % 2 {
-                0 => 5 - minute,
-                1 => 65 - minute,
-                _ => unreachable!(),
-            };
-
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::PollutedGeyser,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
+            refresh(
+                &mut schedule.maintenance_windows,
+                get_maintenance_windows(&pool, now).await,
+                "maintenance windows",
+            );
         }
 
-        if ((hour % 2) == 0) && ((25..=35).contains(&minute)) {
-            let time_until_start = 35 - minute;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::Grandma,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+        let notification_notifies = schedule.notifications(now, &config);
 
-        if ((hour % 2) == 0) && ((40..=50).contains(&minute)) {
-            let time_until_start = 50 - minute;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::Turtle,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
+        // Standbys keep their state current so they can take over at once, but leave sending to the leader.
+        if !is_leader() {
+            continue;
         }
 
-        if (day == 1
-            && ((((hour % 4) == 0) && minute == 0)
-                || ((hour % 4) == 3) && (45..=59).contains(&minute)))
-            || (day == last_day_of_month && hour == 23 && (45..=59).contains(&minute))
-        {
-            let time_until_start = (60 - minute) % 60;
-            let date = now + Duration::from_secs((time_until_start * 60).into());
-
-            notification_notifies.push(NotificationNotify {
-                r#type: NotificationType::AviarysFireworkFestival,
-                start_time: date.timestamp(),
-                end_time: None,
-                time_until_start,
-                shard_eruption: None,
-                travelling_spirit_name: None,
-            });
-        }
+        // Polled every minute, so updates are announced soon after the main application records them.
+        let game_updates = match claim_game_updates(&pool, now).await {
+            Ok(game_updates) => game_updates,
+            Err(error) => {
+                tracing::error!("Failed to claim game updates: {error:?}");
+                vec![]
+            }
+        };
+
+        let notification_notifies = claim_subscribed(
+            &pool,
+            &config,
+            now,
+            notification_notifies
+                .into_iter()
+                .chain(corrections)
+                .collect(),
+        )
+        .await;
 
-        // if minute == 0 || (50..=59).contains(&minute) {
-        //     let time_until_start = (60 - minute) % 60;
-        //     let date = now + Duration::from_secs((time_until_start * 60).into());
-
-        //     notification_notifies.push(NotificationNotify {
-        //         r#type: NotificationType::Dragon,
-        //         start_time: date.timestamp(),
-        //         end_time: None,
-        //         time_until_start,
-        //         shard_eruption: None,
-        //         travelling_spirit_name: None,
-        //     });
-        // }
-
-        for notification_notify in notification_notifies {
+        for notification_notify in notification_notifies.into_iter().chain(game_updates) {
             tracing::info!(
                 r#type = ?notification_notify.r#type,
                 until = notification_notify.time_until_start,
                 "Notifications Queuing"
             );
 
-            let send = tx.send(notification_notify).await;
+            queue_at_start(&queue, &pool, notification_notify).await;
+        }
+
+        heartbeat(config.heartbeat_url.as_deref());
+    }
+}
+
+/// Claims the notifications of a minute that something is subscribed to, to be queued, and publishes the rest.
+async fn claim_subscribed(
+    pool: &Pool<Postgres>,
+    config: &Arc<Config>,
+    now: DateTime<Tz>,
+    notification_notifies: Vec<NotificationNotify>,
+) -> Vec<NotificationNotify> {
+    let (notification_notifies, unsubscribed): (Vec<_>, Vec<_>) = notification_notifies
+        .into_iter()
+        .partition(|notification_notify| {
+            is_subscribed(
+                notification_notify.r#type,
+                notification_notify.time_until_start,
+            )
+        });
+
+    // Feeds and the broker announce every notification, but one nobody is subscribed to need not be claimed or queued.
+    for notification_notify in unsubscribed {
+        publish_at_start(pool, config, notification_notify);
+    }
+
+    // The task may restart within a minute it already queued, so skip types already processed.
+    claim_processed_minute(pool, now, notification_notifies).await
+}
 
-            if let Err(error) = send {
-                tracing::error!("Failed to queue notification: {error:?}");
+/// Queues a notification, spilling it to the outbox if the queue is full.
+///
+/// Shards land partway through a minute, so a notification of an occurrence starting now is held in the background until the second it starts.
+async fn queue_at_start(
+    queue: &Arc<NotificationQueue>,
+    pool: &Pool<Postgres>,
+    notification_notify: NotificationNotify,
+) {
+    if let Some(until_start) = until_start(&notification_notify) {
+        let queue = Arc::clone(queue);
+        let pool = pool.clone();
+
+        tokio::spawn(async move {
+            sleep(until_start).await;
+
+            if let Some(notification_notify) = queue.push(notification_notify).await {
+                spill_notification(&pool, notification_notify).await;
             }
-        }
+        });
+
+        return;
+    }
+
+    if let Some(notification_notify) = queue.push(notification_notify).await {
+        spill_notification(pool, notification_notify).await;
     }
 }
+
+/// Publishes a notification nobody is subscribed to in the background, at the second it starts if it is of the start.
+fn publish_at_start(
+    pool: &Pool<Postgres>,
+    config: &Arc<Config>,
+    notification_notify: NotificationNotify,
+) {
+    let pool = pool.clone();
+    let config = Arc::clone(config);
+
+    tokio::spawn(async move {
+        if let Some(until_start) = until_start(&notification_notify) {
+            sleep(until_start).await;
+        }
+
+        publish_notification(&pool, &config, &notification_notify).await;
+    });
+}
+
+/// How long until an occurrence a notification is of the start of starts, if it has yet to.
+fn until_start(notification_notify: &NotificationNotify) -> Option<Duration> {
+    let until_start = notification_notify.start_time * 1000 - Utc::now().timestamp_millis();
+
+    (notification_notify.time_until_start == 0 && until_start > 0)
+        .then(|| Duration::from_millis(until_start as u64))
+}
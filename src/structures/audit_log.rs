@@ -0,0 +1,80 @@
+use super::notification::optional_snowflake;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use sqlx::{postgres::PgRow, FromRow, Pool, Postgres, Row};
+
+/// How many entries are returned when reading a guild's audit log.
+const AUDIT_LOG_LIMIT: i64 = 100;
+
+/// A change to what is notified, recorded so support can explain it without the logs.
+#[derive(Default)]
+pub struct AuditEvent<'a> {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: Option<ChannelId>,
+    pub user_id: Option<UserId>,
+    /// What made the change, such as "permission_check" or "admin".
+    pub actor: &'a str,
+    /// What changed, such as "disabled" or "circuit_opened".
+    pub action: &'a str,
+    pub reason: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+pub struct AuditLogEntry {
+    pub channel_id: Option<ChannelId>,
+    pub user_id: Option<UserId>,
+    pub actor: String,
+    pub action: String,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FromRow<'_, PgRow> for AuditLogEntry {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            channel_id: optional_snowflake(row, "channel_id")?,
+            user_id: optional_snowflake(row, "user_id")?,
+            actor: row.try_get("actor")?,
+            action: row.try_get("action")?,
+            reason: row.try_get("reason")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// Records an audit event. Failing to do so is logged rather than failing the change itself.
+pub async fn audit(pool: &Pool<Postgres>, event: AuditEvent<'_>) {
+    let result = sqlx::query(
+        r#"insert into audit_log (guild_id, channel_id, user_id, actor, "action", reason) values ($1, $2, $3, $4, $5, $6);"#,
+    )
+    .bind(event.guild_id.map(|guild_id| guild_id.get() as i64))
+    .bind(event.channel_id.map(|channel_id| channel_id.get() as i64))
+    .bind(event.user_id.map(|user_id| user_id.get() as i64))
+    .bind(event.actor)
+    .bind(event.action)
+    .bind(event.reason)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(
+            action = event.action,
+            "Failed to record an audit event: {error:?}"
+        );
+    }
+}
+
+/// Retrieves a guild's most recent audit events, newest first.
+pub async fn get_audit_log(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+) -> Result<Vec<AuditLogEntry>, sqlx::Error> {
+    sqlx::query_as(
+        r#"select channel_id, user_id, actor, "action", reason, created_at from audit_log where guild_id = $1 order by created_at desc limit $2;"#,
+    )
+    .bind(guild_id.get() as i64)
+    .bind(AUDIT_LOG_LIMIT)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,70 @@
+use super::{
+    config::Config,
+    notification::NotificationNotify,
+    processed_minute::{claim_processed_minute, get_processed_minutes},
+    schedule::Schedule,
+};
+use chrono::{DateTime, TimeDelta};
+use chrono_tz::Tz;
+use sqlx::{Pool, Postgres};
+
+/// Finds the occurrences that started while the scheduler was down, before `now`, to notify of late.
+///
+/// Only types configured to be caught up are, and only within their window. A type is caught up from the minute after it was last queued, so types never queued are not. The minutes caught up on are claimed, so restarting again does not repeat them.
+pub async fn catch_up(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    schedule: &mut Schedule,
+    now: DateTime<Tz>,
+) -> Vec<NotificationNotify> {
+    let Some(window) = config.catch_up.values().copied().max() else {
+        return vec![];
+    };
+
+    let processed_minutes = match get_processed_minutes(pool).await {
+        Ok(processed_minutes) => processed_minutes,
+        Err(error) => {
+            tracing::error!("Failed to retrieve the processed minutes to catch up: {error:?}");
+            return vec![];
+        }
+    };
+
+    let mut late_notification_notifies = vec![];
+
+    // The current minute is not late, so it is left to be scheduled as usual.
+    for minutes_ago in (1..=window).rev() {
+        let minute = now - TimeDelta::minutes(minutes_ago.into());
+
+        // Every minute is scheduled, in order, to keep the schedule's state as it would have been.
+        let missed = schedule
+            .notifications(minute, config)
+            .into_iter()
+            .filter(|notification_notify| {
+                notification_notify.time_until_start == 0
+                    && config
+                        .catch_up
+                        .get(&notification_notify.r#type)
+                        .is_some_and(|window| minutes_ago <= *window)
+                    && processed_minutes
+                        .get(&notification_notify.r#type)
+                        .is_some_and(|processed_minute| *processed_minute < minute)
+            })
+            .map(|notification_notify| NotificationNotify {
+                late: true,
+                ..notification_notify
+            })
+            .collect::<Vec<_>>();
+
+        for notification_notify in claim_processed_minute(pool, minute, missed).await {
+            tracing::info!(
+                r#type = ?notification_notify.r#type,
+                minutes_ago,
+                "Catching up on a missed occurrence."
+            );
+
+            late_notification_notifies.push(notification_notify);
+        }
+    }
+
+    late_notification_notifies
+}
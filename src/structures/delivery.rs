@@ -0,0 +1,67 @@
+use super::notification::NotificationNotify;
+use crate::utility::{constants::DELIVERY_RETENTION, database::with_retry};
+use chrono::Utc;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use sqlx::{Pool, Postgres};
+use std::collections::HashSet;
+
+/// Retrieves the channels already sent a notification's occurrence.
+pub async fn get_delivered_channels(
+    pool: &Pool<Postgres>,
+    notification_notify: &NotificationNotify,
+) -> Result<HashSet<ChannelId>, sqlx::Error> {
+    let channel_ids: Vec<i64> = with_retry("notification deliveries", || {
+        sqlx::query_scalar(
+            r#"select channel_id from notification_deliveries where "type" = $1 and start_time = $2 and "offset" = $3;"#,
+        )
+        .bind(notification_notify.r#type)
+        .bind(notification_notify.start_time)
+        .bind(notification_notify.time_until_start as i16)
+        .fetch_all(pool)
+    })
+    .await?;
+
+    Ok(channel_ids
+        .into_iter()
+        .filter_map(|channel_id| u64::try_from(channel_id).ok())
+        .filter(|channel_id| *channel_id != 0)
+        .map(ChannelId::new)
+        .collect())
+}
+
+/// Remembers the message a channel was sent for an occurrence. Failing to do so is logged, as the message's nonce still prevents a resend for a few minutes.
+pub async fn record_channel_delivery(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    notification_notify: &NotificationNotify,
+) {
+    let result = sqlx::query(
+        r#"insert into notification_deliveries (channel_id, "type", start_time, "offset", guild_id, message_id) values ($1, $2, $3, $4, $5, $6) on conflict (channel_id, "type", start_time, "offset") do nothing;"#,
+    )
+    .bind(channel_id.get() as i64)
+    .bind(notification_notify.r#type)
+    .bind(notification_notify.start_time)
+    .bind(notification_notify.time_until_start as i16)
+    .bind(guild_id.get() as i64)
+    .bind(message_id.get() as i64)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(%guild_id, %channel_id, "Failed to record a delivery: {error:?}");
+    }
+}
+
+/// Forgets deliveries too old to be sent again.
+pub async fn forget_old_deliveries(pool: &Pool<Postgres>) {
+    let result = sqlx::query("delete from notification_deliveries where delivered_at < $1;")
+        .bind(Utc::now() - DELIVERY_RETENTION)
+        .execute(pool)
+        .await;
+
+    if let Err(error) = result {
+        tracing::error!("Failed to forget old deliveries: {error:?}");
+    }
+}
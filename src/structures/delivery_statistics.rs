@@ -0,0 +1,68 @@
+use super::notification::NotificationType;
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::{FromRow, Pool, Postgres};
+
+/// What happened to the subscriptions matched by one notification.
+#[derive(Default)]
+pub struct DeliveryCounts {
+    pub sent: i32,
+    pub failed: i32,
+    /// Throttled, or not attempted as the channel's circuit is open.
+    pub skipped: i32,
+    /// Merged into another message to the same channel.
+    pub coalesced: i32,
+}
+
+#[derive(FromRow, Serialize)]
+pub struct DeliveryStatistics {
+    pub date: NaiveDate,
+    #[serde(rename = "type")]
+    pub r#type: NotificationType,
+    pub sent: i32,
+    pub failed: i32,
+    pub skipped: i32,
+    pub coalesced: i32,
+}
+
+/// Adds to today's counts for a notification type. Failing to do so is logged rather than failing the send.
+pub async fn record_delivery(
+    pool: &Pool<Postgres>,
+    r#type: NotificationType,
+    delivery_counts: &DeliveryCounts,
+) {
+    let today = Utc::now()
+        .with_timezone(&chrono_tz::America::Los_Angeles)
+        .date_naive();
+
+    let result = sqlx::query(
+        r#"insert into delivery_statistics ("date", "type", sent, failed, skipped, coalesced) values ($1, $2, $3, $4, $5, $6) on conflict ("date", "type") do update set sent = delivery_statistics.sent + excluded.sent, failed = delivery_statistics.failed + excluded.failed, skipped = delivery_statistics.skipped + excluded.skipped, coalesced = delivery_statistics.coalesced + excluded.coalesced;"#,
+    )
+    .bind(today)
+    .bind(r#type)
+    .bind(delivery_counts.sent)
+    .bind(delivery_counts.failed)
+    .bind(delivery_counts.skipped)
+    .bind(delivery_counts.coalesced)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(?r#type, "Failed to record delivery statistics: {error:?}");
+    }
+}
+
+/// Retrieves the counts for every notification type between two dates, inclusive.
+pub async fn get_delivery_statistics(
+    pool: &Pool<Postgres>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<DeliveryStatistics>, sqlx::Error> {
+    sqlx::query_as(
+        r#"select "date", "type", sent, failed, skipped, coalesced from delivery_statistics where "date" between $1 and $2 order by "date", "type";"#,
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,68 @@
+use super::{
+    config::Config,
+    notification::{NotificationNotify, NotificationType},
+};
+use crate::utility::constants::{FEED_MAXIMUM_ENTRIES, FEED_RETENTION};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Postgres};
+
+/// A notification as it was dispatched, with its text as written for Discord.
+#[derive(FromRow)]
+pub struct DispatchedNotification {
+    pub r#type: NotificationType,
+    pub start_time: i64,
+    pub offset: i16,
+    pub text: String,
+    pub dispatched_at: DateTime<Utc>,
+}
+
+/// Records a notification for feeds and forgets those older than the retention. Failing to do so is logged rather than failing the send.
+pub async fn record_dispatch(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    notification_notify: &NotificationNotify,
+) {
+    let text = notification_notify.with_notes(notification_notify.text(config.flavor_text));
+
+    let result = sqlx::query(
+        r#"insert into dispatched_notifications ("type", start_time, "offset", "text") values ($1, $2, $3, $4) on conflict ("type", start_time, "offset") do nothing;"#,
+    )
+    .bind(notification_notify.r#type)
+    .bind(notification_notify.start_time)
+    .bind(notification_notify.time_until_start as i16)
+    .bind(text)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(
+            r#type = ?notification_notify.r#type,
+            "Failed to record a dispatched notification: {error:?}"
+        );
+
+        return;
+    }
+
+    let result = sqlx::query("delete from dispatched_notifications where dispatched_at < $1;")
+        .bind(Utc::now() - FEED_RETENTION)
+        .execute(pool)
+        .await;
+
+    if let Err(error) = result {
+        tracing::error!("Failed to forget old dispatched notifications: {error:?}");
+    }
+}
+
+/// Retrieves the most recently dispatched notifications, newest first, of one type or of every type.
+pub async fn get_dispatched_notifications(
+    pool: &Pool<Postgres>,
+    r#type: Option<NotificationType>,
+) -> Result<Vec<DispatchedNotification>, sqlx::Error> {
+    sqlx::query_as(
+        r#"select "type", start_time, "offset", "text", dispatched_at from dispatched_notifications where $1::smallint is null or "type" = $1 order by dispatched_at desc, id desc limit $2;"#,
+    )
+    .bind(r#type)
+    .bind(FEED_MAXIMUM_ENTRIES)
+    .fetch_all(pool)
+    .await
+}
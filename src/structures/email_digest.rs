@@ -0,0 +1,187 @@
+use super::{
+    config::Config,
+    international_space_station::get_international_space_station_schedule,
+    shard_eruption::get_known_shard_eruption,
+    travelling_spirit::{get_current_travelling_spirit, get_upcoming_travelling_spirits},
+};
+use crate::utility::{functions::format_list, wind_paths::ShardEruptionResponse};
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Weekday};
+use chrono_tz::{America::Los_Angeles, Tz};
+use sqlx::{Pool, Postgres};
+
+/// A summary of Sky sent by email. Emails cannot show Discord timestamps, so times are written in Sky time.
+pub struct EmailDigest {
+    pub subject: String,
+    pub body: String,
+}
+
+/// Summarises the day of `now`: its shard eruption, the resets, and the travelling spirit.
+pub async fn daily_email_digest(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    now: DateTime<Tz>,
+) -> Result<EmailDigest> {
+    let today = now.date_naive();
+    let shard_eruption = get_known_shard_eruption(pool, config, today).await?;
+    let mut lines = vec![
+        format!("Here is {} in Sky.", today.format("%A, %-d %B")),
+        String::new(),
+    ];
+
+    lines.push("Shard eruption".to_string());
+
+    match &shard_eruption {
+        Some(shard_eruption) => {
+            lines.push(describe_shard_eruption(shard_eruption));
+
+            let landings = shard_eruption
+                .timestamps
+                .iter()
+                .map(|dates| {
+                    format!(
+                        "{} to {}",
+                        dates.start.with_timezone(&Los_Angeles).format("%H:%M"),
+                        dates.end.with_timezone(&Los_Angeles).format("%H:%M")
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            lines.push(format!(
+                "It lands from {} Sky time.",
+                format_list(&landings)
+            ));
+        }
+        None => lines.push("There is no shard eruption today.".to_string()),
+    }
+
+    lines.push(String::new());
+    lines.push("Resets".to_string());
+    lines.push("Daily quests and candles reset at 00:00 Sky time.".to_string());
+
+    lines.push(if today.weekday() == Weekday::Sat {
+        "Eye of Eden statues reset tonight at 00:00 Sky time.".to_string()
+    } else {
+        "Eye of Eden statues reset on Sunday at 00:00 Sky time.".to_string()
+    });
+
+    lines.push(String::new());
+    lines.push("Travelling spirit".to_string());
+    lines.push(travelling_spirit(pool, now).await?);
+
+    Ok(EmailDigest {
+        subject: format!("Sky today: {}", today.format("%A, %-d %B")),
+        body: lines.join("\n"),
+    })
+}
+
+/// Summarises the 7 days from `now`.
+pub async fn weekly_email_digest(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    now: DateTime<Tz>,
+) -> Result<EmailDigest> {
+    let today = now.date_naive();
+    let week = today.iter_days().take(7).collect::<Vec<_>>();
+    let mut lines = vec!["Here is the week ahead in Sky.".to_string(), String::new()];
+
+    lines.push("Shard eruptions".to_string());
+
+    for date in &week {
+        let description = match get_known_shard_eruption(pool, config, *date).await? {
+            Some(shard_eruption) => describe_shard_eruption(&shard_eruption),
+            None => "No shard eruption.".to_string(),
+        };
+
+        lines.push(format!("- {}: {description}", date.format("%A")));
+    }
+
+    lines.push(String::new());
+    lines.push("International Space Station".to_string());
+
+    let international_space_station_schedule = get_international_space_station_schedule(
+        pool,
+        today,
+        &config.international_space_station.dates,
+    )
+    .await;
+
+    let international_space_station_days = week
+        .iter()
+        .filter(|date| international_space_station_schedule.is_accessible(**date))
+        .map(|date| date.format("%A").to_string())
+        .collect::<Vec<_>>();
+
+    lines.push(if international_space_station_days.is_empty() {
+        "Not accessible this week.".to_string()
+    } else {
+        format!(
+            "Accessible on {}.",
+            format_list(&international_space_station_days)
+        )
+    });
+
+    lines.push(String::new());
+    lines.push("Eye of Eden".to_string());
+
+    lines.push(format!(
+        "Statues reset on {} at 00:00 Sky time.",
+        next_sunday(today).format("%A, %-d %B")
+    ));
+
+    lines.push(String::new());
+    lines.push("Travelling spirit".to_string());
+    lines.push(travelling_spirit(pool, now).await?);
+
+    Ok(EmailDigest {
+        subject: format!("Sky this week: {}", today.format("%-d %B")),
+        body: lines.join("\n"),
+    })
+}
+
+fn describe_shard_eruption(shard_eruption: &ShardEruptionResponse) -> String {
+    format!(
+        "{} shard eruption in the {} ({}), rewarding {}.{}",
+        if shard_eruption.strong {
+            "A strong"
+        } else {
+            "A regular"
+        },
+        shard_eruption.realm,
+        shard_eruption.sky_map,
+        shard_eruption.reward_text(),
+        shard_eruption.variant_note()
+    )
+}
+
+/// The Sunday after a day, when the statues in the Eye of Eden reset.
+fn next_sunday(date: NaiveDate) -> NaiveDate {
+    date + TimeDelta::days(7 - i64::from(date.weekday().num_days_from_sunday()))
+}
+
+async fn travelling_spirit(pool: &Pool<Postgres>, now: DateTime<Tz>) -> Result<String> {
+    let mut sentences = vec![];
+
+    if let Some(current_travelling_spirit) = get_current_travelling_spirit(pool).await? {
+        sentences.push(format!("{current_travelling_spirit} is visiting."));
+    }
+
+    if let Some(travelling_spirit) = get_upcoming_travelling_spirits(pool)
+        .await?
+        .into_iter()
+        .find(|travelling_spirit| travelling_spirit.start >= now)
+    {
+        sentences.push(format!(
+            "{} arrives on {} at {} Sky time.",
+            travelling_spirit.entity,
+            travelling_spirit.start.format("%A, %-d %B"),
+            travelling_spirit.start.format("%H:%M")
+        ));
+    }
+
+    if sentences.is_empty() {
+        sentences.push("No travelling spirit has been announced yet.".to_string());
+    }
+
+    Ok(sentences.join(" "))
+}
@@ -0,0 +1,262 @@
+use super::{
+    config::Config,
+    email_digest::{daily_email_digest, weekly_email_digest, EmailDigest},
+    leader::is_leader,
+};
+use crate::utility::{
+    constants::{EMAIL_DIGEST_INTERVAL, EMAIL_DIGEST_MAXIMUM_LATENESS},
+    email, metrics,
+};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::{America::Los_Angeles, Tz};
+use lettre::Address;
+use rand::RngCore;
+use serde::Deserialize;
+use sqlx::{FromRow, Pool, Postgres};
+use std::sync::Arc;
+use tokio::time::sleep;
+
+/// When someone wants digests emailed to them.
+#[derive(Deserialize)]
+pub struct EmailPreferences {
+    /// The hour of the day, in Sky time.
+    pub hour: u8,
+    /// The day of the week for a weekly digest. Digests are daily without one.
+    pub weekday: Option<Weekday>,
+}
+
+impl EmailPreferences {
+    pub fn validate(&self) -> Result<()> {
+        if self.hour > 23 {
+            bail!("The hour must be from 0 to 23.");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(FromRow)]
+struct EmailSubscription {
+    email: String,
+    token: String,
+    hour: i16,
+    weekday: Option<i16>,
+    confirmed_at: Option<DateTime<Utc>>,
+    last_sent_at: Option<DateTime<Utc>>,
+}
+
+impl EmailSubscription {
+    /// The latest time a digest was due at or before `now`.
+    fn due_at(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let time = NaiveTime::from_hms_opt(self.hour.try_into().ok()?, 0, 0)?;
+
+        let mut date = now.date_naive();
+
+        if let Some(weekday) = self.weekday {
+            let days = (i64::from(date.weekday().num_days_from_monday()) - i64::from(weekday))
+                .rem_euclid(7);
+
+            date -= TimeDelta::days(days);
+        }
+
+        let due_at = Los_Angeles
+            .from_local_datetime(&date.and_time(time))
+            .earliest()?;
+
+        if due_at <= now {
+            return Some(due_at);
+        }
+
+        let period = if self.weekday.is_some() { 7 } else { 1 };
+
+        Los_Angeles
+            .from_local_datetime(&(date - TimeDelta::days(period)).and_time(time))
+            .earliest()
+    }
+}
+
+/// Registers an email address for digests and returns the token to confirm it with.
+///
+/// An address that is already confirmed is left alone and gets no token, so nobody can change another person's digests by knowing their address.
+pub async fn register_email(
+    pool: &Pool<Postgres>,
+    email: &str,
+    preferences: &EmailPreferences,
+) -> Result<Option<String>> {
+    let email = email.parse::<Address>()?.to_string();
+    let mut token = [0; 32];
+    rand::thread_rng().fill_bytes(&mut token);
+
+    let token: Option<String> = sqlx::query_scalar(
+        r#"insert into email_subscriptions (email, token, "hour", weekday) values ($1, $2, $3, $4) on conflict (email) do update set "hour" = excluded."hour", weekday = excluded.weekday where email_subscriptions.confirmed_at is null returning token;"#,
+    )
+    .bind(email)
+    .bind(hex::encode(token))
+    .bind(i16::from(preferences.hour))
+    .bind(preferences.weekday.map(|weekday| weekday.num_days_from_monday() as i16))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// Confirms an email address may be sent digests. Returns whether the token was known.
+pub async fn confirm_email(pool: &Pool<Postgres>, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "update email_subscriptions set confirmed_at = coalesce(confirmed_at, now()) where token = $1;",
+    )
+    .bind(token)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Changes when digests are sent. Returns whether the token was known.
+pub async fn update_email_preferences(
+    pool: &Pool<Postgres>,
+    token: &str,
+    preferences: &EmailPreferences,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"update email_subscriptions set "hour" = $2, weekday = $3 where token = $1;"#,
+    )
+    .bind(token)
+    .bind(i16::from(preferences.hour))
+    .bind(
+        preferences
+            .weekday
+            .map(|weekday| weekday.num_days_from_monday() as i16),
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Stops sending digests to an email address. Returns whether the token was known.
+pub async fn unsubscribe_email(pool: &Pool<Postgres>, token: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("delete from email_subscriptions where token = $1;")
+        .bind(token)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sends the email asking the holder of an address to confirm they want digests.
+pub async fn send_confirmation(config: &Config, email: &str, token: &str) {
+    let body = format!(
+        "Someone, hopefully you, asked for Sky digests to be sent to this address.\n\nConfirm with this link:\n{}\n\nIf it was not you, ignore this email and nothing more will be sent.",
+        email_link(config, token, "confirm")
+    );
+
+    if let Err(error) = email::send(email, "Confirm your Sky digest", body, None).await {
+        tracing::error!("Failed to send an email confirmation: {error:?}");
+    }
+}
+
+/// A link to an action on an email subscription in the public API.
+pub fn email_link(config: &Config, token: &str, action: &str) -> String {
+    format!(
+        "{}/email-subscriptions/{token}/{action}",
+        config.email.public_url.trim_end_matches('/')
+    )
+}
+
+/// Periodically emails digests to the confirmed addresses that are due one.
+pub async fn send_email_digests(pool: Pool<Postgres>, config: Arc<Config>) {
+    loop {
+        sleep(EMAIL_DIGEST_INTERVAL).await;
+
+        if !is_leader() {
+            continue;
+        }
+
+        if let Err(error) = send_due(&pool, &config).await {
+            tracing::error!("Error sending email digests: {error:?}");
+        }
+    }
+}
+
+async fn send_due(pool: &Pool<Postgres>, config: &Config) -> Result<()> {
+    let now = Utc::now().with_timezone(&Los_Angeles);
+
+    let email_subscriptions: Vec<EmailSubscription> = sqlx::query_as(
+        r#"select email, token, "hour", weekday, confirmed_at, last_sent_at from email_subscriptions where confirmed_at is not null;"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Everyone due in the same run is sent the same digest, so each is built once.
+    let mut daily: Option<EmailDigest> = None;
+    let mut weekly: Option<EmailDigest> = None;
+
+    for email_subscription in email_subscriptions {
+        let Some(due_at) = email_subscription.due_at(now) else {
+            continue;
+        };
+
+        // Digests are not sent for before the address was confirmed, or so late they are out of date.
+        if email_subscription
+            .confirmed_at
+            .is_some_and(|confirmed_at| confirmed_at > due_at)
+            || email_subscription
+                .last_sent_at
+                .is_some_and(|last_sent_at| last_sent_at >= due_at)
+            || now - due_at > EMAIL_DIGEST_MAXIMUM_LATENESS
+        {
+            continue;
+        }
+
+        let digest = if email_subscription.weekday.is_some() {
+            match &mut weekly {
+                Some(digest) => &*digest,
+                None => weekly.insert(weekly_email_digest(pool, config, now).await?),
+            }
+        } else {
+            match &mut daily {
+                Some(digest) => &*digest,
+                None => daily.insert(daily_email_digest(pool, config, now).await?),
+            }
+        };
+
+        let unsubscribe_url = email_link(config, &email_subscription.token, "unsubscribe");
+
+        let body = format!(
+            "{}\n\n--\nYou asked for this digest. Unsubscribe:\n{unsubscribe_url}",
+            digest.body
+        );
+
+        match email::send(
+            &email_subscription.email,
+            &digest.subject,
+            body,
+            Some(&unsubscribe_url),
+        )
+        .await
+        {
+            Ok(()) => {
+                metrics::increment("email_digests_sent");
+
+                sqlx::query("update email_subscriptions set last_sent_at = $2 where email = $1;")
+                    .bind(&email_subscription.email)
+                    .bind(due_at)
+                    .execute(pool)
+                    .await?;
+            }
+            // The address does not exist or refuses the digest, so stop sending it.
+            Err(error) if error.is_permanent() => {
+                tracing::info!("Unsubscribing an email address that was rejected: {error:?}");
+                unsubscribe_email(pool, &email_subscription.token).await?;
+            }
+            Err(error) => {
+                metrics::increment("email_digests_failed");
+                tracing::warn!("Failed to send an email digest: {error:?}");
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use super::notification::{NotificationNotify, NotificationType};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+
+/// A new game version or patch notes entry.
+#[derive(Clone, Debug, Deserialize, FromRow, Serialize)]
+pub struct GameUpdate {
+    pub version: String,
+    /// Where the patch notes are published, if anywhere.
+    pub url: Option<String>,
+}
+
+/// Marks the game updates added in the last day that have yet to be notified of as notified, returning their notifications.
+///
+/// Claiming them in one statement means each is notified of once, even across a change of leader. Older ones are left alone so a backfill does not flood channels.
+pub async fn claim_game_updates(
+    pool: &Pool<Postgres>,
+    now: DateTime<Tz>,
+) -> Result<Vec<NotificationNotify>, sqlx::Error> {
+    let game_updates: Vec<GameUpdate> = sqlx::query_as(
+        r#"update game_updates set notified_at = now() where notified_at is null and created_at > now() - interval '1 day' returning version, url;"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(game_updates
+        .into_iter()
+        .map(|game_update| game_update_notification(now, game_update))
+        .collect())
+}
+
+/// Retrieves the most recent game update, for test sends.
+pub async fn get_latest_game_update(
+    pool: &Pool<Postgres>,
+) -> Result<Option<GameUpdate>, sqlx::Error> {
+    sqlx::query_as(r#"select version, url from game_updates order by created_at desc limit 1;"#)
+        .fetch_optional(pool)
+        .await
+}
+
+pub fn game_update_notification(now: DateTime<Tz>, game_update: GameUpdate) -> NotificationNotify {
+    NotificationNotify {
+        r#type: NotificationType::GameUpdate,
+        start_time: now.timestamp(),
+        end_time: None,
+        time_until_start: 0,
+        shard_eruption: None,
+        travelling_spirit_name: None,
+        special_visit_entities: None,
+        travelling_spirit_details: None,
+        weekly_digest: None,
+        occurrences: None,
+        recurring_event: None,
+        maintenance_end: None,
+        game_update: Some(game_update),
+        late: false,
+        queued_at: None,
+    }
+}
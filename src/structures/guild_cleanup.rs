@@ -0,0 +1,148 @@
+use super::{
+    audit_log::{audit, AuditEvent},
+    config::Config,
+    leader::is_leader,
+};
+use crate::utility::{alert::alert, discord_clients::DiscordClients};
+use serenity::{
+    http::{GuildPagination, Http},
+    model::id::GuildId,
+};
+use sqlx::{Pool, Postgres};
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+/// The most guilds Discord returns per page.
+const GUILDS_PAGE_SIZE: u64 = 200;
+
+/// Periodically deletes the subscriptions of guilds the bot was removed from, as they can never be sent again.
+pub async fn clean_up_guilds(
+    clients: Arc<DiscordClients>,
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+) {
+    let interval = Duration::from_secs((config.guild_cleanup_interval * 60).into());
+
+    loop {
+        sleep(interval).await;
+
+        if !is_leader() {
+            continue;
+        }
+
+        if let Err(error) = clean_up(&clients.primary(), &pool, &config).await {
+            tracing::error!("Error cleaning up guilds: {error:?}");
+        }
+    }
+}
+
+async fn clean_up(client: &Http, pool: &Pool<Postgres>, config: &Config) -> anyhow::Result<()> {
+    let guild_ids = current_guild_ids(client).await?;
+
+    let subscribed_guild_ids: Vec<i64> =
+        sqlx::query_scalar("select distinct guild_id from notifications;")
+            .fetch_all(pool)
+            .await?;
+
+    let departed_guild_ids = subscribed_guild_ids
+        .iter()
+        .copied()
+        .filter(|guild_id| !guild_ids.contains(&(*guild_id as u64)))
+        .collect::<Vec<_>>();
+
+    if departed_guild_ids.is_empty() {
+        return Ok(());
+    }
+
+    // Losing most guilds at once is far more likely to be a bad response than reality.
+    if departed_guild_ids.len() * 2 > subscribed_guild_ids.len() {
+        alert(
+            config.alert_webhook_url.as_deref(),
+            &format!(
+                "Skipped cleaning up guilds, as {} of {} subscribed guilds appear to have removed the bot.",
+                departed_guild_ids.len(),
+                subscribed_guild_ids.len()
+            ),
+        )
+        .await;
+
+        return Ok(());
+    }
+
+    let mut transaction = pool.begin().await?;
+
+    let deleted = sqlx::query("delete from notifications where guild_id = any($1);")
+        .bind(&departed_guild_ids)
+        .execute(&mut *transaction)
+        .await?
+        .rows_affected();
+
+    sqlx::query("delete from guild_pauses where guild_id = any($1);")
+        .bind(&departed_guild_ids)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query("delete from shard_eruption_deliveries where guild_id = any($1);")
+        .bind(&departed_guild_ids)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query("delete from notification_deliveries where guild_id = any($1);")
+        .bind(&departed_guild_ids)
+        .execute(&mut *transaction)
+        .await?;
+
+    sqlx::query("delete from shard_eruption_realm_roles where guild_id = any($1);")
+        .bind(&departed_guild_ids)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    for guild_id in &departed_guild_ids {
+        audit(
+            pool,
+            AuditEvent {
+                guild_id: Some(GuildId::new(*guild_id as u64)),
+                actor: "guild_cleanup",
+                action: "deleted",
+                reason: Some("The bot was removed from the server."),
+                ..Default::default()
+            },
+        )
+        .await;
+    }
+
+    tracing::info!(
+        "Deleted {deleted} subscriptions of {} guilds the bot was removed from.",
+        departed_guild_ids.len()
+    );
+
+    Ok(())
+}
+
+/// Pages through every guild the bot is in.
+async fn current_guild_ids(client: &Http) -> serenity::Result<HashSet<u64>> {
+    let mut guild_ids = HashSet::new();
+    let mut after = None;
+
+    loop {
+        let guilds = client
+            .get_guilds(after.map(GuildPagination::After), Some(GUILDS_PAGE_SIZE))
+            .await?;
+
+        let Some(last) = guilds.last() else {
+            break;
+        };
+
+        after = Some(last.id);
+        let full_page = guilds.len() as u64 == GUILDS_PAGE_SIZE;
+        guild_ids.extend(guilds.iter().map(|guild| guild.id.get()));
+
+        if !full_page {
+            break;
+        }
+    }
+
+    Ok(guild_ids)
+}
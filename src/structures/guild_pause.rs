@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serenity::model::id::GuildId;
+use sqlx::{Pool, Postgres};
+
+/// Pauses a guild's notifications until a time, replacing any existing pause.
+pub async fn pause_guild(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    paused_until: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "insert into guild_pauses (guild_id, paused_until) values ($1, $2) on conflict (guild_id) do update set paused_until = excluded.paused_until;",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(paused_until)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Resumes a guild's notifications. Returns whether they were paused.
+pub async fn resume_guild(pool: &Pool<Postgres>, guild_id: GuildId) -> Result<bool, sqlx::Error> {
+    let paused: Option<bool> = sqlx::query_scalar(
+        "delete from guild_pauses where guild_id = $1 returning paused_until > now();",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(paused.unwrap_or(false))
+}
+
+/// Returns when a guild's notifications resume, if they are paused.
+pub async fn get_pause(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    sqlx::query_scalar(
+        "select paused_until from guild_pauses where guild_id = $1 and paused_until > now();",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_optional(pool)
+    .await
+}
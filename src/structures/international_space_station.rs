@@ -0,0 +1,68 @@
+use crate::utility::database::with_retry;
+use chrono::{Datelike, Months, NaiveDate};
+use sqlx::{FromRow, Pool, Postgres};
+
+#[derive(FromRow)]
+pub struct InternationalSpaceStationDatePacket {
+    date: NaiveDate,
+}
+
+pub struct InternationalSpaceStationSchedule {
+    dates: Vec<NaiveDate>,
+}
+
+impl InternationalSpaceStationSchedule {
+    /// Builds a schedule for the month of `today` and the following month from days of the month.
+    pub fn from_days(today: NaiveDate, days: &[u32]) -> Self {
+        let first_day_of_month = today.with_day(1).unwrap();
+
+        let dates = [0, 1]
+            .into_iter()
+            .filter_map(|months| first_day_of_month.checked_add_months(Months::new(months)))
+            .flat_map(|month| days.iter().filter_map(move |&day| month.with_day(day)))
+            .collect();
+
+        Self { dates }
+    }
+
+    pub fn is_accessible(&self, date: NaiveDate) -> bool {
+        self.dates.contains(&date)
+    }
+}
+
+/// Retrieves the International Space Station schedule, falling back to the configured days of the month.
+pub async fn get_international_space_station_schedule(
+    pool: &Pool<Postgres>,
+    today: NaiveDate,
+    fallback_days: &[u32],
+) -> InternationalSpaceStationSchedule {
+    let rows: Result<Vec<InternationalSpaceStationDatePacket>, _> =
+        with_retry("International Space Station dates", || {
+            sqlx::query_as(
+                r#"select "date" from international_space_station_dates where "date" >= $1 and "date" <= $1 + 31 order by "date";"#,
+            )
+            .bind(today)
+            .fetch_all(pool)
+        })
+        .await;
+
+    match rows {
+        Ok(rows) if !rows.is_empty() => InternationalSpaceStationSchedule {
+            dates: rows.into_iter().map(|row| row.date).collect(),
+        },
+        Ok(_) => {
+            tracing::warn!(
+                "No International Space Station dates found. Falling back to the configured dates."
+            );
+
+            InternationalSpaceStationSchedule::from_days(today, fallback_days)
+        }
+        Err(error) => {
+            tracing::warn!(
+                "Failed to retrieve International Space Station dates. Falling back to the configured dates: {error:?}"
+            );
+
+            InternationalSpaceStationSchedule::from_days(today, fallback_days)
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::utility::{
+    constants::{LEADER_ELECTION_INTERVAL, LEADER_ELECTION_LOCK_KEY},
+    metrics,
+};
+use anyhow::Result;
+use sqlx::{Connection, PgConnection, Pool, Postgres};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::time::sleep;
+
+static LEADER: AtomicBool = AtomicBool::new(false);
+
+/// Whether this instance should queue notifications.
+pub fn is_leader() -> bool {
+    LEADER.load(Ordering::Relaxed)
+}
+
+/// Makes this instance the leader without an election, for single-instance deployments.
+pub fn assume_leadership() {
+    LEADER.store(true, Ordering::Relaxed);
+}
+
+/// Competes for leadership through a session-level advisory lock for as long as the process runs.
+///
+/// The lock lives as long as the connection, so if the leader dies Postgres releases it and a standby takes over on its next attempt. The connection is opened with the pool's options, so it follows rotated credentials.
+pub async fn elect(pool: Pool<Postgres>) {
+    loop {
+        if let Err(error) = campaign(&pool).await {
+            tracing::error!("Leader election failed: {error:?}");
+        }
+
+        if LEADER.swap(false, Ordering::Relaxed) {
+            tracing::warn!("Stepped down as leader.");
+            metrics::increment("leader_step_downs");
+        }
+
+        sleep(LEADER_ELECTION_INTERVAL).await;
+    }
+}
+
+async fn campaign(pool: &Pool<Postgres>) -> Result<()> {
+    let mut connection = PgConnection::connect_with(&pool.connect_options()).await?;
+
+    loop {
+        if is_leader() {
+            // Losing the connection loses the lock, so make sure it is still alive.
+            connection.ping().await?;
+        } else {
+            let acquired: bool = sqlx::query_scalar("select pg_try_advisory_lock($1);")
+                .bind(LEADER_ELECTION_LOCK_KEY)
+                .fetch_one(&mut connection)
+                .await?;
+
+            if acquired {
+                LEADER.store(true, Ordering::Relaxed);
+                tracing::info!("Elected leader.");
+                metrics::increment("leader_elections");
+            }
+        }
+
+        sleep(LEADER_ELECTION_INTERVAL).await;
+    }
+}
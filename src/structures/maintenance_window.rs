@@ -0,0 +1,84 @@
+use super::{
+    config::Maintenance,
+    notification::{NotificationNotify, NotificationType},
+};
+use crate::utility::database::with_retry;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::{FromRow, Pool, Postgres};
+
+#[derive(FromRow)]
+pub struct MaintenanceWindowPacket {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+/// A period the game's servers are down, so events cannot be attended.
+#[derive(Debug)]
+pub struct MaintenanceWindow {
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+}
+
+/// Retrieves the maintenance windows that have yet to end, starting within 2 days.
+pub async fn get_maintenance_windows(
+    pool: &Pool<Postgres>,
+    now: DateTime<Tz>,
+) -> Result<Vec<MaintenanceWindow>, sqlx::Error> {
+    let rows: Vec<MaintenanceWindowPacket> = with_retry("maintenance windows", || {
+        sqlx::query_as(
+            r#"select "start", "end" from maintenance_windows where "end" > $1 and "start" <= $1 + interval '2 days' order by "start";"#,
+        )
+        .bind(now.with_timezone(&Utc))
+        .fetch_all(pool)
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| MaintenanceWindow {
+            start: row.start.with_timezone(&now.timezone()),
+            end: row.end.with_timezone(&now.timezone()),
+        })
+        .collect())
+}
+
+/// Suppresses or annotates the notifications about to be queued whose occurrence starts during maintenance.
+///
+/// Schedules and summaries are not events to attend, so they are left alone.
+pub fn apply_maintenance_windows(
+    maintenance_windows: &[MaintenanceWindow],
+    notification_notifies: &mut Vec<NotificationNotify>,
+    maintenance: Maintenance,
+) {
+    let maintenance_end = |notification_notify: &NotificationNotify| {
+        if matches!(
+            notification_notify.r#type,
+            NotificationType::NoShardEruption
+                | NotificationType::WeeklyDigest
+                | NotificationType::AviarysFireworkFestivalSchedule
+                | NotificationType::ShardEruptionCorrection
+                | NotificationType::GameUpdate
+        ) {
+            return None;
+        }
+
+        maintenance_windows
+            .iter()
+            .find(|maintenance_window| {
+                (maintenance_window.start.timestamp()..maintenance_window.end.timestamp())
+                    .contains(&notification_notify.start_time)
+            })
+            .map(|maintenance_window| maintenance_window.end.timestamp())
+    };
+
+    match maintenance {
+        Maintenance::Suppress => notification_notifies
+            .retain(|notification_notify| maintenance_end(notification_notify).is_none()),
+        Maintenance::Annotate => {
+            for notification_notify in notification_notifies.iter_mut() {
+                notification_notify.maintenance_end = maintenance_end(notification_notify);
+            }
+        }
+    }
+}
@@ -1,2 +1,34 @@
+pub mod audit_log;
+pub mod catch_up;
+pub mod config;
+pub mod delivery;
+pub mod delivery_statistics;
+pub mod dispatched_notification;
+pub mod email_digest;
+pub mod email_subscription;
+pub mod game_update;
+pub mod guild_cleanup;
+pub mod guild_pause;
+pub mod international_space_station;
+pub mod leader;
+pub mod maintenance_window;
 pub mod notification;
+pub mod notification_thread;
+pub mod occurrence_override;
+pub mod outbox;
+pub mod permission_check;
+pub mod processed_minute;
+pub mod realm_role;
+pub mod recurring_event;
+pub mod reminder;
+pub mod schedule;
+pub mod shard_eruption;
+pub mod special_visit;
+pub mod subscriber_index;
+pub mod subscription;
+pub mod test_send;
 pub mod travelling_spirit;
+pub mod travelling_spirit_import;
+pub mod user_subscription;
+pub mod webhook;
+pub mod weekly_digest;
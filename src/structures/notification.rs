@@ -1,331 +1,1680 @@
-use crate::utility::wind_paths::ShardEruptionResponse;
-use anyhow::{anyhow, Result};
-use futures::{future::join_all, FutureExt};
+use super::{
+    audit_log::{audit, AuditEvent},
+    config::{Config, FlavorText, StaleNotifications},
+    delivery::{forget_old_deliveries, get_delivered_channels, record_channel_delivery},
+    delivery_statistics::{record_delivery, DeliveryCounts},
+    dispatched_notification::record_dispatch,
+    game_update::GameUpdate,
+    notification_thread::{event_key, get_thread, save_thread, thread_name},
+    permission_check::disable_channel,
+    realm_role::{get_realm_roles, RealmRole},
+    recurring_event::RecurringEventDetails,
+    reminder::reminder_custom_id,
+    shard_eruption::record_shard_eruption_delivery,
+    user_subscription::send_direct_messages,
+    webhook::send_webhooks,
+};
+use crate::utility::{
+    broker,
+    channel_cache::{self, channel_metadata, mark_joined, remember},
+    channel_circuit,
+    constants::{
+        GRANDMA_DURATION, LONG_LEAD_OFFSETS, POLLUTED_GEYSER_DURATION, SUBSCRIPTION_PAGE_SIZE,
+        TURTLE_DURATION,
+    },
+    database::{read_pool, with_retry},
+    discord_clients::DiscordClients,
+    dispatcher::{Dispatcher, Urgency},
+    emoji::is_valid_emoji,
+    event_stream,
+    flavor::{flavor, DAILY_RESET, GRANDMA, POLLUTED_GEYSER, TURTLE},
+    functions::{base36, format_list},
+    image_cache::cached_image,
+    latency, metrics,
+    permissions::{is_missing, MISSING_CHANNEL},
+    social,
+    spirits::SpiritDetails,
+    template::render_template,
+    wind_paths::ShardEruptionResponse,
+};
+use anyhow::{anyhow, bail, Result};
+use chrono::Utc;
+use futures::TryStreamExt;
 use serde::{Deserialize, Serialize};
 use serenity::{
-    all::{CreateAllowedMentions, CreateMessage, MessageFlags, Nonce},
+    all::{
+        AutoArchiveDuration, ButtonStyle, CreateActionRow, CreateAllowedMentions, CreateAttachment,
+        CreateButton, CreateEmbed, CreateMessage, CreateThread, EditThread, Message, MessageFlags,
+        Nonce,
+    },
     http::Http,
     model::id::{ChannelId, GuildId, RoleId},
 };
-use sqlx::{prelude::FromRow, Pool, Postgres};
-use std::{fmt, str::FromStr};
-
-#[derive(Clone, Deserialize, FromRow, Serialize)]
-pub struct NotificationPacket {
-    guild_id: String,
-    r#type: i16,
-    channel_id: String,
-    role_id: String,
-    offset: i16,
-    sendable: bool,
-}
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgRow, PgTypeInfo, PgValueRef},
+    prelude::FromRow,
+    Decode, Encode, Pool, Postgres, Row,
+};
+use std::{borrow::Cow, collections::HashSet, fmt, num::NonZeroU64, str::FromStr, sync::Arc};
+use tokio::time::timeout;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(async_graphql::Enum, Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[repr(i16)]
 pub enum NotificationType {
-    DailyReset,
-    EyeOfEden,
-    InternationalSpaceStation,
-    Dragon,
-    PollutedGeyser,
-    Grandma,
-    Turtle,
-    ShardEruptionRegular,
-    ShardEruptionStrong,
-    Aurora,
-    Passage,
-    AviarysFireworkFestival,
-    TravellingSpirit,
+    DailyReset = 0,
+    EyeOfEden = 1,
+    InternationalSpaceStation = 2,
+    #[allow(dead_code)]
+    Dragon = 3,
+    PollutedGeyser = 4,
+    Grandma = 5,
+    Turtle = 6,
+    ShardEruptionRegular = 7,
+    ShardEruptionStrong = 8,
+    Aurora = 9,
+    /// An activity repeating through the current season, as defined in the recurring events table.
+    #[serde(alias = "Passage")]
+    SeasonalActivity = 10,
+    AviarysFireworkFestival = 11,
+    TravellingSpirit = 12,
+    SpecialVisit = 13,
+    ShardEruptionEnd = 14,
+    NoShardEruption = 15,
+    WeeklyDigest = 16,
+    AviarysFireworkFestivalSchedule = 17,
+    /// Bundles the Polluted Geyser, Grandma, and Turtle. Never scheduled itself.
+    WaxEvents = 18,
+    /// Sent when the wind paths correct today's shard eruption, to guilds already notified of it. Never scheduled.
+    ShardEruptionCorrection = 19,
+    /// Sent when the main application records a new game version or patch notes. Never scheduled.
+    GameUpdate = 20,
+    /// Special shard eruptions only, such as memory shards, alongside the regular or strong notification.
+    ShardEruptionVariant = 21,
+}
+
+impl NotificationType {
+    pub const ALL: [NotificationType; 22] = [
+        NotificationType::DailyReset,
+        NotificationType::EyeOfEden,
+        NotificationType::InternationalSpaceStation,
+        NotificationType::Dragon,
+        NotificationType::PollutedGeyser,
+        NotificationType::Grandma,
+        NotificationType::Turtle,
+        NotificationType::ShardEruptionRegular,
+        NotificationType::ShardEruptionStrong,
+        NotificationType::Aurora,
+        NotificationType::SeasonalActivity,
+        NotificationType::AviarysFireworkFestival,
+        NotificationType::TravellingSpirit,
+        NotificationType::SpecialVisit,
+        NotificationType::ShardEruptionEnd,
+        NotificationType::NoShardEruption,
+        NotificationType::WeeklyDigest,
+        NotificationType::AviarysFireworkFestivalSchedule,
+        NotificationType::WaxEvents,
+        NotificationType::ShardEruptionCorrection,
+        NotificationType::GameUpdate,
+        NotificationType::ShardEruptionVariant,
+    ];
 }
 
 impl fmt::Display for NotificationType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            NotificationType::DailyReset => write!(f, "0"),
-            NotificationType::EyeOfEden => write!(f, "1"),
-            NotificationType::InternationalSpaceStation => write!(f, "2"),
-            NotificationType::Dragon => write!(f, "3"),
-            NotificationType::PollutedGeyser => write!(f, "4"),
-            NotificationType::Grandma => write!(f, "5"),
-            NotificationType::Turtle => write!(f, "6"),
-            NotificationType::ShardEruptionRegular => write!(f, "7"),
-            NotificationType::ShardEruptionStrong => write!(f, "8"),
-            NotificationType::Aurora => write!(f, "9"),
-            NotificationType::Passage => write!(f, "10"),
-            NotificationType::AviarysFireworkFestival => write!(f, "11"),
-            NotificationType::TravellingSpirit => write!(f, "12"),
-        }
+        write!(f, "{}", *self as i16)
     }
 }
 
-pub struct NotificationNotify {
-    pub r#type: NotificationType,
-    pub start_time: i64,
-    pub end_time: Option<i64>,
-    pub time_until_start: u32,
-    pub shard_eruption: Option<ShardEruptionResponse>,
-    pub travelling_spirit_name: Option<String>,
+impl TryFrom<i16> for NotificationType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i16) -> Result<Self> {
+        NotificationType::ALL
+            .into_iter()
+            .find(|r#type| *r#type as i16 == value)
+            .ok_or_else(|| anyhow!("Unknown notification type {value}."))
+    }
 }
 
-#[derive(Debug)]
-pub struct Notification {
-    guild_id: GuildId,
-    r#type: i16,
-    pub channel_id: ChannelId,
-    pub role_id: RoleId,
-    offset: i16,
-    sendable: bool,
+impl FromStr for NotificationType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value
+            .parse::<i16>()
+            .map_err(|_| anyhow!("Invalid notification type {value}."))?;
+
+        NotificationType::try_from(value)
+    }
 }
 
-impl From<NotificationPacket> for Notification {
-    fn from(packet: NotificationPacket) -> Self {
-        Self {
-            guild_id: GuildId::from_str(&packet.guild_id).expect("Invalid guild id."),
-            r#type: packet.r#type,
-            channel_id: ChannelId::from_str(&packet.channel_id).expect("Invalid channel id."),
-            role_id: RoleId::from_str(&packet.role_id).expect("Invalid role id."),
-            offset: packet.offset,
-            sendable: packet.sendable,
+impl sqlx::Type<Postgres> for NotificationType {
+    fn type_info() -> PgTypeInfo {
+        <i16 as sqlx::Type<Postgres>>::type_info()
+    }
+}
+
+impl<'q> Encode<'q, Postgres> for NotificationType {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        <i16 as Encode<'q, Postgres>>::encode_by_ref(&(*self as i16), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for NotificationType {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let value = <i16 as Decode<'r, Postgres>>::decode(value)?;
+        Ok(NotificationType::try_from(value)?)
+    }
+}
+
+impl NotificationType {
+    /// A human-readable name, as shown in slash commands.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationType::DailyReset => "Daily reset",
+            NotificationType::EyeOfEden => "Eye of Eden",
+            NotificationType::InternationalSpaceStation => "International Space Station",
+            NotificationType::Dragon => "Dragon",
+            NotificationType::PollutedGeyser => "Polluted geyser",
+            NotificationType::Grandma => "Grandma",
+            NotificationType::Turtle => "Turtle",
+            NotificationType::ShardEruptionRegular => "Regular shard eruption",
+            NotificationType::ShardEruptionStrong => "Strong shard eruption",
+            NotificationType::Aurora => "AURORA concert",
+            NotificationType::SeasonalActivity => "Seasonal activities",
+            NotificationType::AviarysFireworkFestival => "Aviary's Firework Festival",
+            NotificationType::TravellingSpirit => "Travelling spirit",
+            NotificationType::SpecialVisit => "Special visit",
+            NotificationType::ShardEruptionEnd => "Shard eruption end",
+            NotificationType::NoShardEruption => "No shard eruption",
+            NotificationType::WeeklyDigest => "Weekly digest",
+            NotificationType::AviarysFireworkFestivalSchedule => {
+                "Aviary's Firework Festival schedule"
+            }
+            NotificationType::WaxEvents => "Wax events",
+            NotificationType::ShardEruptionCorrection => "Shard eruption correction",
+            NotificationType::GameUpdate => "Game update",
+            NotificationType::ShardEruptionVariant => "Special shard eruption",
+        }
+    }
+
+    /// Whether players may subscribe to this type in their direct messages.
+    ///
+    /// Limited to what players follow individually, so frequent schedules do not flood direct messages.
+    pub fn is_direct_messageable(&self) -> bool {
+        matches!(
+            self,
+            NotificationType::ShardEruptionRegular
+                | NotificationType::ShardEruptionStrong
+                | NotificationType::ShardEruptionVariant
+                | NotificationType::ShardEruptionEnd
+                | NotificationType::TravellingSpirit
+        )
+    }
+
+    /// How urgently notifications of this type should be dispatched. Higher is more urgent.
+    pub fn priority(&self) -> u8 {
+        match self {
+            NotificationType::ShardEruptionRegular
+            | NotificationType::ShardEruptionStrong
+            | NotificationType::ShardEruptionVariant => 3,
+            NotificationType::TravellingSpirit
+            | NotificationType::SpecialVisit
+            | NotificationType::ShardEruptionEnd
+            | NotificationType::ShardEruptionCorrection => 2,
+            NotificationType::DailyReset
+            | NotificationType::EyeOfEden
+            | NotificationType::InternationalSpaceStation
+            | NotificationType::NoShardEruption
+            | NotificationType::GameUpdate => 1,
+            NotificationType::Dragon
+            | NotificationType::PollutedGeyser
+            | NotificationType::Grandma
+            | NotificationType::Turtle
+            | NotificationType::Aurora
+            | NotificationType::SeasonalActivity
+            | NotificationType::AviarysFireworkFestival
+            | NotificationType::WeeklyDigest
+            | NotificationType::AviarysFireworkFestivalSchedule
+            | NotificationType::WaxEvents => 0,
+        }
+    }
+
+    /// How many minutes an occurrence lasts, for types known to last a fixed time.
+    pub fn duration(&self) -> Option<u32> {
+        match self {
+            NotificationType::PollutedGeyser => Some(POLLUTED_GEYSER_DURATION),
+            NotificationType::Grandma => Some(GRANDMA_DURATION),
+            NotificationType::Turtle => Some(TURTLE_DURATION),
+            _ => None,
+        }
+    }
+
+    /// When an occurrence starting at the given time ends, for types known to last a fixed time.
+    pub fn end_time(&self, start_time: i64) -> Option<i64> {
+        self.duration()
+            .map(|duration| start_time + i64::from(duration) * 60)
+    }
+
+    /// The lead time used unless one is configured. Types with none are only sent as they happen.
+    pub fn default_lead_time(&self) -> u32 {
+        match self {
+            NotificationType::DailyReset => 15,
+            NotificationType::EyeOfEden => 24,
+            NotificationType::InternationalSpaceStation => 15,
+            NotificationType::Dragon => 10,
+            NotificationType::PollutedGeyser => 10,
+            NotificationType::Grandma => 10,
+            NotificationType::Turtle => 10,
+            NotificationType::ShardEruptionRegular => 10,
+            NotificationType::ShardEruptionStrong => 10,
+            NotificationType::Aurora => 15,
+            NotificationType::SeasonalActivity => 15,
+            NotificationType::AviarysFireworkFestival => 15,
+            NotificationType::TravellingSpirit => 15,
+            NotificationType::SpecialVisit => 15,
+            NotificationType::ShardEruptionEnd => 10,
+            NotificationType::NoShardEruption => 0,
+            NotificationType::WeeklyDigest => 0,
+            NotificationType::AviarysFireworkFestivalSchedule => 0,
+            NotificationType::WaxEvents => 10,
+            NotificationType::ShardEruptionCorrection => 0,
+            NotificationType::GameUpdate => 0,
+            NotificationType::ShardEruptionVariant => 10,
+        }
+    }
+
+    /// The minutes before an occurrence, beyond the lead time, that notifications may also be sent.
+    pub fn long_lead_offsets(&self) -> &'static [u32] {
+        match self {
+            NotificationType::TravellingSpirit | NotificationType::SpecialVisit => {
+                &LONG_LEAD_OFFSETS
+            }
+            _ => &[],
+        }
+    }
+
+    /// Whether notifications may be sent this many minutes before an occurrence.
+    pub fn allows_offset(&self, config: &Config, offset: i16) -> bool {
+        u32::try_from(offset).is_ok_and(|offset| {
+            offset <= config.lead_time(*self) || self.long_lead_offsets().contains(&offset)
+        })
+    }
+
+    /// Explains which offsets are allowed, for when one is not.
+    pub fn describe_offsets(&self, config: &Config) -> String {
+        let mut description = format!(
+            "{} notifications may be sent at most {} minutes in advance",
+            self.name(),
+            config.lead_time(*self)
+        );
+
+        if !self.long_lead_offsets().is_empty() {
+            description.push_str(&format!(
+                ", or exactly {} minutes in advance",
+                self.long_lead_offsets()
+                    .iter()
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        description + "."
+    }
+
+    /// The virtual type whose subscriptions also receive notifications of this type, if any.
+    pub fn bundle(&self) -> Option<NotificationType> {
+        match self {
+            NotificationType::PollutedGeyser
+            | NotificationType::Grandma
+            | NotificationType::Turtle => Some(NotificationType::WaxEvents),
+            _ => None,
         }
     }
 }
 
-impl Notification {
-    pub async fn send(
-        &self,
-        client: &Http,
-        notification_notify: &NotificationNotify,
-    ) -> Result<()> {
-        let r#type = &notification_notify.r#type;
+impl NotificationNotify {
+    /// The dispatch priority. Occurrences starting now outrank lead-time notifications of the same type.
+    pub fn priority(&self) -> u8 {
+        self.r#type.priority() * 2 + u8::from(self.time_until_start == 0)
+    }
 
-        let suffix = match r#type {
+    /// The message describing the occurrence, without any mention.
+    pub fn text(&self, flavor_text: FlavorText) -> String {
+        match self.r#type {
             NotificationType::DailyReset => {
-                if notification_notify.time_until_start == 0 {
-                    "It's a new day. Time to forge candles again!".to_string()
+                if self.time_until_start == 0 {
+                    flavor(&DAILY_RESET, flavor_text, self.start_time).to_string()
                 } else {
-                    format!(
-                        "A new day will begin in <t:{}:R>!",
-                        notification_notify.start_time
-                    )
+                    format!("A new day will begin in <t:{}:R>!", self.start_time)
                 }
             }
             NotificationType::EyeOfEden => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     "Sky kids may save statues in the Eye of Eden again!".to_string()
                 } else {
                     format!(
                         "Statues in the Eye of Eden will reset <t:{}:R>!",
-                        notification_notify.start_time
+                        self.start_time
                     )
                 }
             }
             NotificationType::InternationalSpaceStation => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     "The International Space Station is accessible!".to_string()
                 } else {
                     format!(
                         "The International Space Station will be accessible <t:{}:R>!",
-                        notification_notify.start_time
+                        self.start_time
                     )
                 }
             }
             NotificationType::Dragon => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     "The dragon is appearing now!".to_string()
                 } else {
-                    format!(
-                        "The dragon will appear <t:{}:R>!",
-                        notification_notify.start_time
-                    )
+                    format!("The dragon will appear <t:{}:R>!", self.start_time)
                 }
             }
             NotificationType::PollutedGeyser => {
-                if notification_notify.time_until_start == 0 {
-                    "The Polluted Geyser is starting to erupt!".to_string()
+                let text = if self.time_until_start == 0 {
+                    flavor(&POLLUTED_GEYSER, flavor_text, self.start_time).to_string()
                 } else {
-                    format!(
-                        "The Polluted Geyser will erupt <t:{}:R>!",
-                        notification_notify.start_time
-                    )
-                }
+                    format!("The Polluted Geyser will erupt <t:{}:R>!", self.start_time)
+                };
+
+                self.with_end(text)
             }
             NotificationType::Grandma => {
-                if notification_notify.time_until_start == 0 {
-                    "Grandma has begun sharing her light!".to_string()
+                let text = if self.time_until_start == 0 {
+                    flavor(&GRANDMA, flavor_text, self.start_time).to_string()
                 } else {
-                    format!(
-                        "Grandma will share her light <t:{}:R>!",
-                        notification_notify.start_time
-                    )
-                }
+                    format!("Grandma will share her light <t:{}:R>!", self.start_time)
+                };
+
+                self.with_end(text)
             }
             NotificationType::Turtle => {
-                if notification_notify.time_until_start == 0 {
-                    "The turtle needs cleansing of darkness now!".to_string()
+                let text = if self.time_until_start == 0 {
+                    flavor(&TURTLE, flavor_text, self.start_time).to_string()
                 } else {
                     format!(
                         "The turtle will need cleansing of darkness <t:{}:R>!",
-                        notification_notify.start_time
+                        self.start_time
                     )
-                }
+                };
+
+                self.with_end(text)
             }
             NotificationType::ShardEruptionRegular => {
-                let shard_eruption = notification_notify
+                let shard_eruption = self
                     .shard_eruption
                     .as_ref()
                     .expect("A shard eruption must have data.");
 
-                let end_time = notification_notify
+                let end_time = self
                     .end_time
                     .expect("A shard eruption must have an end time.");
 
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     format!(
-                        "A regular shard eruption is landing in the [{} ({})]({}) and clears up <t:{}:R>!",
+                        "A regular shard eruption is landing in the [{} ({})]({}) and clears up <t:{}:R>! It rewards {}.{}",
                         shard_eruption.realm,
                         shard_eruption.sky_map,
                         shard_eruption.url,
-                        end_time
+                        end_time,
+                        shard_eruption.reward_text(),
+                        shard_eruption.variant_note()
                     )
                 } else {
                     format!(
-                        "A regular shard eruption lands in the [{} ({})]({}) <t:{}:R> and clears up <t:{}:R>!",
+                        "A regular shard eruption lands in the [{} ({})]({}) <t:{}:R> and clears up <t:{}:R>! It rewards {}.{}",
                         shard_eruption.realm,
                         shard_eruption.sky_map,
                         shard_eruption.url,
-                        notification_notify.start_time,
-                        end_time
+                        self.start_time,
+                        end_time,
+                        shard_eruption.reward_text(),
+                        shard_eruption.variant_note()
                     )
                 }
             }
             NotificationType::ShardEruptionStrong => {
-                let shard_eruption = notification_notify
+                let shard_eruption = self
+                    .shard_eruption
+                    .as_ref()
+                    .expect("A shard eruption must have data.");
+
+                let end_time = self
+                    .end_time
+                    .expect("A shard eruption must have an end time.");
+
+                if self.time_until_start == 0 {
+                    format!(
+                        "A strong shard eruption is landing in the [{} ({})]({}) and clears up <t:{}:R>! It rewards {}.{}",
+                        shard_eruption.realm,
+                        shard_eruption.sky_map,
+                        shard_eruption.url,
+                        end_time,
+                        shard_eruption.reward_text(),
+                        shard_eruption.variant_note()
+                    )
+                } else {
+                    format!(
+                        "A strong shard eruption lands in the [{} ({})]({}) <t:{}:R> and clears up <t:{}:R>! It rewards {}.{}",
+                        shard_eruption.realm,
+                        shard_eruption.sky_map,
+                        shard_eruption.url,
+                        self.start_time,
+                        end_time,
+                        shard_eruption.reward_text(),
+                        shard_eruption.variant_note()
+                    )
+                }
+            }
+            NotificationType::ShardEruptionVariant => {
+                let shard_eruption = self
                     .shard_eruption
                     .as_ref()
                     .expect("A shard eruption must have data.");
 
-                let end_time = notification_notify
+                let variant = shard_eruption
+                    .variant
+                    .as_ref()
+                    .expect("A special shard eruption must have a variant.");
+
+                let end_time = self
                     .end_time
                     .expect("A shard eruption must have an end time.");
 
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     format!(
-                        "A strong shard eruption is landing in the [{} ({})]({}) and clears up <t:{}:R>!",
+                        "A {variant} shard eruption is landing in the [{} ({})]({}) and clears up <t:{}:R>! It rewards {}.",
                         shard_eruption.realm,
                         shard_eruption.sky_map,
                         shard_eruption.url,
-                        end_time
+                        end_time,
+                        shard_eruption.reward_text()
                     )
                 } else {
                     format!(
-						"A strong shard eruption lands in the [{} ({})]({}) <t:{}:R> and clears up <t:{}:R>!",
-						shard_eruption.realm,
-						shard_eruption.sky_map,
-						shard_eruption.url,
-						notification_notify.start_time,
-						end_time
-					)
+                        "A {variant} shard eruption lands in the [{} ({})]({}) <t:{}:R> and clears up <t:{}:R>! It rewards {}.",
+                        shard_eruption.realm,
+                        shard_eruption.sky_map,
+                        shard_eruption.url,
+                        self.start_time,
+                        end_time,
+                        shard_eruption.reward_text()
+                    )
+                }
+            }
+            NotificationType::ShardEruptionEnd => {
+                let shard_eruption = self
+                    .shard_eruption
+                    .as_ref()
+                    .expect("A shard eruption must have data.");
+
+                if self.time_until_start == 0 {
+                    format!(
+                        "The shard eruption in the [{} ({})]({}) has cleared up!",
+                        shard_eruption.realm, shard_eruption.sky_map, shard_eruption.url
+                    )
+                } else {
+                    format!(
+                        "The shard eruption in the [{} ({})]({}) clears up <t:{}:R>! Last chance to collect {}!",
+                        shard_eruption.realm,
+                        shard_eruption.sky_map,
+                        shard_eruption.url,
+                        self.start_time,
+                        shard_eruption.reward_text()
+                    )
+                }
+            }
+            NotificationType::NoShardEruption => {
+                "There are no shard eruptions today. Enjoy the calm skies!".to_string()
+            }
+            NotificationType::ShardEruptionCorrection => match &self.shard_eruption {
+                Some(shard_eruption) => {
+                    let landings = shard_eruption
+                        .timestamps
+                        .iter()
+                        .map(|dates| format!("<t:{}:t>", dates.start.timestamp()))
+                        .collect::<Vec<_>>();
+
+                    format!(
+                        "Correction: today's {} shard eruption is in the [{} ({})]({}), landing at {}. It rewards {}.",
+                        if shard_eruption.strong {
+                            "strong"
+                        } else {
+                            "regular"
+                        },
+                        shard_eruption.realm,
+                        shard_eruption.sky_map,
+                        shard_eruption.url,
+                        format_list(&landings),
+                        shard_eruption.reward_text()
+                    )
+                }
+                None => "Correction: there are no shard eruptions today after all.".to_string(),
+            },
+            NotificationType::GameUpdate => {
+                let game_update = self
+                    .game_update
+                    .as_ref()
+                    .expect("A game update must have a version.");
+
+                match &game_update.url {
+                    Some(url) => format!(
+                        "Sky {} is out! [Read the patch notes.]({url})",
+                        game_update.version
+                    ),
+                    None => format!("Sky {} is out!", game_update.version),
+                }
+            }
+            NotificationType::WeeklyDigest => self
+                .weekly_digest
+                .clone()
+                .expect("A weekly digest must have content."),
+            NotificationType::AviarysFireworkFestivalSchedule => {
+                let occurrences = self
+                    .occurrences
+                    .as_ref()
+                    .expect("A festival schedule must have occurrences.")
+                    .iter()
+                    .map(|occurrence| format!("<t:{occurrence}:t>"))
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "Aviary's Firework Festival is today! Fireworks begin at {}.",
+                    format_list(&occurrences)
+                )
+            }
+            // Only subscribed to, as the bundled types are scheduled individually.
+            NotificationType::WaxEvents => {
+                if self.time_until_start == 0 {
+                    "A wax event is starting!".to_string()
+                } else {
+                    format!("A wax event will start <t:{}:R>!", self.start_time)
                 }
             }
             NotificationType::Aurora => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     "The AURORA concert is starting! Take your friends!".to_string()
                 } else {
                     format!(
                         "The AURORA concert will start <t:{}:R>! Take your friends!",
-                        notification_notify.start_time
+                        self.start_time
                     )
                 }
             }
-            NotificationType::Passage => {
-                if notification_notify.time_until_start == 0 {
-                    "The Season of Passage quests are starting!".to_string()
+            NotificationType::SeasonalActivity => {
+                let recurring_event = self
+                    .recurring_event
+                    .as_ref()
+                    .expect("A seasonal activity must have details.");
+
+                if self.time_until_start == 0 {
+                    recurring_event.message.clone()
                 } else {
                     format!(
-                        "The Season of Passage quests will start <t:{}:R>!",
-                        notification_notify.start_time
+                        "{} will start <t:{}:R>!",
+                        recurring_event.name, self.start_time
                     )
                 }
             }
             NotificationType::AviarysFireworkFestival => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     "Aviary's Firework Festival is beginning!".to_string()
                 } else {
                     format!(
                         "Aviary's Firework Festival will begin <t:{}:R>!",
-                        notification_notify.start_time
+                        self.start_time
                     )
                 }
             }
             NotificationType::TravellingSpirit => {
-                if notification_notify.time_until_start == 0 {
+                if self.time_until_start == 0 {
                     format!(
                         "{} has arrived!",
-                        notification_notify
-                            .travelling_spirit_name
+                        self.travelling_spirit_name
                             .as_ref()
                             .expect("A travelling spirit must have a name.")
                     )
                 } else {
                     format!(
                         "{} will arrive <t:{}:R>!",
-                        notification_notify
-                            .travelling_spirit_name
+                        self.travelling_spirit_name
                             .as_ref()
                             .expect("A travelling spirit must have a name."),
-                        notification_notify.start_time
+                        self.start_time
+                    )
+                }
+            }
+            NotificationType::SpecialVisit => {
+                let entities = format_list(
+                    self.special_visit_entities
+                        .as_ref()
+                        .expect("A special visit must have entities."),
+                );
+
+                let end_time = self
+                    .end_time
+                    .expect("A special visit must have an end time.");
+
+                if self.time_until_start == 0 {
+                    format!(
+                        "A special visit with {} has begun! It ends <t:{}:R>.",
+                        entities, end_time
+                    )
+                } else {
+                    format!(
+                        "A special visit with {} will begin <t:{}:R> and end <t:{}:R>!",
+                        entities, self.start_time, end_time
                     )
                 }
             }
+        }
+    }
+
+    /// Says when the occurrence ends after the text, if that is known.
+    fn with_end(&self, text: String) -> String {
+        match self.end_time {
+            Some(end_time) => format!("{text} It ends <t:{end_time}:R>."),
+            None => text,
+        }
+    }
+
+    /// Notes the game maintenance the occurrence starts during, if any, and when it started if this is late, after the text.
+    pub fn with_notes(&self, text: String) -> String {
+        text + &self.notes()
+    }
+
+    /// The notes [`Self::with_notes`] adds, each starting with a space.
+    fn notes(&self) -> String {
+        let mut notes = String::new();
+
+        if let Some(maintenance_end) = self.maintenance_end {
+            notes += &format!(" The game is under maintenance until <t:{maintenance_end}:t>.");
+        }
+
+        if self.late {
+            notes += &format!(" This started <t:{}:R>.", self.start_time);
+        }
+
+        notes
+    }
+
+    /// Whether the notification has waited longer than `maximum_age` seconds since it was queued. 0 never considers it stale.
+    pub fn is_stale(&self, maximum_age: u32) -> bool {
+        maximum_age > 0
+            && self.queued_at.is_some_and(|queued_at| {
+                Utc::now().timestamp_millis() - queued_at > i64::from(maximum_age) * 1000
+            })
+    }
+
+    /// When the notification was due, as a Unix timestamp.
+    pub fn due_time(&self) -> i64 {
+        self.start_time - i64::from(self.time_until_start) * 60
+    }
+
+    /// How soon sends must happen. Shard eruptions lose their value if delayed at all, so are never spread out.
+    pub fn urgency(&self) -> Urgency {
+        if matches!(
+            self.r#type,
+            NotificationType::ShardEruptionRegular
+                | NotificationType::ShardEruptionStrong
+                | NotificationType::ShardEruptionVariant
+        ) {
+            Urgency::Immediate
+        } else if self.time_until_start == 0 {
+            Urgency::Starting
+        } else {
+            Urgency::Routine
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct NotificationNotify {
+    pub r#type: NotificationType,
+    pub start_time: i64,
+    pub end_time: Option<i64>,
+    pub time_until_start: u32,
+    /// Shared with the schedule and every other notification of the day's shard eruption, as it is large and sent to many guilds at once.
+    pub shard_eruption: Option<Arc<ShardEruptionResponse>>,
+    pub travelling_spirit_name: Option<String>,
+    pub special_visit_entities: Option<Vec<String>>,
+    /// Shared with the schedule, which notifies of the spirit at every lead-time minute.
+    pub travelling_spirit_details: Option<Arc<SpiritDetails>>,
+    pub weekly_digest: Option<String>,
+    /// Every start time of a recurring event, for notifications that list them at once.
+    pub occurrences: Option<Vec<i64>>,
+    pub recurring_event: Option<RecurringEventDetails>,
+    /// When the game maintenance the occurrence starts during ends, if it is to be noted.
+    pub maintenance_end: Option<i64>,
+    pub game_update: Option<GameUpdate>,
+    /// Whether the occurrence started while the scheduler was down and is being caught up on.
+    #[serde(default)]
+    pub late: bool,
+    /// When the notification was first queued, as a Unix timestamp in milliseconds. It is kept through the outbox and the stream, so time spent in them counts towards its age.
+    #[serde(default)]
+    pub queued_at: Option<i64>,
+}
+
+/// The parts of a notification's message that are the same for every guild, rendered once for an occurrence rather than for each of its sends.
+pub struct RenderedText {
+    /// The built-in text, absent when each send picks its own variant.
+    text: Option<String>,
+    notes: String,
+}
+
+impl RenderedText {
+    pub fn new(notification_notify: &NotificationNotify, flavor_text: FlavorText) -> Self {
+        Self {
+            text: (flavor_text != FlavorText::Random)
+                .then(|| notification_notify.text(flavor_text)),
+            notes: notification_notify.notes(),
+        }
+    }
+
+    /// The built-in text, rendered again only if each send picks its own variant.
+    fn text(
+        &self,
+        notification_notify: &NotificationNotify,
+        flavor_text: FlavorText,
+    ) -> Cow<'_, str> {
+        match &self.text {
+            Some(text) => Cow::Borrowed(text),
+            None => Cow::Owned(notification_notify.text(flavor_text)),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Notification {
+    guild_id: GuildId,
+    r#type: NotificationType,
+    pub channel_id: ChannelId,
+    /// The role to mention. Notifications without one are informational.
+    pub role_id: Option<RoleId>,
+    offset: i16,
+    sendable: bool,
+    rich: bool,
+    threaded: bool,
+    /// Whether to send with Discord's @silent flag.
+    silent: bool,
+    /// Replaces the built-in text.
+    template: Option<String>,
+    /// Prepended to the text, overriding the configured emoji for the type.
+    emoji: Option<String>,
+    /// The minimum number of minutes between the occurrences notified.
+    minimum_interval: Option<i32>,
+    /// Whether only occurrences starting on the hour are notified.
+    on_the_hour: bool,
+    /// The start time of the last occurrence notified, for throttling.
+    last_occurrence: Option<i64>,
+    /// The role for the realm of the shard eruption notified of, if the guild set one.
+    realm_role: Option<RealmRole>,
+}
+
+impl FromRow<'_, PgRow> for Notification {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            guild_id: snowflake(row, "guild_id")?,
+            r#type: row.try_get("type")?,
+            channel_id: snowflake(row, "channel_id")?,
+            role_id: optional_snowflake(row, "role_id")?,
+            offset: row.try_get("offset")?,
+            sendable: row.try_get("sendable")?,
+            rich: row.try_get("rich")?,
+            threaded: row.try_get("threaded")?,
+            silent: row.try_get("silent")?,
+            template: row.try_get("template")?,
+            emoji: row.try_get("emoji")?,
+            minimum_interval: row.try_get("minimum_interval")?,
+            on_the_hour: row.try_get("on_the_hour")?,
+            last_occurrence: row.try_get("last_occurrence")?,
+            realm_role: None,
+        })
+    }
+}
+
+/// Reads a bigint column as a Discord id, rejecting values that cannot be one.
+pub fn snowflake<T: From<NonZeroU64>>(row: &PgRow, column: &str) -> Result<T, sqlx::Error> {
+    let value: i64 = row.try_get(column)?;
+
+    u64::try_from(value)
+        .ok()
+        .and_then(NonZeroU64::new)
+        .map(T::from)
+        .ok_or_else(|| sqlx::Error::ColumnDecode {
+            index: column.to_string(),
+            source: format!("{value} is not a valid snowflake.").into(),
+        })
+}
+
+/// Reads a nullable bigint column as a Discord id.
+pub fn optional_snowflake<T: From<NonZeroU64>>(
+    row: &PgRow,
+    column: &str,
+) -> Result<Option<T>, sqlx::Error> {
+    let value: Option<i64> = row.try_get(column)?;
+    value.map(|_| snowflake(row, column)).transpose()
+}
+
+impl Notification {
+    /// The guild's subscription to a type, or the defaults if it has none, redirected to a channel for a test send.
+    ///
+    /// Test sends never mention the role or start a thread, and are sent silently.
+    pub async fn for_test(
+        pool: &Pool<Postgres>,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        r#type: NotificationType,
+    ) -> Result<Self> {
+        let row = sqlx::query(
+            r#"select * from notifications where guild_id = $1 and "type" = $2 limit 1;"#,
+        )
+        .bind(guild_id.get() as i64)
+        .bind(r#type)
+        .fetch_optional(pool)
+        .await?;
+
+        let notification = match row {
+            Some(row) => Notification::from_row(&row)?,
+            None => Self {
+                guild_id,
+                r#type,
+                channel_id,
+                role_id: None,
+                offset: 0,
+                sendable: true,
+                rich: false,
+                threaded: false,
+                silent: false,
+                template: None,
+                emoji: None,
+                minimum_interval: None,
+                on_the_hour: false,
+                last_occurrence: None,
+                realm_role: None,
+            },
         };
 
+        Ok(Self {
+            channel_id,
+            role_id: None,
+            threaded: false,
+            silent: true,
+            ..notification
+        })
+    }
+
+    /// Sends the notification, returning the message sent.
+    pub async fn send(
+        &self,
+        client: &Http,
+        pool: &Pool<Postgres>,
+        config: &Config,
+        notification_notify: &NotificationNotify,
+        rendered: &RenderedText,
+    ) -> Result<Message> {
+        let r#type = &notification_notify.r#type;
         let channel_id = self.channel_id;
-        let role_id = self.role_id;
+        let role_ids = self.mentioned_roles();
 
-        client
-            .send_message(
-                channel_id,
-                vec![],
-                &CreateMessage::new()
-                    .allowed_mentions(CreateAllowedMentions::new().roles(vec![role_id]))
-                    .content(format!("<@&{}> {}", role_id, suffix))
-                    .enforce_nonce(true)
-                    .flags(MessageFlags::SUPPRESS_EMBEDS)
-                    .nonce(Nonce::String(format!("{}-{}", r#type, channel_id,))),
+        // Failing to fetch the channel for any other reason is no reason to skip it, as the send itself may still succeed.
+        let metadata = match channel_metadata(client, channel_id).await {
+            Ok(metadata) => Some(metadata),
+            Err(error)
+                if error
+                    .downcast_ref::<serenity::Error>()
+                    .is_some_and(is_missing) =>
+            {
+                disable_channel(pool, self.guild_id, channel_id, MISSING_CHANNEL).await;
+                bail!(MISSING_CHANNEL);
+            }
+            Err(error) => {
+                tracing::warn!(%channel_id, "Failed to fetch a channel: {error:?}");
+                None
+            }
+        };
+
+        if let Some(metadata) = metadata {
+            if let Some(problem) = metadata.problem() {
+                disable_channel(pool, self.guild_id, channel_id, problem).await;
+                bail!(problem);
+            }
+
+            if metadata.archived {
+                let thread = channel_id
+                    .edit_thread(client, EditThread::new().archived(false))
+                    .await
+                    .map_err(|error| anyhow!(error))?;
+
+                remember(&thread);
+            }
+
+            // Sending only joins public threads, so the bot joins first to reach private ones too.
+            if metadata.is_thread() && !metadata.joined {
+                match channel_id.join_thread(client).await {
+                    Ok(()) => mark_joined(channel_id),
+                    Err(error) => {
+                        tracing::warn!(%channel_id, "Failed to join a thread: {error:?}");
+                    }
+                }
+            }
+        }
+
+        // Only the mentions, the emoji, and a template differ between guilds. The rest was rendered once for all of them.
+        let text = self.text(config, notification_notify, rendered);
+
+        let mentions = role_ids
+            .iter()
+            .map(|role_id| format!("<@&{role_id}> "))
+            .collect::<String>();
+
+        let content = match self.emoji(config, notification_notify) {
+            Some(emoji) => format!("{mentions}{emoji} {text}{}", rendered.notes),
+            None => format!("{mentions}{text}{}", rendered.notes),
+        };
+
+        let mut message = CreateMessage::new()
+            .allowed_mentions(CreateAllowedMentions::new().roles(role_ids))
+            .content(content)
+            .enforce_nonce(true)
+            .nonce(Nonce::String(self.nonce(notification_notify)));
+
+        // The button is answered by the interactions endpoint, so only offer it when that is running.
+        if config.interactions.address.is_some() && notification_notify.time_until_start > 0 {
+            message = message.components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                reminder_custom_id(*r#type, notification_notify.start_time),
+            )
+            .label("Remind me at start")
+            .style(ButtonStyle::Secondary)])]);
+        }
+
+        let mut files = vec![];
+        let mut embed = self.embed(notification_notify);
+
+        if let Some(shard_eruption) = self.shard_map(notification_notify) {
+            if let Some(image) = cached_image(&shard_eruption.url).await {
+                let filename = shard_map_filename(&shard_eruption.url);
+
+                embed = Some(
+                    CreateEmbed::new()
+                        .title(format!(
+                            "{} ({})",
+                            shard_eruption.realm, shard_eruption.sky_map
+                        ))
+                        .image(format!("attachment://{filename}")),
+                );
+
+                files.push(CreateAttachment::bytes(image.as_slice(), filename));
+            }
+        }
+
+        let mut flags = MessageFlags::empty();
+
+        if self.silent {
+            flags |= MessageFlags::SUPPRESS_NOTIFICATIONS;
+        }
+
+        match embed {
+            Some(embed) => message = message.embed(embed),
+            None => flags |= MessageFlags::SUPPRESS_EMBEDS,
+        }
+
+        if !flags.is_empty() {
+            message = message.flags(flags);
+        }
+
+        let event = event_key(notification_notify).filter(|_| {
+            self.threaded && metadata.is_none_or(|metadata| metadata.supports_threads())
+        });
+
+        let Some(event) = event else {
+            return client
+                .send_message(channel_id, files, &message)
+                .await
+                .map_err(|error| anyhow!(error));
+        };
+
+        // Follow-ups go in the event's thread. If it has gone, start a new one.
+        if let Some(thread_id) = get_thread(pool, channel_id, &event).await? {
+            match client
+                .send_message(thread_id, files.clone(), &message)
+                .await
+            {
+                Ok(sent) => return Ok(sent),
+                Err(error) => {
+                    tracing::warn!(%thread_id, "Failed to send to a thread: {error:?}");
+                }
+            }
+        }
+
+        let sent = client
+            .send_message(channel_id, files, &message)
+            .await
+            .map_err(|error| anyhow!(error))?;
+
+        let thread = channel_id
+            .create_thread_from_message(
+                client,
+                sent.id,
+                CreateThread::new(thread_name(notification_notify))
+                    .auto_archive_duration(AutoArchiveDuration::OneDay),
             )
             .await
             .map_err(|error| anyhow!(error))?;
 
+        save_thread(pool, channel_id, &event, thread.id).await?;
+        Ok(sent)
+    }
+
+    /// Sends the notification, giving up once its type's deadline passes so a hung request cannot hold up the occurrence's other sends.
+    async fn send_within_deadline(
+        &self,
+        client: &Http,
+        pool: &Pool<Postgres>,
+        config: &Config,
+        notification_notify: &NotificationNotify,
+        rendered: &RenderedText,
+    ) -> Result<Message> {
+        let deadline = config.send_timeout(
+            notification_notify.r#type,
+            notification_notify.time_until_start,
+        );
+
+        match timeout(
+            deadline,
+            self.send(client, pool, config, notification_notify, rendered),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => {
+                metrics::increment("send_timeouts");
+                bail!("The send timed out after {deadline:?}.");
+            }
+        }
+    }
+
+    /// Identifies the message for an occurrence to Discord, which discards a resend within a few minutes.
+    ///
+    /// Discord limits nonces to 25 characters, so each part is written in base 36.
+    fn nonce(&self, notification_notify: &NotificationNotify) -> String {
+        format!(
+            "{}-{}-{}-{}",
+            base36(notification_notify.r#type as u64),
+            base36(notification_notify.time_until_start.into()),
+            base36(notification_notify.start_time.max(0) as u64),
+            base36(self.channel_id.get())
+        )
+    }
+
+    /// The roles to mention: the subscription's, the realm's, or both.
+    fn mentioned_roles(&self) -> Vec<RoleId> {
+        match &self.realm_role {
+            Some(realm_role) if realm_role.replace => vec![realm_role.role_id],
+            Some(realm_role) if self.role_id != Some(realm_role.role_id) => self
+                .role_id
+                .into_iter()
+                .chain([realm_role.role_id])
+                .collect(),
+            _ => self.role_id.into_iter().collect(),
+        }
+    }
+
+    /// Whether the guild asked not to be notified of this occurrence.
+    fn is_throttled(&self, notification_notify: &NotificationNotify) -> bool {
+        let start_time = notification_notify.start_time;
+
+        // Los Angeles is a whole number of hours from UTC, so the minute and second are the same in both, and an occurrence on the hour there is on the hour in UTC.
+        if self.on_the_hour && start_time % 3600 != 0 {
+            return true;
+        }
+
+        match (self.minimum_interval, self.last_occurrence) {
+            (Some(minimum_interval), Some(last_occurrence)) => {
+                start_time > last_occurrence
+                    && start_time - last_occurrence < i64::from(minimum_interval) * 60
+            }
+            _ => false,
+        }
+    }
+
+    /// Remembers the occurrence notified, so the next ones can be throttled.
+    async fn record_occurrence(
+        &self,
+        pool: &Pool<Postgres>,
+        notification_notify: &NotificationNotify,
+    ) -> Result<()> {
+        if self.minimum_interval.is_none() {
+            return Ok(());
+        }
+
+        sqlx::query(
+            r#"update notifications set last_occurrence = $1 where guild_id = $2 and "type" = $3;"#,
+        )
+        .bind(notification_notify.start_time)
+        .bind(self.guild_id.get() as i64)
+        .bind(self.r#type)
+        .execute(pool)
+        .await?;
+
         Ok(())
     }
+
+    /// Renders the guild's template, falling back to the built-in text if it cannot be rendered.
+    fn text<'a>(
+        &self,
+        config: &Config,
+        notification_notify: &NotificationNotify,
+        rendered: &'a RenderedText,
+    ) -> Cow<'a, str> {
+        let Some(template) = &self.template else {
+            let text = rendered.text(notification_notify, config.flavor_text);
+
+            // A bundle covers several events, so say which one this is and when.
+            if self.r#type == NotificationType::WaxEvents {
+                return Cow::Owned(format!(
+                    "Wax event at <t:{}:t>: {text}",
+                    notification_notify.start_time
+                ));
+            }
+
+            return text;
+        };
+
+        match render_template(template, notification_notify) {
+            Ok(text) => Cow::Owned(text),
+            Err(error) => {
+                tracing::warn!(
+                    guild_id = %self.guild_id,
+                    "Falling back to the built-in text: {error:?}"
+                );
+
+                rendered.text(notification_notify, config.flavor_text)
+            }
+        }
+    }
+
+    /// Returns the guild's emoji for the notification, or the configured one for its type.
+    fn emoji<'a>(
+        &'a self,
+        config: &'a Config,
+        notification_notify: &NotificationNotify,
+    ) -> Option<&'a str> {
+        match self.emoji.as_deref() {
+            Some(emoji) if is_valid_emoji(emoji) => return Some(emoji),
+            Some(emoji) => {
+                tracing::warn!(guild_id = %self.guild_id, "Ignoring invalid emoji {emoji}.")
+            }
+            None => {}
+        }
+
+        config
+            .emojis
+            .get(&notification_notify.r#type)
+            .or_else(|| config.emojis.get(&self.r#type))
+            .map(String::as_str)
+    }
+
+    /// Returns the shard eruption whose map should be attached for guilds that opted into rich notifications.
+    fn shard_map<'a>(
+        &self,
+        notification_notify: &'a NotificationNotify,
+    ) -> Option<&'a ShardEruptionResponse> {
+        if !self.rich {
+            return None;
+        }
+
+        match notification_notify.r#type {
+            NotificationType::ShardEruptionRegular
+            | NotificationType::ShardEruptionStrong
+            | NotificationType::ShardEruptionVariant
+            | NotificationType::ShardEruptionEnd
+            | NotificationType::ShardEruptionCorrection => {
+                notification_notify.shard_eruption.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds the embed for guilds that opted into rich notifications, if the notification has anything to show.
+    fn embed(&self, notification_notify: &NotificationNotify) -> Option<CreateEmbed> {
+        if !self.rich {
+            return None;
+        }
+
+        match notification_notify.r#type {
+            NotificationType::TravellingSpirit => {
+                let details = notification_notify.travelling_spirit_details.as_ref()?;
+
+                let description = details
+                    .items
+                    .iter()
+                    .map(|item| format!("- {}", item.summary()))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                Some(
+                    CreateEmbed::new()
+                        .title(
+                            notification_notify
+                                .travelling_spirit_name
+                                .as_ref()
+                                .expect("A travelling spirit must have a name."),
+                        )
+                        .description(description)
+                        .image(&details.image_url),
+                )
+            }
+            NotificationType::PollutedGeyser
+            | NotificationType::Grandma
+            | NotificationType::Turtle => {
+                let end_time = notification_notify.end_time?;
+
+                Some(
+                    CreateEmbed::new()
+                        .title(notification_notify.r#type.name())
+                        .field(
+                            "Starts",
+                            format!("<t:{}:t>", notification_notify.start_time),
+                            true,
+                        )
+                        .field("Ends", format!("<t:{end_time}:R>"), true),
+                )
+            }
+            _ => None,
+        }
+    }
 }
 
-pub async fn prepare_notification_to_send(
-    client: &Http,
+fn shard_map_filename(url: &str) -> String {
+    let extension = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension)
+        .unwrap_or("webp");
+
+    format!("shard-eruption.{extension}")
+}
+
+/// A page of the subscriptions due for a notification.
+pub struct SubscriptionPage {
+    pub notifications: Vec<Notification>,
+    /// The guild and type the next page starts after, if this page was full.
+    pub next: Option<(i64, i16)>,
+}
+
+/// Retrieves a page of the subscriptions due for a notification, in guild and type order after a position. Paused guilds and rows that fail to parse are skipped.
+///
+/// Rows are parsed as they are streamed in, and pages are short, so a type with tens of thousands of subscribers is never held in memory at once. Corrections only go to guilds sent a shard eruption notification today.
+pub async fn matching_notifications(
     pool: &Pool<Postgres>,
     notification_notify: &NotificationNotify,
-) {
-    let results: Vec<NotificationPacket> = sqlx::query_as(
-        r#"select * from notifications where type = $1 and "offset" = $2 and sendable is true;"#,
-    )
-    .bind(notification_notify.r#type as i16)
-    .bind(notification_notify.time_until_start as i16)
-    .fetch_all(pool)
-    .await
-    .expect("Failed to retrieve notification packets.");
-
-    let futures = results
-        .iter()
-        .map(|notification_packet| {
-            let notification = Notification::from(notification_packet.clone());
-            { async move { notification.send(client, notification_notify).await } }.boxed()
+    after: Option<(i64, i16)>,
+) -> Result<SubscriptionPage> {
+    let delivered_on = (notification_notify.r#type == NotificationType::ShardEruptionCorrection)
+        .then(|| {
+            Utc::now()
+                .with_timezone(&chrono_tz::America::Los_Angeles)
+                .date_naive()
+        });
+
+    let (mut notifications, last, rows): (Vec<Notification>, Option<(i64, i16)>, i64) =
+        with_retry("notifications", || {
+            sqlx::query(
+                r#"select * from notifications where type in ($1, $2) and ((cardinality(countdown) = 0 and (("offset" = $3 and delivery_mode in (0, 2)) or ($3 = 0 and delivery_mode in (1, 2)))) or $3 = any(countdown)) and sendable is true and guild_id not in (select guild_id from guild_pauses where paused_until > now()) and ($4::date is null or guild_id in (select guild_id from shard_eruption_deliveries where "date" = $4)) and ($5::bigint is null or (guild_id, "type") > ($5, $6::smallint)) order by guild_id, "type" limit $7;"#,
+            )
+            .bind(notification_notify.r#type)
+            .bind(
+                notification_notify
+                    .r#type
+                    .bundle()
+                    .unwrap_or(notification_notify.r#type),
+            )
+            .bind(notification_notify.time_until_start as i16)
+            .bind(delivered_on)
+            .bind(after.map(|(guild_id, _)| guild_id))
+            .bind(after.map(|(_, r#type)| r#type))
+            .bind(SUBSCRIPTION_PAGE_SIZE)
+            .fetch(read_pool(pool))
+            .try_fold(
+                (Vec::new(), None, 0),
+                |(mut notifications, _, rows), row| async move {
+                    let position = (row.try_get("guild_id")?, row.try_get("type")?);
+
+                    match Notification::from_row(&row) {
+                        Ok(notification) => notifications.push(notification),
+                        Err(error) => tracing::error!("Skipping invalid notification: {error:?}"),
+                    }
+
+                    Ok((notifications, Some(position), rows + 1))
+                },
+            )
         })
-        .collect::<Vec<_>>();
+        .await?;
+
+    // Realm roles are extra, so failing to retrieve them only loses those mentions.
+    if let Some(shard_eruption) = &notification_notify.shard_eruption {
+        let guild_ids = notifications
+            .iter()
+            .map(|notification| notification.guild_id.get() as i64)
+            .collect::<Vec<_>>();
+
+        match get_realm_roles(read_pool(pool), &shard_eruption.realm, &guild_ids).await {
+            Ok(mut realm_roles) => {
+                for notification in &mut notifications {
+                    notification.realm_role = realm_roles.remove(&notification.guild_id);
+                }
+            }
+            Err(error) => {
+                tracing::error!("Failed to retrieve realm roles: {error:?}");
+            }
+        }
+    }
+
+    Ok(SubscriptionPage {
+        notifications,
+        next: last.filter(|_| rows == SUBSCRIPTION_PAGE_SIZE),
+    })
+}
+
+/// Announces a notification everywhere but to subscriptions: the event stream, the broker, social accounts, and the feeds.
+pub async fn publish_notification(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    notification_notify: &NotificationNotify,
+) {
+    event_stream::publish(notification_notify);
+    broker::publish_notification(notification_notify).await;
+    social::post(config.flavor_text, notification_notify);
+    record_dispatch(pool, config, notification_notify).await;
+}
+
+pub async fn prepare_notification_to_send(
+    clients: &DiscordClients,
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+    config: &Config,
+    mut notification_notify: NotificationNotify,
+) {
+    let stale = notification_notify.is_stale(config.maximum_notification_age);
+
+    if stale {
+        metrics::increment("stale_notifications");
+
+        match config.stale_notifications {
+            StaleNotifications::Drop => {
+                tracing::warn!(
+                    r#type = ?notification_notify.r#type,
+                    start_time = notification_notify.start_time,
+                    "Dropping a stale notification."
+                );
+
+                return;
+            }
+            // One of an occurrence yet to start is still accurate, so it is only noted once the occurrence has.
+            StaleNotifications::Annotate => {
+                notification_notify.late |=
+                    notification_notify.start_time <= Utc::now().timestamp();
+            }
+        }
+    }
+
+    let notification_notify = &notification_notify;
+    publish_notification(pool, config, notification_notify).await;
+
+    // A notification replayed from the outbox or reclaimed from the stream may have been sent already.
+    let delivered = match get_delivered_channels(pool, notification_notify).await {
+        Ok(delivered) => delivered,
+        Err(error) => {
+            tracing::error!(
+                r#type = ?notification_notify.r#type,
+                "Failed to retrieve deliveries: {error:?}"
+            );
+
+            HashSet::new()
+        }
+    };
+
+    let rendered = &RenderedText::new(notification_notify, config.flavor_text);
+    let mut counts = DeliveryCounts::default();
+    let mut after = None;
+
+    // Each page is sent before the next is retrieved, so memory and the sends in flight stay bounded however many subscribe.
+    loop {
+        let page = match matching_notifications(pool, notification_notify, after).await {
+            Ok(page) => page,
+            Err(error) => {
+                tracing::error!(
+                    r#type = ?notification_notify.r#type,
+                    "Failed to retrieve notifications: {error:?}"
+                );
+
+                break;
+            }
+        };
+
+        let matched = page.notifications.len();
+
+        let recipients = page
+            .notifications
+            .into_iter()
+            .filter(|notification| !delivered.contains(&notification.channel_id))
+            .filter(|notification| !notification.is_throttled(notification_notify))
+            .filter(|notification| channel_circuit::allow(notification.channel_id.get()));
+
+        let results = dispatcher
+            .fan_out(
+                notification_notify.urgency(),
+                recipients,
+                |notification| notification.channel_id.get(),
+                |notification| async move {
+                    // Each send asks for a client, so one rejected by Discord fails the rest over at once.
+                    let client = clients.current();
+
+                    let mut result = notification
+                        .send_within_deadline(&client, pool, config, notification_notify, rendered)
+                        .await;
+
+                    // Sends already underway when the token was rejected are retried with the one failed over to.
+                    if let Err(error) = &result {
+                        if clients.record_error(&client, error) {
+                            let fallback = clients.current();
+
+                            if !Arc::ptr_eq(&fallback, &client) {
+                                result = notification
+                                    .send_within_deadline(
+                                        &fallback,
+                                        pool,
+                                        config,
+                                        notification_notify,
+                                        rendered,
+                                    )
+                                    .await;
+                            }
+                        }
+                    }
+
+                    match &result {
+                        Ok(sent) => {
+                            record_channel_delivery(
+                                pool,
+                                notification.guild_id,
+                                notification.channel_id,
+                                sent.id,
+                                notification_notify,
+                            )
+                            .await;
+
+                            // Catching up is late by design, so it would only skew the latency. Stale notifications were meant to be on time, so count.
+                            if !notification_notify.late || stale {
+                                latency::record(
+                                    sent.timestamp.timestamp_millis()
+                                        - notification_notify.due_time() * 1000,
+                                );
+                            }
+
+                            if channel_circuit::record_success(notification.channel_id.get()) {
+                                audit(
+                                    pool,
+                                    AuditEvent {
+                                        guild_id: Some(notification.guild_id),
+                                        channel_id: Some(notification.channel_id),
+                                        actor: "channel_circuit",
+                                        action: "circuit_closed",
+                                        ..Default::default()
+                                    },
+                                )
+                                .await;
+                            }
+
+                            if let Err(error) = notification
+                                .record_occurrence(pool, notification_notify)
+                                .await
+                            {
+                                tracing::error!(
+                                    guild_id = %notification.guild_id,
+                                    "Failed to record a throttled occurrence: {error:?}"
+                                );
+                            }
+
+                            if notification_notify.shard_eruption.is_some() {
+                                record_shard_eruption_delivery(pool, notification.guild_id).await;
+                            }
+                        }
+                        Err(error) => {
+                            tracing::error!(
+                                guild_id = %notification.guild_id,
+                                channel_id = %notification.channel_id,
+                                r#type = ?notification_notify.r#type,
+                                "Failed to send notification: {error:?}"
+                            );
+
+                            // The channel may have changed, so its metadata is fetched again next time.
+                            channel_cache::forget(notification.channel_id);
+
+                            if channel_circuit::record_failure(notification.channel_id.get()) {
+                                audit(
+                                    pool,
+                                    AuditEvent {
+                                        guild_id: Some(notification.guild_id),
+                                        channel_id: Some(notification.channel_id),
+                                        actor: "channel_circuit",
+                                        action: "circuit_opened",
+                                        reason: Some(&error.to_string()),
+                                        ..Default::default()
+                                    },
+                                )
+                                .await;
+                            }
+                        }
+                    }
+
+                    result
+                },
+            )
+            .await;
+
+        let sent = results.iter().filter(|result| result.is_ok()).count();
+        counts.sent += sent as i32;
+        counts.failed += (results.len() - sent) as i32;
+        counts.skipped += (matched - results.len()) as i32;
+
+        match page.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+
+    record_delivery(pool, notification_notify.r#type, &counts).await;
+
+    forget_old_deliveries(pool).await;
+
+    if notification_notify.r#type.is_direct_messageable() {
+        send_direct_messages(
+            &clients.current(),
+            pool,
+            dispatcher,
+            config,
+            notification_notify,
+        )
+        .await;
+    }
+
+    send_webhooks(pool, dispatcher, config, notification_notify).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_types_round_trip() {
+        for r#type in NotificationType::ALL {
+            assert_eq!(NotificationType::try_from(r#type as i16).unwrap(), r#type);
+            assert_eq!(
+                r#type.to_string().parse::<NotificationType>().unwrap(),
+                r#type
+            );
+        }
+
+        assert!(NotificationType::try_from(NotificationType::ALL.len() as i16).is_err());
+        assert!(NotificationType::try_from(-1).is_err());
+        assert!("Aurora".parse::<NotificationType>().is_err());
+    }
+
+    #[test]
+    fn nonces_fit_discord_limit() {
+        // The largest snowflake written in 12 base 36 digits, which lasts until well past 2080.
+        let notification = Notification {
+            guild_id: GuildId::new(1),
+            r#type: NotificationType::TravellingSpirit,
+            channel_id: ChannelId::new(36_u64.pow(12) - 1),
+            role_id: None,
+            offset: 0,
+            sendable: true,
+            rich: false,
+            threaded: false,
+            silent: false,
+            template: None,
+            emoji: None,
+            minimum_interval: None,
+            on_the_hour: false,
+            last_occurrence: None,
+            realm_role: None,
+        };
 
-    let results = join_all(futures).await;
+        for r#type in NotificationType::ALL {
+            let notification_notify = NotificationNotify {
+                r#type,
+                start_time: i32::MAX.into(),
+                end_time: None,
+                time_until_start: *LONG_LEAD_OFFSETS.iter().max().unwrap(),
+                shard_eruption: None,
+                travelling_spirit_name: None,
+                special_visit_entities: None,
+                travelling_spirit_details: None,
+                weekly_digest: None,
+                occurrences: None,
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            };
 
-    for result in results {
-        if let Err(error) = result {
-            tracing::error!("Failed to send notification: {error:?}");
+            let nonce = notification.nonce(&notification_notify);
+            assert!(nonce.len() <= 25, "{nonce}");
         }
     }
 }
@@ -0,0 +1,93 @@
+use super::notification::{NotificationNotify, NotificationType};
+use chrono::DateTime;
+use chrono_tz::America::Los_Angeles;
+use serenity::model::id::ChannelId;
+use sqlx::{Pool, Postgres};
+use std::num::NonZeroU64;
+
+/// Identifies the event a notification belongs to, so its follow-ups can share a thread.
+///
+/// Only events with follow-ups worth discussing have one.
+pub fn event_key(notification_notify: &NotificationNotify) -> Option<String> {
+    match notification_notify.r#type {
+        // Landing and clearing up notifications share the eruption's end time.
+        NotificationType::ShardEruptionRegular
+        | NotificationType::ShardEruptionStrong
+        | NotificationType::ShardEruptionVariant
+        | NotificationType::ShardEruptionEnd => notification_notify
+            .end_time
+            .map(|end_time| format!("shard-eruption-{end_time}")),
+        NotificationType::TravellingSpirit => Some(format!(
+            "travelling-spirit-{}",
+            notification_notify.start_time
+        )),
+        _ => None,
+    }
+}
+
+/// Names a thread after the event and its date.
+pub fn thread_name(notification_notify: &NotificationNotify) -> String {
+    let date = DateTime::from_timestamp(notification_notify.start_time, 0)
+        .map(|date| {
+            date.with_timezone(&Los_Angeles)
+                .format("%-d %B")
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    let event = match (
+        &notification_notify.shard_eruption,
+        &notification_notify.travelling_spirit_name,
+    ) {
+        (Some(shard_eruption), _) => format!(
+            "Shard eruption in {} ({})",
+            shard_eruption.realm, shard_eruption.sky_map
+        ),
+        (None, Some(travelling_spirit_name)) => travelling_spirit_name.clone(),
+        (None, None) => notification_notify.r#type.name().to_string(),
+    };
+
+    format!("{event} – {date}")
+}
+
+/// Returns the thread already created for the event in the channel.
+pub async fn get_thread(
+    pool: &Pool<Postgres>,
+    channel_id: ChannelId,
+    event: &str,
+) -> Result<Option<ChannelId>, sqlx::Error> {
+    let thread_id: Option<i64> = sqlx::query_scalar(
+        r#"select thread_id from notification_threads where channel_id = $1 and "event" = $2;"#,
+    )
+    .bind(channel_id.get() as i64)
+    .bind(event)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(thread_id
+        .and_then(|thread_id| NonZeroU64::new(thread_id as u64))
+        .map(ChannelId::from))
+}
+
+/// Records the thread created for the event in the channel, forgetting threads for events long past.
+pub async fn save_thread(
+    pool: &Pool<Postgres>,
+    channel_id: ChannelId,
+    event: &str,
+    thread_id: ChannelId,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("delete from notification_threads where created_at < now() - interval '2 days';")
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"insert into notification_threads (channel_id, "event", thread_id) values ($1, $2, $3) on conflict (channel_id, "event") do update set thread_id = excluded.thread_id, created_at = now();"#,
+    )
+    .bind(channel_id.get() as i64)
+    .bind(event)
+    .bind(thread_id.get() as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use super::{
+    config::Config,
+    notification::{NotificationNotify, NotificationType},
+};
+use crate::utility::database::with_retry;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::{FromRow, Pool, Postgres};
+
+#[derive(FromRow)]
+pub struct OccurrenceOverridePacket {
+    id: i32,
+    r#type: i16,
+    start: Option<DateTime<Utc>>,
+    new_start: Option<DateTime<Utc>>,
+}
+
+/// A manual correction to the regular schedule of a notification type.
+///
+/// An override without a start adds an occurrence, one without a new start removes an occurrence, and one with both shifts an occurrence.
+#[derive(Debug)]
+pub struct OccurrenceOverride {
+    pub r#type: NotificationType,
+    pub start: Option<DateTime<Tz>>,
+    pub new_start: Option<DateTime<Tz>>,
+}
+
+pub async fn get_occurrence_overrides(
+    pool: &Pool<Postgres>,
+    now: DateTime<Tz>,
+) -> Result<Vec<OccurrenceOverride>, sqlx::Error> {
+    let rows: Vec<OccurrenceOverridePacket> = with_retry("occurrence overrides", || {
+        sqlx::query_as(
+            r#"select id, "type", "start", new_start from occurrence_overrides where coalesce(new_start, "start") >= $1 - interval '1 day' and coalesce(new_start, "start") <= $1 + interval '2 days';"#,
+        )
+        .bind(now.with_timezone(&Utc))
+        .fetch_all(pool)
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let r#type = match NotificationType::try_from(row.r#type) {
+                Ok(r#type) => r#type,
+                Err(error) => {
+                    tracing::warn!("Skipping occurrence override {}: {error}", row.id);
+                    return None;
+                }
+            };
+
+            // These are sourced from the wind paths and the travelling spirits and recurring events tables respectively, so correct them there.
+            if matches!(
+                r#type,
+                NotificationType::ShardEruptionRegular
+                    | NotificationType::ShardEruptionStrong
+                    | NotificationType::ShardEruptionVariant
+                    | NotificationType::TravellingSpirit
+                    | NotificationType::SeasonalActivity
+            ) {
+                tracing::warn!(
+                    "Skipping occurrence override {} as {:?} cannot be overridden.",
+                    row.id,
+                    r#type
+                );
+
+                return None;
+            }
+
+            Some(OccurrenceOverride {
+                r#type,
+                start: row.start.map(|start| start.with_timezone(&now.timezone())),
+                new_start: row
+                    .new_start
+                    .map(|new_start| new_start.with_timezone(&now.timezone())),
+            })
+        })
+        .collect())
+}
+
+/// Removes overridden occurrences from the notifications about to be queued and adds any overridden occurrences that are due.
+pub fn apply_occurrence_overrides(
+    config: &Config,
+    occurrence_overrides: &[OccurrenceOverride],
+    notification_notifies: &mut Vec<NotificationNotify>,
+    now: DateTime<Tz>,
+) {
+    notification_notifies.retain(|notification_notify| {
+        !occurrence_overrides.iter().any(|occurrence_override| {
+            occurrence_override.r#type == notification_notify.r#type
+                && occurrence_override
+                    .start
+                    .is_some_and(|start| start.timestamp() == notification_notify.start_time)
+        })
+    });
+
+    for occurrence_override in occurrence_overrides {
+        let Some(new_start) = occurrence_override.new_start else {
+            continue;
+        };
+
+        let time_until_start = new_start.signed_duration_since(now).num_minutes();
+
+        if !(0..=config.lead_time(occurrence_override.r#type).into()).contains(&time_until_start) {
+            continue;
+        }
+
+        notification_notifies.push(NotificationNotify {
+            r#type: occurrence_override.r#type,
+            start_time: new_start.timestamp(),
+            end_time: occurrence_override.r#type.end_time(new_start.timestamp()),
+            time_until_start: time_until_start as u32,
+            shard_eruption: None,
+            travelling_spirit_name: None,
+            special_visit_entities: None,
+            travelling_spirit_details: None,
+            weekly_digest: None,
+            occurrences: None,
+            recurring_event: None,
+            maintenance_end: None,
+            game_update: None,
+            late: false,
+            queued_at: None,
+        });
+    }
+}
@@ -0,0 +1,81 @@
+use super::{leader::is_leader, notification::NotificationNotify};
+use crate::utility::{constants::OUTBOX_DRAIN_INTERVAL, metrics, queue::NotificationQueue};
+use sqlx::{types::Json, FromRow, Pool, Postgres};
+use std::sync::Arc;
+use tokio::time::sleep;
+
+#[derive(FromRow)]
+pub struct OutboxPacket {
+    payload: Json<NotificationNotify>,
+}
+
+/// Persists a notification the queue had no room for.
+pub async fn spill_notification(pool: &Pool<Postgres>, notification_notify: NotificationNotify) {
+    let r#type = notification_notify.r#type;
+
+    let result = sqlx::query("insert into notification_outbox (payload) values ($1);")
+        .bind(Json(notification_notify))
+        .execute(pool)
+        .await;
+
+    match result {
+        Ok(_) => {
+            tracing::warn!(
+                ?r#type,
+                "Spilled a notification to the outbox as the queue is full."
+            );
+            metrics::increment("queue_spilled");
+        }
+        Err(error) => {
+            tracing::error!(
+                ?r#type,
+                "Failed to spill a notification to the outbox: {error:?}"
+            );
+            metrics::increment("queue_dropped");
+        }
+    }
+}
+
+/// Moves spilled notifications back into the queue, oldest first, as room frees up.
+///
+/// Room is reserved before they are taken, so queuing them never waits while the transaction deleting them is open, and they are only deleted once queued.
+pub async fn drain_outbox(pool: Pool<Postgres>, queue: Arc<NotificationQueue>) {
+    loop {
+        sleep(OUTBOX_DRAIN_INTERVAL).await;
+        let available = queue.available();
+
+        if available == 0 || !is_leader() {
+            continue;
+        }
+
+        if let Err(error) = drain(&pool, &queue, available).await {
+            tracing::error!("Failed to drain the outbox: {error:?}");
+        }
+    }
+}
+
+async fn drain(
+    pool: &Pool<Postgres>,
+    queue: &NotificationQueue,
+    available: usize,
+) -> Result<(), sqlx::Error> {
+    // Anything else queuing may have taken the room in the meantime.
+    let Some(mut reservation) = queue.reserve(available) else {
+        return Ok(());
+    };
+
+    let mut transaction = pool.begin().await?;
+
+    let rows: Vec<OutboxPacket> = sqlx::query_as(
+        "delete from notification_outbox where id in (select id from notification_outbox order by id limit $1 for update skip locked) returning payload;",
+    )
+    .bind(reservation.len() as i64)
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    for row in rows {
+        reservation.push(row.payload.0);
+    }
+
+    transaction.commit().await
+}
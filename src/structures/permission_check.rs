@@ -0,0 +1,201 @@
+use super::{
+    audit_log::{audit, AuditEvent},
+    config::Config,
+    leader::is_leader,
+    notification::{optional_snowflake, snowflake},
+    subscriber_index::invalidate_subscriber_index,
+};
+use crate::utility::{discord_clients::DiscordClients, permissions::GuildView};
+use serenity::{
+    http::Http,
+    model::id::{ChannelId, GuildId, RoleId},
+};
+use sqlx::{postgres::PgRow, FromRow, Pool, Postgres};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+/// Where a subscription sends to, which is all its permissions depend on.
+struct Destination {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    role_id: Option<RoleId>,
+}
+
+impl FromRow<'_, PgRow> for Destination {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            guild_id: snowflake(row, "guild_id")?,
+            channel_id: snowflake(row, "channel_id")?,
+            role_id: optional_snowflake(row, "role_id")?,
+        })
+    }
+}
+
+/// Periodically checks every subscription can still be sent, so problems surface before the next event.
+///
+/// Subscriptions that cannot be sent are disabled with the reason, and re-enabled once the problem is fixed.
+pub async fn check_permissions(
+    clients: Arc<DiscordClients>,
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+) {
+    let interval = Duration::from_secs((config.permission_check_interval * 60).into());
+
+    loop {
+        sleep(interval).await;
+
+        if !is_leader() {
+            continue;
+        }
+
+        if let Err(error) = check(&clients.primary(), &pool).await {
+            tracing::error!("Error checking permissions: {error:?}");
+        }
+    }
+}
+
+async fn check(client: &Http, pool: &Pool<Postgres>) -> anyhow::Result<()> {
+    // Rows disabled by hand have no reason, so they are left alone.
+    let destinations: Vec<Destination> = sqlx::query_as(
+        "select distinct guild_id, channel_id, role_id from notifications where sendable is true or unsendable_reason is not null;",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut guilds = BTreeMap::<GuildId, Vec<Destination>>::new();
+
+    for destination in destinations {
+        guilds
+            .entry(destination.guild_id)
+            .or_default()
+            .push(destination);
+    }
+
+    for (guild_id, destinations) in guilds {
+        let guild_view = match GuildView::fetch(client, guild_id).await {
+            Ok(guild_view) => guild_view,
+            Err(error) => {
+                tracing::warn!(%guild_id, "Failed to fetch a guild: {error:?}");
+                continue;
+            }
+        };
+
+        for destination in destinations {
+            let problem = match &guild_view {
+                Some(guild_view) => {
+                    match guild_view
+                        .channel_problem(client, destination.channel_id, destination.role_id)
+                        .await
+                    {
+                        Ok(problem) => problem,
+                        Err(error) => {
+                            tracing::warn!(
+                                channel_id = %destination.channel_id,
+                                "Failed to check a channel: {error:?}"
+                            );
+
+                            continue;
+                        }
+                    }
+                }
+                None => Some("The bot is not in the server.".to_string()),
+            };
+
+            record(pool, &destination, problem.as_deref()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn record(
+    pool: &Pool<Postgres>,
+    destination: &Destination,
+    problem: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let result = sqlx::query(
+        "update notifications set sendable = $4 is null, unsendable_reason = $4 where guild_id = $1 and channel_id = $2 and role_id is not distinct from $3 and unsendable_reason is distinct from $4 and (sendable is true or unsendable_reason is not null);",
+    )
+    .bind(destination.guild_id.get() as i64)
+    .bind(destination.channel_id.get() as i64)
+    .bind(destination.role_id.map(|role_id| role_id.get() as i64))
+    .bind(problem)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        invalidate_subscriber_index();
+
+        audit(
+            pool,
+            AuditEvent {
+                guild_id: Some(destination.guild_id),
+                channel_id: Some(destination.channel_id),
+                actor: "permission_check",
+                action: if problem.is_some() {
+                    "disabled"
+                } else {
+                    "enabled"
+                },
+                reason: problem,
+                ..Default::default()
+            },
+        )
+        .await;
+
+        match problem {
+            Some(problem) => tracing::info!(
+                guild_id = %destination.guild_id,
+                channel_id = %destination.channel_id,
+                "Disabling notifications: {problem}"
+            ),
+            None => tracing::info!(
+                guild_id = %destination.guild_id,
+                channel_id = %destination.channel_id,
+                "Re-enabling notifications."
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Disables a channel's subscriptions when a send finds the channel cannot be sent to, so the problem is reported without waiting for the next check, which re-enables them once it is fixed.
+pub async fn disable_channel(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    problem: &str,
+) {
+    let result = sqlx::query(
+        "update notifications set sendable = false, unsendable_reason = $3 where guild_id = $1 and channel_id = $2 and sendable is true;",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(channel_id.get() as i64)
+    .bind(problem)
+    .execute(pool)
+    .await;
+
+    match result {
+        Ok(result) if result.rows_affected() > 0 => {
+            audit(
+                pool,
+                AuditEvent {
+                    guild_id: Some(guild_id),
+                    channel_id: Some(channel_id),
+                    actor: "send",
+                    action: "disabled",
+                    reason: Some(problem),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            tracing::info!(%guild_id, %channel_id, "Disabling notifications: {problem}");
+        }
+        Ok(_) => {}
+        Err(error) => {
+            tracing::error!(%guild_id, %channel_id, "Failed to disable notifications: {error:?}");
+        }
+    }
+}
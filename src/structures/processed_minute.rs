@@ -0,0 +1,77 @@
+use super::notification::{NotificationNotify, NotificationType};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use sqlx::{Pool, Postgres};
+use std::collections::{HashMap, HashSet};
+
+/// Retrieves the last minute each type was queued for.
+pub async fn get_processed_minutes(
+    pool: &Pool<Postgres>,
+) -> Result<HashMap<NotificationType, DateTime<Utc>>, sqlx::Error> {
+    let rows: Vec<(i16, DateTime<Utc>)> =
+        sqlx::query_as(r#"select "type", minute from processed_minutes;"#)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(r#type, minute)| Some((NotificationType::try_from(r#type).ok()?, minute)))
+        .collect())
+}
+
+/// Records `now` as processed for the types of some notifications, keeping only those of types not already processed at or after it.
+///
+/// The notify task restarts after a panic, and leadership may change hands, so the same minute can be scheduled twice. Claiming the minute in one statement means only the first attempt queues it. Should the claim fail, the notifications are kept, as a duplicate is preferable to nothing.
+pub async fn claim_processed_minute(
+    pool: &Pool<Postgres>,
+    now: DateTime<Tz>,
+    notification_notifies: Vec<NotificationNotify>,
+) -> Vec<NotificationNotify> {
+    if notification_notifies.is_empty() {
+        return notification_notifies;
+    }
+
+    let types = notification_notifies
+        .iter()
+        .map(|notification_notify| notification_notify.r#type as i16)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let claimed: Vec<(i16,)> = match sqlx::query_as(
+        r#"insert into processed_minutes ("type", minute) select unnest($1::smallint[]), $2 on conflict ("type") do update set minute = excluded.minute where processed_minutes.minute < excluded.minute returning "type";"#,
+    )
+    .bind(&types)
+    .bind(now)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(claimed) => claimed,
+        Err(error) => {
+            tracing::error!("Failed to record the processed minute: {error:?}");
+            return notification_notifies;
+        }
+    };
+
+    let claimed = claimed
+        .into_iter()
+        .filter_map(|(r#type,)| NotificationType::try_from(r#type).ok())
+        .collect::<HashSet<_>>();
+
+    notification_notifies
+        .into_iter()
+        .filter(|notification_notify| {
+            let is_claimed = claimed.contains(&notification_notify.r#type);
+
+            if !is_claimed {
+                tracing::warn!(
+                    r#type = ?notification_notify.r#type,
+                    %now,
+                    "Skipping a notification already queued this minute."
+                );
+            }
+
+            is_claimed
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, RoleId};
+use sqlx::{Pool, Postgres};
+use std::collections::{BTreeMap, HashMap};
+
+/// A role a guild mentions for shard eruptions in one realm.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RealmRole {
+    pub role_id: RoleId,
+    /// Whether to mention it instead of the subscription's role, rather than as well.
+    pub replace: bool,
+}
+
+/// Sets the role a guild mentions for shard eruptions in a realm, replacing any existing one.
+pub async fn set_realm_role(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    realm: &str,
+    realm_role: &RealmRole,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "insert into shard_eruption_realm_roles (guild_id, realm, role_id, replace) values ($1, $2, $3, $4) on conflict (guild_id, realm) do update set role_id = excluded.role_id, replace = excluded.replace;",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(realm)
+    .bind(realm_role.role_id.get() as i64)
+    .bind(realm_role.replace)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stops mentioning a role for shard eruptions in a realm. Returns whether there was one.
+pub async fn remove_realm_role(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    realm: &str,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query("delete from shard_eruption_realm_roles where guild_id = $1 and realm = $2;")
+            .bind(guild_id.get() as i64)
+            .bind(realm)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Retrieves the roles a guild mentions for shard eruptions, by realm.
+pub async fn get_guild_realm_roles(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+) -> Result<BTreeMap<String, RealmRole>, sqlx::Error> {
+    let rows: Vec<(String, i64, bool)> = sqlx::query_as(
+        "select realm, role_id, replace from shard_eruption_realm_roles where guild_id = $1;",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(realm, role_id, replace)| {
+            let role_id = u64::try_from(role_id).ok().filter(|id| *id != 0)?;
+
+            Some((
+                realm,
+                RealmRole {
+                    role_id: RoleId::new(role_id),
+                    replace,
+                },
+            ))
+        })
+        .collect())
+}
+
+/// Retrieves the roles some guilds mention for shard eruptions in a realm.
+pub async fn get_realm_roles(
+    pool: &Pool<Postgres>,
+    realm: &str,
+    guild_ids: &[i64],
+) -> Result<HashMap<GuildId, RealmRole>, sqlx::Error> {
+    let rows: Vec<(i64, i64, bool)> = sqlx::query_as(
+        "select guild_id, role_id, replace from shard_eruption_realm_roles where realm = $1 and guild_id = any($2);",
+    )
+    .bind(realm)
+    .bind(guild_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(guild_id, role_id, replace)| {
+            let guild_id = u64::try_from(guild_id).ok().filter(|id| *id != 0)?;
+            let role_id = u64::try_from(role_id).ok().filter(|id| *id != 0)?;
+
+            Some((
+                GuildId::new(guild_id),
+                RealmRole {
+                    role_id: RoleId::new(role_id),
+                    replace,
+                },
+            ))
+        })
+        .collect())
+}
@@ -0,0 +1,119 @@
+use super::notification::NotificationType;
+use crate::utility::database::with_retry;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::{America::Los_Angeles, Tz};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The types whose notifications need nothing but a recurring event's details.
+const RECURRING_EVENT_TYPES: [NotificationType; 2] =
+    [NotificationType::Aurora, NotificationType::SeasonalActivity];
+
+#[derive(FromRow)]
+pub struct RecurringEventPacket {
+    id: i32,
+    r#type: i16,
+    name: String,
+    message: String,
+    start: DateTime<Utc>,
+    end: Option<DateTime<Utc>>,
+    first_occurrence: NaiveTime,
+    repeat_interval: i32,
+}
+
+/// An event repeating at a fixed cadence, such as a season's quests or the AURORA concert.
+pub struct RecurringEvent {
+    pub r#type: NotificationType,
+    pub name: String,
+    /// Sent when an occurrence starts.
+    pub message: String,
+    pub start: DateTime<Tz>,
+    /// When the event stops recurring, if ever.
+    pub end: Option<DateTime<Tz>>,
+    /// The time of the first occurrence each day, in Sky time.
+    pub first_occurrence: NaiveTime,
+    /// The minutes between occurrences.
+    pub repeat_interval: i32,
+}
+
+/// What a notification tells of a recurring event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecurringEventDetails {
+    pub name: String,
+    pub message: String,
+    /// Whether this is the event's first occurrence, as when a season begins.
+    #[serde(default)]
+    pub first: bool,
+}
+
+impl RecurringEvent {
+    /// Whether an occurrence starts at a wall-clock time in Sky time.
+    pub fn is_occurrence(&self, time: NaiveDateTime) -> bool {
+        let in_window = Los_Angeles
+            .from_local_datetime(&time)
+            .earliest()
+            .is_some_and(|instant| {
+                self.start <= instant && self.end.is_none_or(|end| instant < end)
+            });
+
+        let seconds = (time.time() - self.first_occurrence).num_seconds();
+        in_window && seconds >= 0 && seconds % (i64::from(self.repeat_interval) * 60) == 0
+    }
+
+    /// Whether the event has stopped recurring by `now`.
+    pub fn has_ended(&self, now: DateTime<Tz>) -> bool {
+        self.end.is_some_and(|end| end <= now)
+    }
+
+    /// The details of the occurrence starting at a Unix timestamp.
+    pub fn details(&self, start_time: i64) -> RecurringEventDetails {
+        RecurringEventDetails {
+            name: self.name.clone(),
+            message: self.message.clone(),
+            first: start_time
+                < (self.start + TimeDelta::minutes(self.repeat_interval.into())).timestamp(),
+        }
+    }
+}
+
+/// Retrieves every recurring event that has yet to end, soonest first.
+///
+/// Rows of types that cannot be notified of from their details alone are skipped.
+pub async fn get_recurring_events(pool: &sqlx::PgPool) -> Result<Vec<RecurringEvent>, sqlx::Error> {
+    let rows: Vec<RecurringEventPacket> = with_retry("recurring events", || {
+        sqlx::query_as(
+            r#"select id, "type", name, message, "start", "end", first_occurrence, repeat_interval from recurring_events where "end" is null or "end" > now() order by "start";"#,
+        )
+        .fetch_all(pool)
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let r#type = NotificationType::try_from(row.r#type)
+                .ok()
+                .filter(|r#type| RECURRING_EVENT_TYPES.contains(r#type));
+
+            let Some(r#type) = r#type else {
+                tracing::warn!(
+                    "Skipping recurring event {} as type {} cannot recur.",
+                    row.id,
+                    row.r#type
+                );
+
+                return None;
+            };
+
+            Some(RecurringEvent {
+                r#type,
+                name: row.name,
+                message: row.message,
+                start: row.start.with_timezone(&Los_Angeles),
+                end: row.end.map(|end| end.with_timezone(&Los_Angeles)),
+                first_occurrence: row.first_occurrence,
+                repeat_interval: row.repeat_interval,
+            })
+        })
+        .collect())
+}
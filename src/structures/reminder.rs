@@ -0,0 +1,177 @@
+use super::{
+    leader::is_leader,
+    notification::{snowflake, NotificationType},
+    user_subscription::is_closed,
+};
+use crate::utility::{
+    constants::{REMINDER_INTERVAL, REMINDER_MAXIMUM_LATENESS},
+    discord_clients::DiscordClients,
+    dispatcher::{Dispatcher, Urgency},
+    metrics,
+};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serenity::{
+    all::{CreateMessage, MessageFlags},
+    model::id::UserId,
+};
+use sqlx::{postgres::PgRow, FromRow, Pool, Postgres, Row};
+use std::sync::Arc;
+use tokio::time::sleep;
+
+/// A one-shot direct message reminder for the start of an occurrence, requested from a notification's button.
+pub struct Reminder {
+    pub user_id: UserId,
+    pub r#type: NotificationType,
+    pub start: DateTime<Utc>,
+    /// The notification the reminder was requested from.
+    pub message_url: String,
+}
+
+impl FromRow<'_, PgRow> for Reminder {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            user_id: snowflake(row, "user_id")?,
+            r#type: row.try_get("type")?,
+            start: row.try_get("start")?,
+            message_url: row.try_get("message_url")?,
+        })
+    }
+}
+
+/// The custom id of the button that requests a reminder for an occurrence.
+pub fn reminder_custom_id(r#type: NotificationType, start_time: i64) -> String {
+    format!("remind:{type}:{start_time}")
+}
+
+/// Parses a reminder button's custom id into the occurrence it is for.
+pub fn parse_reminder_custom_id(custom_id: &str) -> Option<(NotificationType, i64)> {
+    let mut parts = custom_id.strip_prefix("remind:")?.split(':');
+    let r#type = parts.next()?.parse().ok()?;
+    let start_time = parts.next()?.parse().ok()?;
+    Some((r#type, start_time))
+}
+
+/// Adds a reminder. Returns whether the user did not already have it.
+pub async fn add_reminder(pool: &Pool<Postgres>, reminder: &Reminder) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"insert into reminders (user_id, "type", "start", message_url) values ($1, $2, $3, $4) on conflict do nothing;"#,
+    )
+    .bind(reminder.user_id.get() as i64)
+    .bind(reminder.r#type)
+    .bind(reminder.start)
+    .bind(&reminder.message_url)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Removes a reminder once it is sent, or can never be.
+async fn remove_reminder(pool: &Pool<Postgres>, reminder: &Reminder) {
+    let result = sqlx::query(
+        r#"delete from reminders where user_id = $1 and "type" = $2 and "start" = $3;"#,
+    )
+    .bind(reminder.user_id.get() as i64)
+    .bind(reminder.r#type)
+    .bind(reminder.start)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(user_id = %reminder.user_id, "Failed to remove a reminder: {error:?}");
+    }
+}
+
+/// Sends reminders as they fall due for as long as the process runs.
+///
+/// A reminder is only removed once sent, so one that fails is retried until it is too late to be worth sending.
+pub async fn send_reminders(
+    clients: Arc<DiscordClients>,
+    pool: Pool<Postgres>,
+    dispatcher: Arc<Dispatcher>,
+) {
+    loop {
+        sleep(REMINDER_INTERVAL).await;
+
+        if !is_leader() {
+            continue;
+        }
+
+        // A reminder that is too late is not worth sending, such as after downtime.
+        let result = sqlx::query(r#"delete from reminders where "start" < $1;"#)
+            .bind(Utc::now() - REMINDER_MAXIMUM_LATENESS)
+            .execute(&pool)
+            .await;
+
+        if let Err(error) = result {
+            tracing::error!("Failed to remove late reminders: {error:?}");
+        }
+
+        let reminders: Vec<Reminder> = match sqlx::query_as(
+            r#"select user_id, "type", "start", message_url from reminders where "start" <= now();"#,
+        )
+        .fetch_all(&pool)
+        .await
+        {
+            Ok(reminders) => reminders,
+            Err(error) => {
+                tracing::error!("Failed to retrieve due reminders: {error:?}");
+                continue;
+            }
+        };
+
+        let client = clients.current();
+
+        let futures = reminders.iter().map(|reminder| {
+            let client = &client;
+            let dispatcher = &dispatcher;
+            let pool = &pool;
+
+            async move {
+                let _permit = dispatcher
+                    .acquire(Urgency::Starting, reminder.user_id.get())
+                    .await;
+
+                let message = CreateMessage::new()
+                    .content(format!(
+                        "Reminder: {} is starting now! {}",
+                        reminder.r#type.name(),
+                        reminder.message_url
+                    ))
+                    .flags(MessageFlags::SUPPRESS_EMBEDS);
+
+                let result = match reminder.user_id.create_dm_channel(client.as_ref()).await {
+                    Ok(channel) => channel
+                        .id
+                        .send_message(client.as_ref(), message)
+                        .await
+                        .map(|_| ()),
+                    Err(error) => Err(error),
+                };
+
+                match result {
+                    Ok(()) => {
+                        metrics::increment("reminders_sent");
+                        remove_reminder(pool, reminder).await;
+                    }
+                    // Retrying cannot help a user who does not accept direct messages.
+                    Err(error) if is_closed(&error) => {
+                        tracing::warn!(
+                            user_id = %reminder.user_id,
+                            "Failed to send a reminder: {error:?}"
+                        );
+
+                        remove_reminder(pool, reminder).await;
+                    }
+                    Err(error) => tracing::warn!(
+                        user_id = %reminder.user_id,
+                        "Failed to send a reminder, so it will be retried: {error:?}"
+                    ),
+                }
+            }
+        });
+
+        join_all(futures).await;
+    }
+}
@@ -0,0 +1,609 @@
+use super::{
+    config::Config,
+    international_space_station::{
+        get_international_space_station_schedule, InternationalSpaceStationSchedule,
+    },
+    maintenance_window::{apply_maintenance_windows, get_maintenance_windows, MaintenanceWindow},
+    notification::{NotificationNotify, NotificationType},
+    occurrence_override::{
+        apply_occurrence_overrides, get_occurrence_overrides, OccurrenceOverride,
+    },
+    recurring_event::{get_recurring_events, RecurringEvent},
+    special_visit::{get_upcoming_special_visits, SpecialVisit},
+    subscriber_index::is_subscribed,
+    travelling_spirit::{
+        get_current_travelling_spirit, get_upcoming_travelling_spirits, TravellingSpirit,
+    },
+    weekly_digest::weekly_digest,
+};
+use crate::utility::{shard_eruption::calculate_shard_eruption, wind_paths::ShardEruptionResponse};
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike, Weekday,
+};
+use chrono_tz::{America::Los_Angeles, Tz};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+/// The start times of Aviary's Firework Festival on a day, every 4 hours from midnight.
+pub fn aviarys_firework_festival_occurrences(date: NaiveDate) -> Vec<i64> {
+    // Resolve each local time separately, as the 1st of November may cross a daylight saving change.
+    (0..24)
+        .step_by(4)
+        .filter_map(|hour| {
+            Los_Angeles
+                .from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+                .earliest()
+        })
+        .map(|occurrence| occurrence.timestamp())
+        .collect()
+}
+
+/// The whole minutes from `now` until `start`, or `None` if that cannot be notified of, as after clock skew.
+fn minutes_until(now: DateTime<Tz>, start: DateTime<Tz>, r#type: NotificationType) -> Option<u32> {
+    let minutes = (start - now).num_minutes();
+
+    match u32::try_from(minutes) {
+        Ok(minutes) => Some(minutes),
+        Err(_) => {
+            tracing::warn!(?r#type, minutes, %start, "Skipping an occurrence out of range.");
+            None
+        }
+    }
+}
+
+/// Whether an occurrence this many minutes away is notified of: every minute of the lead time, then at each long-lead mark.
+fn is_notified_minute(config: &Config, r#type: NotificationType, time_until_start: u32) -> bool {
+    time_until_start <= config.lead_time(r#type)
+        || r#type.long_lead_offsets().contains(&time_until_start)
+}
+
+/// Notifies of an event recurring at wall-clock times in Sky time, if an occurrence is within its lead time.
+///
+/// Occurrences are found by stepping through real minutes, so each wall-clock time happens once, at its earliest instant. Times skipped when the clocks go forward do not happen, and times repeated when they go back happen only the first time.
+fn recurring(
+    config: &Config,
+    now: DateTime<Tz>,
+    r#type: NotificationType,
+    is_occurrence: impl Fn(NaiveDateTime) -> bool,
+) -> Option<NotificationNotify> {
+    let (time_until_start, start) = (0..=config.lead_time(r#type))
+        .map(|minutes| (minutes, now + TimeDelta::minutes(minutes.into())))
+        .find(|(_, instant)| {
+            let time = instant.naive_local();
+
+            is_occurrence(time)
+                && Los_Angeles.from_local_datetime(&time).earliest().as_ref() == Some(instant)
+        })?;
+
+    Some(NotificationNotify {
+        r#type,
+        start_time: start.timestamp(),
+        end_time: r#type.end_time(start.timestamp()),
+        time_until_start,
+        shard_eruption: None,
+        travelling_spirit_name: None,
+        special_visit_entities: None,
+        travelling_spirit_details: None,
+        weekly_digest: None,
+        occurrences: None,
+        recurring_event: None,
+        maintenance_end: None,
+        game_update: None,
+        late: false,
+        queued_at: None,
+    })
+}
+
+/// What the scheduler knows about upcoming events, refreshed as the days go by.
+pub struct Schedule {
+    pub shard_eruption: Option<Arc<ShardEruptionResponse>>,
+    pub travelling_spirits: Vec<TravellingSpirit>,
+    /// The travelling spirit visiting today, for the weekly digest.
+    pub current_travelling_spirit: Option<String>,
+    pub special_visits: Vec<SpecialVisit>,
+    pub international_space_station_schedule: InternationalSpaceStationSchedule,
+    pub occurrence_overrides: Vec<OccurrenceOverride>,
+    pub recurring_events: Vec<RecurringEvent>,
+    pub maintenance_windows: Vec<MaintenanceWindow>,
+}
+
+impl Schedule {
+    /// Determines the notifications to send at `now`, a whole minute.
+    ///
+    /// Events that have started are forgotten, so this should be called for every minute in order.
+    pub fn notifications(&mut self, now: DateTime<Tz>, config: &Config) -> Vec<NotificationNotify> {
+        let (day, hour, minute) = (now.day(), now.hour(), now.minute());
+        let mut notification_notifies = vec![];
+
+        if self.shard_eruption.is_none() && hour == 0 && minute == 0 {
+            notification_notifies.push(NotificationNotify {
+                r#type: NotificationType::NoShardEruption,
+                start_time: now.timestamp(),
+                end_time: None,
+                time_until_start: 0,
+                shard_eruption: None,
+                travelling_spirit_name: None,
+                special_visit_entities: None,
+                travelling_spirit_details: None,
+                weekly_digest: None,
+                occurrences: None,
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            });
+        }
+
+        if let Some(ref shard) = self.shard_eruption {
+            let r#type = if shard.strong {
+                NotificationType::ShardEruptionStrong
+            } else {
+                NotificationType::ShardEruptionRegular
+            };
+
+            // Guilds may follow special shard eruptions alone, so they are notified of separately too.
+            let types = if shard.variant.is_some() {
+                vec![r#type, NotificationType::ShardEruptionVariant]
+            } else {
+                vec![r#type]
+            };
+
+            let lead_time = types.iter().map(|r#type| config.lead_time(*r#type)).max();
+
+            // Find a start timestamp that is within the lead time of the shard eruption.
+            let timestamps = shard.timestamps.iter().find(|dates| {
+                let time = dates.start.signed_duration_since(now);
+                (0..=lead_time.unwrap_or_default().into()).contains(&time.num_minutes())
+            });
+
+            if let Some((dates, time_until_start)) =
+                timestamps.and_then(|dates| Some((dates, minutes_until(now, dates.start, r#type)?)))
+            {
+                for r#type in types
+                    .into_iter()
+                    .filter(|r#type| time_until_start <= config.lead_time(*r#type))
+                {
+                    notification_notifies.push(NotificationNotify {
+                        r#type,
+                        start_time: dates.start.timestamp(),
+                        end_time: Some(dates.end.timestamp()),
+                        time_until_start,
+                        shard_eruption: Some(shard.clone()),
+                        travelling_spirit_name: None,
+                        special_visit_entities: None,
+                        travelling_spirit_details: None,
+                        weekly_digest: None,
+                        occurrences: None,
+                        recurring_event: None,
+                        maintenance_end: None,
+                        game_update: None,
+                        late: false,
+                        queued_at: None,
+                    });
+                }
+            }
+
+            // Find an end timestamp that is within the end lead time of the shard eruption.
+            let timestamps = shard.timestamps.iter().find(|dates| {
+                let time = dates.end.signed_duration_since(now);
+                (0..=config.lead_time(NotificationType::ShardEruptionEnd).into())
+                    .contains(&time.num_minutes())
+            });
+
+            if let Some((dates, time_until_start)) = timestamps.and_then(|dates| {
+                Some((
+                    dates,
+                    minutes_until(now, dates.end, NotificationType::ShardEruptionEnd)?,
+                ))
+            }) {
+                notification_notifies.push(NotificationNotify {
+                    r#type: NotificationType::ShardEruptionEnd,
+                    start_time: dates.end.timestamp(),
+                    end_time: Some(dates.end.timestamp()),
+                    time_until_start,
+                    shard_eruption: Some(shard.clone()),
+                    travelling_spirit_name: None,
+                    special_visit_entities: None,
+                    travelling_spirit_details: None,
+                    weekly_digest: None,
+                    occurrences: None,
+                    recurring_event: None,
+                    maintenance_end: None,
+                    game_update: None,
+                    late: false,
+                    queued_at: None,
+                });
+            }
+        }
+
+        notification_notifies.extend(recurring(
+            config,
+            now,
+            NotificationType::DailyReset,
+            |time| time.hour() == 0 && time.minute() == 0,
+        ));
+
+        notification_notifies.extend(recurring(
+            config,
+            now,
+            NotificationType::EyeOfEden,
+            |time| time.weekday() == Weekday::Sun && time.hour() == 0 && time.minute() == 0,
+        ));
+
+        notification_notifies.extend(recurring(
+            config,
+            now,
+            NotificationType::InternationalSpaceStation,
+            |time| {
+                self.international_space_station_schedule
+                    .is_accessible(time.date())
+                    && time.hour() == 0
+                    && time.minute() == 0
+            },
+        ));
+
+        // Spirits that have arrived are done with. Back-to-back visits are notified in sequence.
+        self.travelling_spirits
+            .retain(|travelling_spirit| travelling_spirit.start >= now);
+
+        for travelling_spirit in &self.travelling_spirits {
+            let Some(time_until_start) = minutes_until(
+                now,
+                travelling_spirit.start,
+                NotificationType::TravellingSpirit,
+            )
+            .filter(|time_until_start| {
+                is_notified_minute(
+                    config,
+                    NotificationType::TravellingSpirit,
+                    *time_until_start,
+                )
+            })
+            // Rather than a notification every lead minute, only the arrival and the minutes something is subscribed to are notified of.
+            .filter(|time_until_start| {
+                *time_until_start == 0
+                    || is_subscribed(NotificationType::TravellingSpirit, *time_until_start)
+            }) else {
+                continue;
+            };
+
+            notification_notifies.push(NotificationNotify {
+                r#type: NotificationType::TravellingSpirit,
+                start_time: travelling_spirit.start.timestamp(),
+                end_time: None,
+                time_until_start,
+                shard_eruption: None,
+                travelling_spirit_name: Some(travelling_spirit.entity.clone()),
+                special_visit_entities: None,
+                travelling_spirit_details: travelling_spirit.details.clone(),
+                weekly_digest: None,
+                occurrences: None,
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            });
+        }
+
+        self.special_visits
+            .retain(|special_visit| special_visit.start >= now);
+
+        for special_visit in &self.special_visits {
+            let Some(time_until_start) = minutes_until(
+                now,
+                special_visit.start,
+                NotificationType::SpecialVisit,
+            )
+            .filter(|time_until_start| {
+                is_notified_minute(config, NotificationType::SpecialVisit, *time_until_start)
+            }) else {
+                continue;
+            };
+
+            notification_notifies.push(NotificationNotify {
+                r#type: NotificationType::SpecialVisit,
+                start_time: special_visit.start.timestamp(),
+                end_time: Some(special_visit.end.timestamp()),
+                time_until_start,
+                shard_eruption: None,
+                travelling_spirit_name: None,
+                special_visit_entities: Some(special_visit.entities.clone()),
+                travelling_spirit_details: None,
+                weekly_digest: None,
+                occurrences: None,
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            });
+        }
+
+        // Seasonal activities and the AURORA concert. Those that have ended are done with.
+        self.recurring_events
+            .retain(|recurring_event| !recurring_event.has_ended(now));
+
+        for recurring_event in &self.recurring_events {
+            notification_notifies.extend(
+                recurring(config, now, recurring_event.r#type, |time| {
+                    recurring_event.is_occurrence(time)
+                })
+                .map(|notification_notify| NotificationNotify {
+                    recurring_event: Some(recurring_event.details(notification_notify.start_time)),
+                    ..notification_notify
+                }),
+            );
+        }
+
+        notification_notifies.extend(recurring(
+            config,
+            now,
+            NotificationType::PollutedGeyser,
+            |time| time.hour() % 2 == 0 && time.minute() == 5,
+        ));
+
+        notification_notifies.extend(recurring(config, now, NotificationType::Grandma, |time| {
+            time.hour() % 2 == 0 && time.minute() == 35
+        }));
+
+        notification_notifies.extend(recurring(config, now, NotificationType::Turtle, |time| {
+            time.hour() % 2 == 0 && time.minute() == 50
+        }));
+
+        notification_notifies.extend(recurring(
+            config,
+            now,
+            NotificationType::AviarysFireworkFestival,
+            |time| time.day() == 1 && time.hour() % 4 == 0 && time.minute() == 0,
+        ));
+
+        if now.weekday() == Weekday::Sun && hour == 0 && minute == 0 {
+            notification_notifies.push(NotificationNotify {
+                r#type: NotificationType::WeeklyDigest,
+                start_time: now.timestamp(),
+                end_time: None,
+                time_until_start: 0,
+                shard_eruption: None,
+                travelling_spirit_name: None,
+                special_visit_entities: None,
+                travelling_spirit_details: None,
+                weekly_digest: Some(weekly_digest(
+                    now,
+                    self.shard_eruption.as_deref(),
+                    &self.international_space_station_schedule,
+                    self.current_travelling_spirit.as_deref(),
+                    &self.travelling_spirits,
+                    &config.cdn_url,
+                )),
+                occurrences: None,
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            });
+        }
+
+        if day == 1 && hour == 0 && minute == 0 {
+            let occurrences = aviarys_firework_festival_occurrences(now.date_naive());
+
+            notification_notifies.push(NotificationNotify {
+                r#type: NotificationType::AviarysFireworkFestivalSchedule,
+                start_time: now.timestamp(),
+                end_time: None,
+                time_until_start: 0,
+                shard_eruption: None,
+                travelling_spirit_name: None,
+                special_visit_entities: None,
+                travelling_spirit_details: None,
+                weekly_digest: None,
+                occurrences: Some(occurrences),
+                recurring_event: None,
+                maintenance_end: None,
+                game_update: None,
+                late: false,
+                queued_at: None,
+            });
+        }
+
+        // if minute == 0 || (50..=59).contains(&minute) {
+        //     let time_until_start = (60 - minute) % 60;
+        //     let date = now + Duration::from_secs((time_until_start * 60).into());
+
+        //     notification_notifies.push(NotificationNotify {
+        //         r#type: NotificationType::Dragon,
+        //         start_time: date.timestamp(),
+        //         end_time: None,
+        //         time_until_start,
+        //         shard_eruption: None,
+        //         travelling_spirit_name: None,
+        //         special_visit_entities: None,
+        //         travelling_spirit_details: None,
+        //         weekly_digest: None,
+        //         occurrences: None,
+        //         recurring_event: None,
+        //         maintenance_end: None,
+        //         game_update: None,
+        //         late: false,
+        //         queued_at: None,
+        //     });
+        // }
+
+        apply_occurrence_overrides(
+            config,
+            &self.occurrence_overrides,
+            &mut notification_notifies,
+            now,
+        );
+
+        apply_maintenance_windows(
+            &self.maintenance_windows,
+            &mut notification_notifies,
+            config.maintenance,
+        );
+
+        notification_notifies
+    }
+}
+
+/// Runs the scheduler minute by minute from `from` until `to`, passing each notification it would emit to `each`. Nothing is sent.
+///
+/// Shard eruptions are calculated rather than fetched, as the wind paths only know today's. Everything else comes from the database as it is now.
+pub async fn run_simulation(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    from: DateTime<Tz>,
+    to: DateTime<Tz>,
+    mut each: impl FnMut(DateTime<Tz>, NotificationNotify),
+) -> Result<()> {
+    let mut now = from;
+
+    let mut schedule = Schedule {
+        shard_eruption: calculate_shard_eruption(from.date_naive(), &config.cdn_url).map(Arc::new),
+        travelling_spirits: get_upcoming_travelling_spirits(pool).await?,
+        current_travelling_spirit: get_current_travelling_spirit(pool).await?,
+        special_visits: get_upcoming_special_visits(pool).await?,
+        international_space_station_schedule: get_international_space_station_schedule(
+            pool,
+            from.date_naive(),
+            &config.international_space_station.dates,
+        )
+        .await,
+        occurrence_overrides: get_occurrence_overrides(pool, now).await?,
+        recurring_events: get_recurring_events(pool).await?,
+        maintenance_windows: get_maintenance_windows(pool, now).await?,
+    };
+
+    while now < to {
+        if now.hour() == 0 && now.minute() == 0 {
+            schedule.shard_eruption =
+                calculate_shard_eruption(now.date_naive(), &config.cdn_url).map(Arc::new);
+
+            schedule.international_space_station_schedule =
+                get_international_space_station_schedule(
+                    pool,
+                    now.date_naive(),
+                    &config.international_space_station.dates,
+                )
+                .await;
+        }
+
+        if now.minute() == 0 {
+            schedule.occurrence_overrides = get_occurrence_overrides(pool, now).await?;
+            schedule.maintenance_windows = get_maintenance_windows(pool, now).await?;
+        }
+
+        for notification_notify in schedule.notifications(now, config) {
+            each(now, notification_notify);
+        }
+
+        now += TimeDelta::minutes(1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The local times an hourly event starts at over a day in Sky time, as the scheduler sees it minute by minute.
+    fn hourly_occurrences(date: NaiveDate) -> Vec<NaiveDateTime> {
+        let start = Los_Angeles
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap();
+
+        let end = Los_Angeles
+            .from_local_datetime(&date.succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap())
+            .unwrap();
+
+        let config = Config::default();
+        let mut now = start;
+        let mut occurrences = vec![];
+
+        while now < end {
+            if let Some(notification_notify) =
+                recurring(&config, now, NotificationType::PollutedGeyser, |time| {
+                    time.minute() == 0
+                })
+                .filter(|notification_notify| notification_notify.time_until_start == 0)
+            {
+                occurrences.push(
+                    Los_Angeles
+                        .timestamp_opt(notification_notify.start_time, 0)
+                        .unwrap()
+                        .naive_local(),
+                );
+            }
+
+            now += TimeDelta::minutes(1);
+        }
+
+        occurrences
+    }
+
+    #[test]
+    fn spring_forward_skips_only_the_missing_hour() {
+        let date = NaiveDate::from_ymd_opt(2026, 3, 8).unwrap();
+        let occurrences = hourly_occurrences(date);
+
+        let expected = (0..24)
+            .filter(|hour| *hour != 2)
+            .map(|hour| date.and_hms_opt(hour, 0, 0).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(occurrences, expected);
+    }
+
+    #[test]
+    fn fall_back_fires_the_repeated_hour_once() {
+        let date = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+        let occurrences = hourly_occurrences(date);
+
+        let expected = (0..24)
+            .map(|hour| date.and_hms_opt(hour, 0, 0).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(occurrences, expected);
+    }
+
+    #[test]
+    fn fall_back_fires_the_repeated_hour_at_its_earliest_instant() {
+        let date = NaiveDate::from_ymd_opt(2026, 11, 1).unwrap();
+
+        let first = Los_Angeles
+            .from_local_datetime(&date.and_hms_opt(1, 0, 0).unwrap())
+            .earliest()
+            .unwrap();
+
+        let second = Los_Angeles
+            .from_local_datetime(&date.and_hms_opt(1, 0, 0).unwrap())
+            .latest()
+            .unwrap();
+
+        let config = Config::default();
+        let is_occurrence = |time: NaiveDateTime| time.hour() == 1 && time.minute() == 0;
+
+        assert_eq!(
+            recurring(
+                &config,
+                first,
+                NotificationType::PollutedGeyser,
+                is_occurrence
+            )
+            .map(|notification_notify| notification_notify.start_time),
+            Some(first.timestamp())
+        );
+
+        assert!(recurring(
+            &config,
+            second,
+            NotificationType::PollutedGeyser,
+            is_occurrence
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,265 @@
+use super::{
+    config::Config,
+    notification::{NotificationNotify, NotificationType},
+};
+use crate::utility::{
+    alert::alert,
+    broker,
+    shard_eruption::{calculate_shard_eruption, shard_eruption_mismatch},
+    wind_paths::{shard_eruption, ShardEruptionRawResponse, ShardEruptionResponse},
+};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serenity::model::id::GuildId;
+use sqlx::{types::Json, FromRow, Pool, Postgres};
+use std::sync::Arc;
+
+#[derive(FromRow)]
+pub struct ShardEruptionCachePacket {
+    data: Option<Json<ShardEruptionRawResponse>>,
+}
+
+/// Retrieves today's shard eruption, falling back to the last successful response for today if the wind paths are unavailable.
+pub async fn get_shard_eruption(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    today: NaiveDate,
+) -> Option<Arc<ShardEruptionResponse>> {
+    let data = match fetch_shard_eruption(pool, config, today).await {
+        Ok(data) => {
+            // The wind paths are trusted, but a disagreement usually means the schedule changed.
+            if let Some(mismatch) = shard_eruption_mismatch(
+                data.as_ref(),
+                calculate_shard_eruption(today, &config.cdn_url).as_ref(),
+            ) {
+                alert(config.alert_webhook_url.as_deref(), &mismatch).await;
+            }
+
+            data
+        }
+        Err(error) => {
+            tracing::warn!("Giving up fetching the shard eruption: {error:?}");
+
+            let cached = match get_cached_shard_eruption(pool, today).await {
+                Ok(cached) => cached,
+                Err(error) => {
+                    tracing::error!("Failed to retrieve the cached shard eruption: {error:?}");
+                    return None;
+                }
+            };
+
+            match cached {
+                Some(data) => {
+                    tracing::info!("Using the cached shard eruption for {today}.");
+                    data
+                }
+                None => {
+                    tracing::warn!("There is no cached shard eruption for {today}.");
+                    None
+                }
+            }
+        }
+    };
+
+    data.map(Arc::new)
+}
+
+/// Retrieves the last successful response from the wind paths for a day without fetching it.
+///
+/// The outer `None` means nothing was cached for the day; the inner one, that the day had no shard eruption.
+async fn get_cached_shard_eruption(
+    pool: &Pool<Postgres>,
+    date: NaiveDate,
+) -> Result<Option<Option<ShardEruptionResponse>>, sqlx::Error> {
+    let cached: Option<ShardEruptionCachePacket> =
+        sqlx::query_as(r#"select data from shard_eruption_cache where "date" = $1;"#)
+            .bind(date)
+            .fetch_optional(pool)
+            .await?;
+
+    let Some(cached) = cached else {
+        return Ok(None);
+    };
+
+    let mut data = cached.data.map(|data| ShardEruptionResponse::from(data.0));
+    apply_variant_override(pool, date, &mut data).await;
+    Ok(Some(data))
+}
+
+/// Retrieves the best known shard eruption for a day without fetching it: the last response from the wind paths if there was one, otherwise the calculation.
+pub async fn get_known_shard_eruption(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    date: NaiveDate,
+) -> Result<Option<ShardEruptionResponse>, sqlx::Error> {
+    if let Some(shard_eruption) = get_cached_shard_eruption(pool, date).await? {
+        return Ok(shard_eruption);
+    }
+
+    let mut shard_eruption = calculate_shard_eruption(date, &config.cdn_url);
+    apply_variant_override(pool, date, &mut shard_eruption).await;
+    Ok(shard_eruption)
+}
+
+/// Fetches today's shard eruption again in case the wind paths corrected it, replacing the one in use if so.
+///
+/// The one in use is kept if the wind paths are unavailable. Returns whether it changed.
+pub async fn refresh_shard_eruption(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    today: NaiveDate,
+    shard_eruption: &mut Option<Arc<ShardEruptionResponse>>,
+) -> bool {
+    let data = match fetch_shard_eruption(pool, config, today).await {
+        Ok(data) => data,
+        Err(error) => {
+            tracing::warn!("Giving up refreshing the shard eruption: {error:?}");
+            return false;
+        }
+    };
+
+    if data.as_ref() == shard_eruption.as_deref() {
+        return false;
+    }
+
+    alert(
+        config.alert_webhook_url.as_deref(),
+        &format!(
+            "The shard eruption for {today} changed from {} to {}.",
+            describe(shard_eruption.as_deref()),
+            describe(data.as_ref())
+        ),
+    )
+    .await;
+
+    broker::publish(
+        "shard_eruption.changed",
+        &serde_json::json!({ "date": today, "previous": shard_eruption, "current": data }),
+    )
+    .await;
+
+    *shard_eruption = data.map(Arc::new);
+    true
+}
+
+/// Builds the correction sent to guilds that opted in once today's shard eruption changes.
+pub fn shard_eruption_correction(
+    now: DateTime<Tz>,
+    shard_eruption: Option<Arc<ShardEruptionResponse>>,
+) -> NotificationNotify {
+    NotificationNotify {
+        r#type: NotificationType::ShardEruptionCorrection,
+        start_time: now.timestamp(),
+        end_time: None,
+        time_until_start: 0,
+        shard_eruption,
+        travelling_spirit_name: None,
+        special_visit_entities: None,
+        travelling_spirit_details: None,
+        weekly_digest: None,
+        occurrences: None,
+        recurring_event: None,
+        maintenance_end: None,
+        game_update: None,
+        late: false,
+        queued_at: None,
+    }
+}
+
+/// Remembers that a guild was notified of today's shard eruption, so it can be told of corrections. Failing to do so is logged.
+pub async fn record_shard_eruption_delivery(pool: &Pool<Postgres>, guild_id: GuildId) {
+    let today = Utc::now()
+        .with_timezone(&chrono_tz::America::Los_Angeles)
+        .date_naive();
+
+    let result = sqlx::query(
+        r#"insert into shard_eruption_deliveries (guild_id, "date") values ($1, $2) on conflict (guild_id) do update set "date" = excluded."date";"#,
+    )
+    .bind(guild_id.get() as i64)
+    .bind(today)
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::error!(%guild_id, "Failed to record a shard eruption delivery: {error:?}");
+    }
+}
+
+/// Fetches today's shard eruption from the wind paths and caches it.
+async fn fetch_shard_eruption(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    today: NaiveDate,
+) -> Result<Option<ShardEruptionResponse>> {
+    let data = shard_eruption(&config.wind_paths_url).await?;
+
+    let result = sqlx::query(
+        r#"insert into shard_eruption_cache ("date", data) values ($1, $2) on conflict ("date") do update set data = excluded.data;"#,
+    )
+    .bind(today)
+    .bind(data.as_ref().map(Json))
+    .execute(pool)
+    .await;
+
+    if let Err(error) = result {
+        tracing::warn!("Failed to cache the shard eruption: {error:?}");
+    }
+
+    let mut data = data.map(ShardEruptionResponse::from);
+    apply_variant_override(pool, today, &mut data).await;
+    Ok(data)
+}
+
+/// Marks a day's shard eruption with the variant recorded for the day, unless the wind paths gave one.
+async fn apply_variant_override(
+    pool: &Pool<Postgres>,
+    today: NaiveDate,
+    shard_eruption: &mut Option<ShardEruptionResponse>,
+) {
+    let Some(shard_eruption) = shard_eruption else {
+        return;
+    };
+
+    if shard_eruption.variant.is_some() {
+        return;
+    }
+
+    match sqlx::query_scalar(r#"select variant from shard_eruption_variants where "date" = $1;"#)
+        .bind(today)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(variant) => shard_eruption.variant = variant,
+        Err(error) => {
+            tracing::warn!("Failed to retrieve the shard eruption variant: {error:?}");
+        }
+    }
+}
+
+fn describe(shard_eruption: Option<&ShardEruptionResponse>) -> String {
+    match shard_eruption {
+        Some(shard_eruption) => format!(
+            "{}{} in {} ({}) landing at {}",
+            if shard_eruption.strong {
+                "strong"
+            } else {
+                "regular"
+            },
+            shard_eruption
+                .variant
+                .as_ref()
+                .map(|variant| format!(" {variant}"))
+                .unwrap_or_default(),
+            shard_eruption.realm,
+            shard_eruption.sky_map,
+            shard_eruption
+                .timestamps
+                .iter()
+                .map(|dates| dates.start.format("%H:%M:%S").to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => "none".to_string(),
+    }
+}
@@ -0,0 +1,40 @@
+use crate::utility::{constants::CATCH_UP_MAXIMUM, database::with_retry};
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+
+#[derive(FromRow)]
+pub struct SpecialVisitPacket {
+    entities: Vec<String>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+/// A group of returning spirits visiting together.
+pub struct SpecialVisit {
+    pub entities: Vec<String>,
+    pub start: DateTime<chrono_tz::Tz>,
+    pub end: DateTime<chrono_tz::Tz>,
+}
+
+/// Retrieves every special visit that has yet to begin, or began recently enough to catch up on, soonest first.
+pub async fn get_upcoming_special_visits(
+    pool: &sqlx::PgPool,
+) -> Result<Vec<SpecialVisit>, sqlx::Error> {
+    let rows: Vec<SpecialVisitPacket> = with_retry("special visits", || {
+        sqlx::query_as(
+            r#"select entities, "start", "end" from special_visits where "start" >= now() - make_interval(mins => $1) order by "start";"#,
+        )
+        .bind(CATCH_UP_MAXIMUM as i32)
+        .fetch_all(pool)
+    })
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SpecialVisit {
+            entities: row.entities,
+            start: row.start.with_timezone(&chrono_tz::America::Los_Angeles),
+            end: row.end.with_timezone(&chrono_tz::America::Los_Angeles),
+        })
+        .collect())
+}
@@ -0,0 +1,147 @@
+use super::notification::NotificationType;
+use crate::utility::constants::{SUBSCRIBER_INDEX_LISTEN_RETRY, SUBSCRIBER_INDEX_REFRESH_INTERVAL};
+use sqlx::{postgres::PgListener, Pool, Postgres};
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        LazyLock, RwLock,
+    },
+};
+use tokio::{sync::Notify, time::sleep};
+
+/// Every type and offset something is subscribed to, or `None` until loaded.
+static SUBSCRIBED: RwLock<Option<HashSet<(NotificationType, u32)>>> = RwLock::new(None);
+
+/// Counts changes to subscriptions, so a reload that started before one does not overwrite its invalidation.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Wakes the reload early when subscriptions change.
+static CHANGED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Whether changes made by other processes are being heard of. Until they are, the index may be stale.
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+/// The channel the database notifies on whenever subscriptions change.
+const CHANNEL: &str = "subscriptions_changed";
+
+/// Whether anything receives notifications of a type this many minutes before an occurrence.
+///
+/// Until the index is loaded, while it is reloaded after a change, or while changes cannot be heard of, everything is assumed to be subscribed to, so nothing is missed.
+pub fn is_subscribed(r#type: NotificationType, time_until_start: u32) -> bool {
+    if !LISTENING.load(Ordering::Relaxed) {
+        return true;
+    }
+
+    let subscribed = SUBSCRIBED.read().unwrap();
+
+    let Some(subscribed) = subscribed.as_ref() else {
+        return true;
+    };
+
+    subscribed.contains(&(r#type, time_until_start))
+        || r#type
+            .bundle()
+            .is_some_and(|bundle| subscribed.contains(&(bundle, time_until_start)))
+}
+
+/// Forgets the index after a subscription is added or changed, so nothing is skipped until it is reloaded, and reloads it at once.
+///
+/// Other processes hear of the change from the database.
+pub fn invalidate_subscriber_index() {
+    let mut subscribed = SUBSCRIBED.write().unwrap();
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    *subscribed = None;
+    drop(subscribed);
+    CHANGED.notify_one();
+}
+
+/// Periodically reloads which types and offsets are subscribed to, keeping the last index if that fails.
+///
+/// Changes from any process are heard of through the database, which reloads the index early.
+pub async fn maintain_subscriber_index(pool: Pool<Postgres>) {
+    tokio::spawn(listen(pool.clone()));
+
+    loop {
+        let generation = GENERATION.load(Ordering::Relaxed);
+
+        match load(&pool).await {
+            Ok(loaded) => {
+                let mut subscribed = SUBSCRIBED.write().unwrap();
+
+                if GENERATION.load(Ordering::Relaxed) == generation {
+                    *subscribed = Some(loaded);
+                }
+            }
+            Err(error) => tracing::error!("Error loading the subscriber index: {error:?}"),
+        }
+
+        tokio::select! {
+            () = sleep(SUBSCRIBER_INDEX_REFRESH_INTERVAL) => {}
+            () = CHANGED.notified() => {}
+        }
+    }
+}
+
+/// Invalidates the index whenever the database notifies of a change.
+///
+/// Changes made while the connection is lost are not heard of, so the index is invalidated again once listening resumes.
+async fn listen(pool: Pool<Postgres>) {
+    loop {
+        let mut listener = match PgListener::connect_with(&pool).await {
+            Ok(listener) => listener,
+            Err(error) => {
+                tracing::error!("Error connecting to listen for subscription changes: {error:?}");
+                sleep(SUBSCRIBER_INDEX_LISTEN_RETRY).await;
+                continue;
+            }
+        };
+
+        if let Err(error) = listener.listen(CHANNEL).await {
+            tracing::error!("Error listening for subscription changes: {error:?}");
+            sleep(SUBSCRIBER_INDEX_LISTEN_RETRY).await;
+            continue;
+        }
+
+        LISTENING.store(true, Ordering::Relaxed);
+        invalidate_subscriber_index();
+
+        loop {
+            match listener.try_recv().await {
+                Ok(Some(_)) => invalidate_subscriber_index(),
+                Ok(None) => {
+                    tracing::warn!("Lost the connection listening for subscription changes.");
+                    break;
+                }
+                Err(error) => {
+                    tracing::error!("Error receiving subscription changes: {error:?}");
+                    break;
+                }
+            }
+        }
+
+        LISTENING.store(false, Ordering::Relaxed);
+        sleep(SUBSCRIBER_INDEX_LISTEN_RETRY).await;
+    }
+}
+
+/// Mirrors what guild subscriptions, direct messages, and webhooks each match on when sent.
+///
+/// Read from the primary, as a replica may not have caught up with the change that invalidated the index.
+async fn load(pool: &Pool<Postgres>) -> Result<HashSet<(NotificationType, u32)>, sqlx::Error> {
+    let rows: Vec<(i16, i16)> = sqlx::query_as(
+        r#"select "type", "offset" from notifications where sendable is true and cardinality(countdown) = 0 and delivery_mode in (0, 2) union select "type", 0::smallint from notifications where sendable is true and cardinality(countdown) = 0 and delivery_mode in (1, 2) union select "type", unnest(countdown) from notifications where sendable is true union select "type", "offset" from user_subscriptions where sendable is true union select "type", "offset" from webhooks where sendable is true;"#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(r#type, offset)| {
+            Some((
+                NotificationType::try_from(r#type).ok()?,
+                u32::try_from(offset).ok()?,
+            ))
+        })
+        .collect())
+}
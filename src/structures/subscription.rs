@@ -0,0 +1,494 @@
+use super::{
+    config::Config,
+    guild_pause::get_pause,
+    notification::{optional_snowflake, snowflake, NotificationType},
+    realm_role::{get_guild_realm_roles, RealmRole},
+    subscriber_index::invalidate_subscriber_index,
+};
+use crate::utility::{
+    constants::{COUNTDOWN_MAXIMUM_LENGTH, REALMS},
+    database::with_retry,
+    emoji::is_valid_emoji,
+    functions::format_list,
+    template::validate_template,
+};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use sqlx::{postgres::PgRow, FromRow, PgExecutor, Pool, Postgres, Row, Transaction};
+use std::collections::{BTreeMap, HashSet};
+
+/// Which notifications of an occurrence a subscription receives.
+#[derive(
+    async_graphql::Enum,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Eq,
+    PartialEq,
+    Serialize,
+    sqlx::Type,
+)]
+#[repr(i16)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    /// At the offset only, which is the start if there is no offset.
+    #[default]
+    Lead = 0,
+    /// At the start only, whatever the offset.
+    Start = 1,
+    /// At the offset, then again at the start.
+    Both = 2,
+}
+
+/// A guild's subscription to a notification type, as managed through slash commands.
+///
+/// Also the format subscriptions are exported and imported in.
+#[derive(Deserialize, Serialize)]
+pub struct Subscription {
+    pub r#type: NotificationType,
+    pub channel_id: ChannelId,
+    pub role_id: Option<RoleId>,
+    pub offset: i16,
+    /// Whether each event gets its own thread for follow-ups.
+    pub threaded: bool,
+    pub silent: bool,
+    pub template: Option<String>,
+    pub emoji: Option<String>,
+    /// The minimum number of minutes between the occurrences notified.
+    #[serde(default)]
+    pub minimum_interval: Option<i32>,
+    #[serde(default)]
+    pub on_the_hour: bool,
+    #[serde(default)]
+    pub delivery_mode: DeliveryMode,
+    /// Offsets to count down through, such as 1440, 60, 15, and 0, instead of the offset and delivery mode.
+    #[serde(default)]
+    pub countdown: Vec<i16>,
+    /// Whether notifications come with images and details, such as a travelling spirit's items or the shard map.
+    #[serde(default)]
+    pub rich: bool,
+    /// Whether notifications are sent, which they stop being once the bot cannot send to the channel.
+    #[serde(default = "sendable")]
+    pub sendable: bool,
+    /// Why notifications are not sent, if the bot found a problem with the channel.
+    #[serde(default)]
+    pub unsendable_reason: Option<String>,
+}
+
+fn sendable() -> bool {
+    true
+}
+
+/// Everything a guild has set up, in the format it is exported and imported in.
+#[derive(Deserialize, Serialize)]
+pub struct GuildConfiguration {
+    pub subscriptions: Vec<Subscription>,
+    /// Roles mentioned for shard eruptions, by realm.
+    #[serde(default)]
+    pub realm_roles: BTreeMap<String, RealmRole>,
+    #[serde(default)]
+    pub paused_until: Option<DateTime<Utc>>,
+}
+
+impl Subscription {
+    /// Checks the subscription is valid, whether it came from the slash commands, the dashboard, or an import.
+    pub fn validate(&self, config: &Config) -> Result<()> {
+        if !self.r#type.allows_offset(config, self.offset) {
+            bail!("{}", self.r#type.describe_offsets(config));
+        }
+
+        if !self.countdown.is_empty()
+            && (self.offset != 0 || self.delivery_mode != DeliveryMode::Lead)
+        {
+            bail!("A countdown replaces the offset and delivery mode, so cannot be combined with them.");
+        }
+
+        validate_countdown(config, self.r#type, &self.countdown)?;
+
+        if self.delivery_mode == DeliveryMode::Both && self.offset == 0 {
+            bail!("Sending in advance and at the start needs an offset.");
+        }
+
+        if let Some(template) = &self.template {
+            validate_template(template)?;
+        }
+
+        if self
+            .emoji
+            .as_deref()
+            .is_some_and(|emoji| !is_valid_emoji(emoji))
+        {
+            bail!(
+                "{} is not a valid emoji.",
+                self.emoji.as_deref().unwrap_or_default()
+            );
+        }
+
+        if self
+            .minimum_interval
+            .is_some_and(|minimum_interval| minimum_interval <= 0)
+        {
+            bail!("The minimum interval must be positive.");
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for Subscription {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            r#type: row.try_get("type")?,
+            channel_id: snowflake(row, "channel_id")?,
+            role_id: optional_snowflake(row, "role_id")?,
+            offset: row.try_get("offset")?,
+            threaded: row.try_get("threaded")?,
+            silent: row.try_get("silent")?,
+            template: row.try_get("template")?,
+            emoji: row.try_get("emoji")?,
+            minimum_interval: row.try_get("minimum_interval")?,
+            on_the_hour: row.try_get("on_the_hour")?,
+            delivery_mode: row.try_get("delivery_mode")?,
+            countdown: row.try_get("countdown")?,
+            rich: row.try_get("rich")?,
+            sendable: row.try_get("sendable")?,
+            unsendable_reason: row.try_get("unsendable_reason")?,
+        })
+    }
+}
+
+/// Checks every offset of a countdown is allowed, and that there are not too many.
+fn validate_countdown(config: &Config, r#type: NotificationType, countdown: &[i16]) -> Result<()> {
+    if countdown.len() > COUNTDOWN_MAXIMUM_LENGTH {
+        bail!("A countdown may have at most {COUNTDOWN_MAXIMUM_LENGTH} offsets.");
+    }
+
+    if let Some(offset) = countdown
+        .iter()
+        .find(|offset| !r#type.allows_offset(config, **offset))
+    {
+        bail!(
+            "{offset} minutes is not allowed. {}",
+            r#type.describe_offsets(config)
+        );
+    }
+
+    if countdown.iter().collect::<HashSet<_>>().len() < countdown.len() {
+        bail!("A countdown may not repeat an offset.");
+    }
+
+    Ok(())
+}
+
+/// Describes a countdown, such as "1440, 60, and 0 minutes in advance", largest first.
+pub fn describe_countdown(countdown: &[i16]) -> String {
+    let mut offsets = countdown.to_vec();
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+
+    format!(
+        "{} minutes in advance",
+        format_list(&offsets.iter().map(i16::to_string).collect::<Vec<_>>())
+    )
+}
+
+/// Subscribes a guild to a notification type, replacing any existing subscription to it.
+pub async fn subscribe(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    subscription: &Subscription,
+) -> Result<(), sqlx::Error> {
+    insert(pool, guild_id, subscription).await?;
+    invalidate_subscriber_index();
+    Ok(())
+}
+
+/// Replaces all of a guild's subscriptions at once, as when editing them on the dashboard.
+pub async fn replace_subscriptions(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    guild_id: GuildId,
+    subscriptions: &[Subscription],
+) -> Result<()> {
+    validate_subscriptions(config, subscriptions)?;
+    let mut transaction = pool.begin().await?;
+    replace(&mut transaction, guild_id, subscriptions).await?;
+    transaction.commit().await?;
+    invalidate_subscriber_index();
+    Ok(())
+}
+
+/// Retrieves everything a guild has set up, to export it.
+pub async fn get_guild_configuration(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+) -> Result<GuildConfiguration, sqlx::Error> {
+    Ok(GuildConfiguration {
+        subscriptions: get_subscriptions(pool, guild_id).await?,
+        realm_roles: get_guild_realm_roles(pool, guild_id).await?,
+        paused_until: get_pause(pool, guild_id).await?,
+    })
+}
+
+/// Replaces everything a guild has set up with an exported configuration, all at once.
+pub async fn replace_guild_configuration(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    guild_id: GuildId,
+    guild_configuration: &GuildConfiguration,
+) -> Result<()> {
+    validate_subscriptions(config, &guild_configuration.subscriptions)?;
+
+    if let Some(realm) = guild_configuration
+        .realm_roles
+        .keys()
+        .find(|realm| !REALMS.contains(&realm.as_str()))
+    {
+        bail!("{realm} is not a realm.");
+    }
+
+    let mut transaction = pool.begin().await?;
+    replace(
+        &mut transaction,
+        guild_id,
+        &guild_configuration.subscriptions,
+    )
+    .await?;
+
+    sqlx::query("delete from shard_eruption_realm_roles where guild_id = $1;")
+        .bind(guild_id.get() as i64)
+        .execute(&mut *transaction)
+        .await?;
+
+    for (realm, realm_role) in &guild_configuration.realm_roles {
+        sqlx::query(
+            "insert into shard_eruption_realm_roles (guild_id, realm, role_id, replace) values ($1, $2, $3, $4);",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(realm)
+        .bind(realm_role.role_id.get() as i64)
+        .bind(realm_role.replace)
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    sqlx::query("delete from guild_pauses where guild_id = $1;")
+        .bind(guild_id.get() as i64)
+        .execute(&mut *transaction)
+        .await?;
+
+    if let Some(paused_until) = guild_configuration.paused_until {
+        sqlx::query("insert into guild_pauses (guild_id, paused_until) values ($1, $2);")
+            .bind(guild_id.get() as i64)
+            .bind(paused_until)
+            .execute(&mut *transaction)
+            .await?;
+    }
+
+    transaction.commit().await?;
+    invalidate_subscriber_index();
+    Ok(())
+}
+
+/// Checks each subscription is valid, and that no type is subscribed to twice.
+fn validate_subscriptions(config: &Config, subscriptions: &[Subscription]) -> Result<()> {
+    let mut types = HashSet::new();
+
+    for subscription in subscriptions {
+        if !types.insert(subscription.r#type) {
+            bail!(
+                "{} notifications are subscribed to more than once.",
+                subscription.r#type.name()
+            );
+        }
+
+        subscription.validate(config)?;
+    }
+
+    Ok(())
+}
+
+/// Deletes a guild's subscriptions and inserts others in their place, as they are.
+async fn replace(
+    transaction: &mut Transaction<'_, Postgres>,
+    guild_id: GuildId,
+    subscriptions: &[Subscription],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("delete from notifications where guild_id = $1;")
+        .bind(guild_id.get() as i64)
+        .execute(&mut **transaction)
+        .await?;
+
+    for subscription in subscriptions {
+        insert(&mut **transaction, guild_id, subscription).await?;
+    }
+
+    Ok(())
+}
+
+/// Inserts a subscription, replacing the whole of any existing one to the type.
+async fn insert(
+    executor: impl PgExecutor<'_>,
+    guild_id: GuildId,
+    subscription: &Subscription,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"insert into notifications (guild_id, "type", channel_id, role_id, "offset", threaded, silent, template, emoji, minimum_interval, on_the_hour, delivery_mode, countdown, rich, sendable, unsendable_reason) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) on conflict (guild_id, "type") do update set channel_id = excluded.channel_id, role_id = excluded.role_id, "offset" = excluded."offset", threaded = excluded.threaded, silent = excluded.silent, template = excluded.template, emoji = excluded.emoji, minimum_interval = excluded.minimum_interval, on_the_hour = excluded.on_the_hour, delivery_mode = excluded.delivery_mode, countdown = excluded.countdown, rich = excluded.rich, sendable = excluded.sendable, unsendable_reason = excluded.unsendable_reason, last_occurrence = null;"#,
+    )
+    .bind(guild_id.get() as i64)
+    .bind(subscription.r#type)
+    .bind(subscription.channel_id.get() as i64)
+    .bind(subscription.role_id.map(|role_id| role_id.get() as i64))
+    .bind(subscription.offset)
+    .bind(subscription.threaded)
+    .bind(subscription.silent)
+    .bind(&subscription.template)
+    .bind(&subscription.emoji)
+    .bind(subscription.minimum_interval)
+    .bind(subscription.on_the_hour)
+    .bind(subscription.delivery_mode)
+    .bind(&subscription.countdown)
+    .bind(subscription.rich)
+    .bind(subscription.sendable)
+    .bind(&subscription.unsendable_reason)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Unsubscribes a guild from a notification type. Returns whether it was subscribed.
+pub async fn unsubscribe(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    r#type: NotificationType,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(r#"delete from notifications where guild_id = $1 and "type" = $2;"#)
+        .bind(guild_id.get() as i64)
+        .bind(r#type)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Moves every subscription in a channel to another, re-enabling them. Returns how many were moved.
+pub async fn migrate_channel(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+    from: ChannelId,
+    to: ChannelId,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "update notifications set channel_id = $3, sendable = true, unsendable_reason = null where guild_id = $1 and channel_id = $2;",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(from.get() as i64)
+    .bind(to.get() as i64)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        invalidate_subscriber_index();
+    }
+
+    Ok(result.rows_affected())
+}
+
+pub async fn get_subscriptions(
+    pool: &Pool<Postgres>,
+    guild_id: GuildId,
+) -> Result<Vec<Subscription>, sqlx::Error> {
+    with_retry("subscriptions", || {
+        sqlx::query_as(
+            r#"select "type", channel_id, role_id, "offset", threaded, silent, template, emoji, minimum_interval, on_the_hour, delivery_mode, countdown, rich, sendable, unsendable_reason from notifications where guild_id = $1 order by "type";"#,
+        )
+        .bind(guild_id.get() as i64)
+        .fetch_all(pool)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription_to(r#type: NotificationType) -> Subscription {
+        Subscription {
+            r#type,
+            channel_id: ChannelId::new(1),
+            role_id: None,
+            offset: 0,
+            threaded: false,
+            silent: false,
+            template: None,
+            emoji: None,
+            minimum_interval: None,
+            on_the_hour: false,
+            delivery_mode: DeliveryMode::Lead,
+            countdown: vec![],
+            rich: false,
+            sendable: true,
+            unsendable_reason: None,
+        }
+    }
+
+    #[test]
+    fn accepts_a_countdown_through_long_lead_offsets() {
+        let config = Config::default();
+
+        let subscription = Subscription {
+            countdown: vec![1440, 60, 15, 0],
+            ..subscription_to(NotificationType::TravellingSpirit)
+        };
+
+        assert!(subscription.validate(&config).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_countdowns() {
+        let config = Config::default();
+
+        for countdown in [vec![15, 15], vec![10, 8, 6, 4, 2, 0], vec![1440], vec![-1]] {
+            let subscription = Subscription {
+                countdown: countdown.clone(),
+                ..subscription_to(NotificationType::Aurora)
+            };
+
+            assert!(subscription.validate(&config).is_err(), "{countdown:?}");
+        }
+
+        let subscription = Subscription {
+            offset: 5,
+            countdown: vec![10, 0],
+            ..subscription_to(NotificationType::Aurora)
+        };
+
+        assert!(subscription.validate(&config).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_emoji() {
+        let config = Config::default();
+
+        let subscription = Subscription {
+            emoji: Some("shard".to_string()),
+            ..subscription_to(NotificationType::Aurora)
+        };
+
+        assert_eq!(
+            subscription.validate(&config).unwrap_err().to_string(),
+            "shard is not a valid emoji."
+        );
+
+        let subscription = Subscription {
+            emoji: Some("<:shard:123>".to_string()),
+            ..subscription_to(NotificationType::Aurora)
+        };
+
+        assert!(subscription.validate(&config).is_ok());
+    }
+}
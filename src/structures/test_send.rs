@@ -0,0 +1,182 @@
+use super::{
+    config::Config,
+    game_update::get_latest_game_update,
+    international_space_station::get_international_space_station_schedule,
+    notification::{Notification, NotificationNotify, NotificationType, RenderedText},
+    recurring_event::get_recurring_events,
+    schedule::aviarys_firework_festival_occurrences,
+    special_visit::get_upcoming_special_visits,
+    travelling_spirit::{get_current_travelling_spirit, get_upcoming_travelling_spirits},
+    weekly_digest::weekly_digest,
+};
+use crate::utility::shard_eruption::calculate_shard_eruption;
+use anyhow::{bail, Context, Result};
+use chrono::{TimeDelta, Timelike, Utc};
+use chrono_tz::America::Los_Angeles;
+use serenity::{
+    all::Message,
+    http::Http,
+    model::id::{ChannelId, GuildId},
+};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+/// Sends a notification of a type to a channel at once, bypassing the schedule.
+///
+/// The guild's subscription to the type supplies the template and emoji. Upcoming or calculated events stand in for the occurrence.
+pub async fn test_send(
+    client: &Http,
+    pool: &Pool<Postgres>,
+    config: &Config,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    r#type: NotificationType,
+) -> Result<Message> {
+    let notification = Notification::for_test(pool, guild_id, channel_id, r#type).await?;
+    let notification_notify = example(pool, config, r#type).await?;
+    let rendered = RenderedText::new(&notification_notify, config.flavor_text);
+
+    notification
+        .send(client, pool, config, &notification_notify, &rendered)
+        .await
+}
+
+/// Builds an occurrence of a type as though its notifications were starting now.
+async fn example(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    r#type: NotificationType,
+) -> Result<NotificationNotify> {
+    // Bundles are never scheduled themselves, so stand in one of their events.
+    let r#type = match r#type {
+        NotificationType::WaxEvents => NotificationType::PollutedGeyser,
+        r#type => r#type,
+    };
+
+    let now = Utc::now()
+        .with_timezone(&Los_Angeles)
+        .with_nanosecond(0)
+        .unwrap();
+
+    let today = now.date_naive();
+    let time_until_start = config.lead_time(r#type);
+    let start_time = (now + TimeDelta::minutes(time_until_start.into())).timestamp();
+
+    let mut notification_notify = NotificationNotify {
+        r#type,
+        start_time,
+        end_time: r#type.end_time(start_time),
+        time_until_start,
+        shard_eruption: None,
+        travelling_spirit_name: None,
+        special_visit_entities: None,
+        travelling_spirit_details: None,
+        weekly_digest: None,
+        occurrences: None,
+        recurring_event: None,
+        maintenance_end: None,
+        game_update: None,
+        late: false,
+        queued_at: None,
+    };
+
+    match r#type {
+        NotificationType::ShardEruptionRegular
+        | NotificationType::ShardEruptionStrong
+        | NotificationType::ShardEruptionVariant
+        | NotificationType::ShardEruptionEnd
+        | NotificationType::ShardEruptionCorrection => {
+            let mut shard_eruption = (0..14)
+                .filter_map(|days| {
+                    calculate_shard_eruption(today + TimeDelta::days(days), &config.cdn_url)
+                })
+                .find(|shard_eruption| match r#type {
+                    NotificationType::ShardEruptionRegular => !shard_eruption.strong,
+                    NotificationType::ShardEruptionStrong => shard_eruption.strong,
+                    _ => true,
+                })
+                .context("No shard eruption was calculated in the next 2 weeks.")?;
+
+            let duration = shard_eruption
+                .timestamps
+                .first()
+                .map(|dates| (dates.end - dates.start).num_seconds())
+                .unwrap_or_default();
+
+            // Variants are never calculated, so stand one in.
+            if r#type == NotificationType::ShardEruptionVariant {
+                shard_eruption.variant = Some("memory".to_string());
+            }
+
+            notification_notify.end_time = Some(start_time + duration);
+            notification_notify.shard_eruption = Some(Arc::new(shard_eruption));
+        }
+        NotificationType::TravellingSpirit => {
+            let name = match get_upcoming_travelling_spirits(pool)
+                .await?
+                .into_iter()
+                .next()
+            {
+                Some(travelling_spirit) => Some(travelling_spirit.entity),
+                None => get_current_travelling_spirit(pool).await?,
+            };
+
+            let Some(name) = name else {
+                bail!("There is no travelling spirit to use as an example.");
+            };
+
+            notification_notify.travelling_spirit_name = Some(name);
+        }
+        NotificationType::SpecialVisit => {
+            let Some(special_visit) = get_upcoming_special_visits(pool).await?.into_iter().next()
+            else {
+                bail!("There is no upcoming special visit to use as an example.");
+            };
+
+            notification_notify.end_time =
+                Some(start_time + (special_visit.end - special_visit.start).num_seconds());
+            notification_notify.special_visit_entities = Some(special_visit.entities);
+        }
+        NotificationType::WeeklyDigest => {
+            let international_space_station_schedule = get_international_space_station_schedule(
+                pool,
+                today,
+                &config.international_space_station.dates,
+            )
+            .await;
+
+            notification_notify.weekly_digest = Some(weekly_digest(
+                now,
+                calculate_shard_eruption(today, &config.cdn_url).as_ref(),
+                &international_space_station_schedule,
+                get_current_travelling_spirit(pool).await?.as_deref(),
+                &get_upcoming_travelling_spirits(pool).await?,
+                &config.cdn_url,
+            ));
+        }
+        NotificationType::SeasonalActivity => {
+            let Some(recurring_event) = get_recurring_events(pool)
+                .await?
+                .into_iter()
+                .find(|recurring_event| recurring_event.r#type == r#type)
+            else {
+                bail!("There is no current or upcoming seasonal activity to use as an example.");
+            };
+
+            notification_notify.recurring_event = Some(recurring_event.details(start_time));
+        }
+        NotificationType::GameUpdate => {
+            let Some(game_update) = get_latest_game_update(pool).await? else {
+                bail!("There is no game update to use as an example.");
+            };
+
+            notification_notify.game_update = Some(game_update);
+        }
+        NotificationType::AviarysFireworkFestivalSchedule => {
+            notification_notify.occurrences = Some(aviarys_firework_festival_occurrences(today));
+        }
+        _ => {}
+    }
+
+    Ok(notification_notify)
+}
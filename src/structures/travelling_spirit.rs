@@ -1,5 +1,12 @@
+use super::config::Config;
+use crate::utility::{
+    constants::{CATCH_UP_MAXIMUM, TRAVELLING_SPIRIT_MAXIMUM_HORIZON},
+    database::with_retry,
+    spirits::{spirit_details, SpiritDetails},
+};
 use chrono::{DateTime, Utc};
 use sqlx::FromRow;
+use std::sync::Arc;
 
 #[derive(FromRow)]
 pub struct TravellingSpiritPacket {
@@ -10,18 +17,74 @@ pub struct TravellingSpiritPacket {
 pub struct TravellingSpirit {
     pub entity: String,
     pub start: DateTime<chrono_tz::Tz>,
+    pub details: Option<Arc<SpiritDetails>>,
 }
 
-pub async fn get_last_travelling_spirit(pool: &sqlx::PgPool) -> TravellingSpirit {
-    let row: TravellingSpiritPacket = sqlx::query_as(
-        r#"select "entity", "start" from travelling_spirits order by visit desc limit 1;"#,
-    )
-    .fetch_one(pool)
+/// Retrieves every travelling spirit that has yet to arrive, or arrived recently enough to catch up on, soonest first.
+///
+/// Rows without a name or too far ahead are skipped, as they cannot be notified of sensibly.
+pub async fn get_upcoming_travelling_spirits(
+    pool: &sqlx::PgPool,
+) -> Result<Vec<TravellingSpirit>, sqlx::Error> {
+    let rows: Vec<TravellingSpiritPacket> = with_retry("travelling spirits", || {
+        sqlx::query_as(
+            r#"select "entity", "start" from travelling_spirits where "start" >= now() - make_interval(mins => $1) order by "start";"#,
+        )
+        .bind(CATCH_UP_MAXIMUM as i32)
+        .fetch_all(pool)
+    })
+    .await?;
+
+    let horizon = Utc::now() + TRAVELLING_SPIRIT_MAXIMUM_HORIZON;
+
+    Ok(rows
+        .into_iter()
+        .filter(|row| {
+            let valid = !row.entity.trim().is_empty() && row.start <= horizon;
+
+            if !valid {
+                tracing::warn!(
+                    entity = row.entity,
+                    start = %row.start,
+                    "Skipping an invalid travelling spirit."
+                );
+            }
+
+            valid
+        })
+        .map(|row| TravellingSpirit {
+            entity: row.entity,
+            start: row.start.with_timezone(&chrono_tz::America::Los_Angeles),
+            details: None,
+        })
+        .collect())
+}
+
+/// Retrieves the travelling spirit visiting now, if any. Visits last from Thursday until the end of Monday.
+pub async fn get_current_travelling_spirit(
+    pool: &sqlx::PgPool,
+) -> Result<Option<String>, sqlx::Error> {
+    with_retry("current travelling spirit", || {
+        sqlx::query_scalar(
+            r#"select "entity" from travelling_spirits where "start" <= now() and "start" > now() - interval '5 days' order by "start" desc limit 1;"#,
+        )
+        .fetch_optional(pool)
+    })
     .await
-    .expect("Failed to fetch the travelling spirit.");
+}
 
-    TravellingSpirit {
-        entity: row.entity,
-        start: row.start.with_timezone(&chrono_tz::America::Los_Angeles),
+/// Retrieves the items and image of each travelling spirit for guilds that opted into rich notifications.
+pub async fn fetch_travelling_spirit_details(
+    travelling_spirits: &mut [TravellingSpirit],
+    config: &Config,
+) {
+    for travelling_spirit in travelling_spirits {
+        travelling_spirit.details = spirit_details(
+            &config.wind_paths_url,
+            &config.cdn_url,
+            &travelling_spirit.entity,
+        )
+        .await
+        .map(Arc::new);
     }
 }
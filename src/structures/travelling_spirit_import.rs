@@ -0,0 +1,72 @@
+use super::config::Config;
+use crate::utility::{alert::alert, broker, error_reporting::report_parse_failure, http::CLIENT};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use std::{sync::Arc, time::Duration};
+use tokio::time::sleep;
+
+#[derive(Deserialize, Serialize)]
+pub struct UpstreamTravellingSpirit {
+    visit: i32,
+    entity: String,
+    start: DateTime<Utc>,
+}
+
+/// Periodically imports newly announced travelling spirits from the upstream data source.
+pub async fn import_travelling_spirits(pool: Pool<Postgres>, config: Arc<Config>) {
+    let interval = Duration::from_secs((config.travelling_spirit.import_interval * 60).into());
+
+    loop {
+        if let Err(error) = import(&pool, &config).await {
+            tracing::error!("Error importing travelling spirits: {error:?}");
+        }
+
+        sleep(interval).await;
+    }
+}
+
+async fn import(pool: &Pool<Postgres>, config: &Config) -> anyhow::Result<()> {
+    let upstream_travelling_spirits = CLIENT
+        .get(format!("{}/travelling-spirits", config.wind_paths_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<UpstreamTravellingSpirit>>()
+        .await
+        .inspect_err(|error| {
+            if error.is_decode() {
+                report_parse_failure("the travelling spirits", error);
+            }
+        })?;
+
+    for upstream_travelling_spirit in upstream_travelling_spirits {
+        // Existing visits are left untouched so manual corrections are not overwritten.
+        let inserted = sqlx::query(
+            r#"insert into travelling_spirits (visit, "entity", "start") values ($1, $2, $3) on conflict do nothing;"#,
+        )
+        .bind(upstream_travelling_spirit.visit)
+        .bind(&upstream_travelling_spirit.entity)
+        .bind(upstream_travelling_spirit.start)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if inserted > 0 {
+            alert(
+                config.alert_webhook_url.as_deref(),
+                &format!(
+                    "New travelling spirit detected: {} (visit {}) arrives <t:{}:F>.",
+                    upstream_travelling_spirit.entity,
+                    upstream_travelling_spirit.visit,
+                    upstream_travelling_spirit.start.timestamp()
+                ),
+            )
+            .await;
+
+            broker::publish("travelling_spirit.detected", &upstream_travelling_spirit).await;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use super::{
+    audit_log::{audit, AuditEvent},
+    config::Config,
+    notification::{snowflake, NotificationNotify, NotificationType},
+    subscriber_index::invalidate_subscriber_index,
+};
+use crate::utility::{database::with_retry, dispatcher::Dispatcher, metrics};
+use anyhow::{bail, Result};
+use futures::future::join_all;
+use serenity::{
+    all::{CreateMessage, MessageFlags},
+    http::{Http, HttpError},
+    model::id::UserId,
+};
+use sqlx::{postgres::PgRow, FromRow, Pool, Postgres, Row};
+
+/// The error Discord returns when a user does not accept direct messages from the bot.
+const CANNOT_SEND_MESSAGES_TO_USER: isize = 50007;
+
+/// A player's subscription to receive a notification type in their direct messages.
+pub struct UserSubscription {
+    pub user_id: UserId,
+    pub r#type: NotificationType,
+    pub offset: i16,
+}
+
+impl UserSubscription {
+    pub fn validate(&self, config: &Config) -> Result<()> {
+        if !self.r#type.allows_offset(config, self.offset) {
+            bail!("{}", self.r#type.describe_offsets(config));
+        }
+
+        Ok(())
+    }
+}
+
+impl FromRow<'_, PgRow> for UserSubscription {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            user_id: snowflake(row, "user_id")?,
+            r#type: row.try_get("type")?,
+            offset: row.try_get("offset")?,
+        })
+    }
+}
+
+/// Subscribes a user to a notification type, re-enabling the subscription if it was disabled.
+pub async fn subscribe_user(
+    pool: &Pool<Postgres>,
+    user_subscription: &UserSubscription,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"insert into user_subscriptions (user_id, "type", "offset") values ($1, $2, $3) on conflict (user_id, "type") do update set "offset" = excluded."offset", sendable = true;"#,
+    )
+    .bind(user_subscription.user_id.get() as i64)
+    .bind(user_subscription.r#type)
+    .bind(user_subscription.offset)
+    .execute(pool)
+    .await?;
+
+    invalidate_subscriber_index();
+    Ok(())
+}
+
+/// Unsubscribes a user from a notification type. Returns whether they were subscribed.
+pub async fn unsubscribe_user(
+    pool: &Pool<Postgres>,
+    user_id: UserId,
+    r#type: NotificationType,
+) -> Result<bool, sqlx::Error> {
+    let result =
+        sqlx::query(r#"delete from user_subscriptions where user_id = $1 and "type" = $2;"#)
+            .bind(user_id.get() as i64)
+            .bind(r#type)
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sends a notification to the direct messages of every subscribed user.
+///
+/// Users who do not accept direct messages have their subscription disabled until they subscribe again.
+pub async fn send_direct_messages(
+    client: &Http,
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+    config: &Config,
+    notification_notify: &NotificationNotify,
+) {
+    let user_subscriptions: Vec<UserSubscription> = match with_retry("user subscriptions", || {
+        sqlx::query_as(
+            r#"select user_id, "type", "offset" from user_subscriptions where "type" = $1 and "offset" = $2 and sendable is true;"#,
+        )
+        .bind(notification_notify.r#type)
+        .bind(notification_notify.time_until_start as i16)
+        .fetch_all(pool)
+    })
+    .await
+    {
+        Ok(user_subscriptions) => user_subscriptions,
+        Err(error) => {
+            tracing::error!(
+                r#type = ?notification_notify.r#type,
+                "Failed to retrieve user subscriptions: {error:?}"
+            );
+
+            return;
+        }
+    };
+
+    let message = CreateMessage::new()
+        .content(notification_notify.with_notes(notification_notify.text(config.flavor_text)))
+        .flags(MessageFlags::SUPPRESS_EMBEDS);
+
+    let futures = user_subscriptions.iter().map(|user_subscription| {
+        let message = message.clone();
+
+        async move {
+            let user_id = user_subscription.user_id;
+            let _permit = dispatcher
+                .acquire(notification_notify.urgency(), user_id.get())
+                .await;
+
+            let result = match user_id.create_dm_channel(client).await {
+                Ok(channel) => channel.id.send_message(client, message).await.map(|_| ()),
+                Err(error) => Err(error),
+            };
+
+            let Err(error) = result else {
+                metrics::increment("direct_messages_sent");
+                return;
+            };
+
+            if !is_closed(&error) {
+                tracing::error!(%user_id, "Failed to send a direct message: {error:?}");
+                return;
+            }
+
+            tracing::info!(%user_id, "Disabling a user subscription as their direct messages are closed.");
+            metrics::increment("user_subscriptions_disabled");
+
+            let result = sqlx::query(
+                r#"update user_subscriptions set sendable = false where user_id = $1 and "type" = $2;"#,
+            )
+            .bind(user_id.get() as i64)
+            .bind(user_subscription.r#type)
+            .execute(pool)
+            .await;
+
+            match result {
+                Ok(_) => {
+                    audit(
+                        pool,
+                        AuditEvent {
+                            user_id: Some(user_id),
+                            actor: "direct_messages",
+                            action: "disabled",
+                            reason: Some("The user does not accept direct messages."),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                }
+                Err(error) => {
+                    tracing::error!(%user_id, "Failed to disable a user subscription: {error:?}")
+                }
+            }
+        }
+    });
+
+    join_all(futures).await;
+}
+
+/// Whether an error sending a direct message is because the user does not accept them.
+pub fn is_closed(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == CANNOT_SEND_MESSAGES_TO_USER
+    )
+}
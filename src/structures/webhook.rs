@@ -0,0 +1,193 @@
+use super::{
+    audit_log::{audit, AuditEvent},
+    config::Config,
+    notification::{NotificationNotify, NotificationType},
+    subscriber_index::invalidate_subscriber_index,
+};
+use crate::utility::{
+    database::with_retry, dispatcher::Dispatcher, http::CLIENT, metrics, slack::slack_message,
+};
+use anyhow::{bail, Result};
+use futures::future::join_all;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, Pool, Postgres};
+
+/// Where a webhook posts to, which decides how messages are formatted.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize, sqlx::Type)]
+#[repr(i16)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookPlatform {
+    Slack = 0,
+}
+
+/// A subscription to a notification type delivered to a webhook instead of a Discord channel.
+#[derive(Deserialize, FromRow, Serialize)]
+pub struct Webhook {
+    #[serde(default)]
+    pub id: i64,
+    pub platform: WebhookPlatform,
+    pub url: String,
+    pub r#type: NotificationType,
+    #[serde(default)]
+    pub offset: i16,
+    #[serde(default = "sendable")]
+    pub sendable: bool,
+}
+
+fn sendable() -> bool {
+    true
+}
+
+impl Webhook {
+    pub fn validate(&self, config: &Config) -> Result<()> {
+        if !self.r#type.allows_offset(config, self.offset) {
+            bail!("{}", self.r#type.describe_offsets(config));
+        }
+
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if url.scheme() == "https" => Ok(()),
+            _ => bail!("{} is not a valid HTTPS URL.", self.url),
+        }
+    }
+}
+
+/// Adds a webhook, re-enabling it if it was already added. Returns its ID.
+pub async fn create_webhook(pool: &Pool<Postgres>, webhook: &Webhook) -> Result<i64, sqlx::Error> {
+    let id = sqlx::query_scalar(
+        r#"insert into webhooks (platform, url, "type", "offset") values ($1, $2, $3, $4) on conflict (url, "type", "offset") do update set platform = excluded.platform, sendable = true returning id;"#,
+    )
+    .bind(webhook.platform)
+    .bind(&webhook.url)
+    .bind(webhook.r#type)
+    .bind(webhook.offset)
+    .fetch_one(pool)
+    .await?;
+
+    invalidate_subscriber_index();
+    Ok(id)
+}
+
+pub async fn get_webhooks(pool: &Pool<Postgres>) -> Result<Vec<Webhook>, sqlx::Error> {
+    sqlx::query_as(
+        r#"select id, platform, url, "type", "offset", sendable from webhooks order by id;"#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Removes a webhook. Returns whether it existed.
+pub async fn delete_webhook(pool: &Pool<Postgres>, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("delete from webhooks where id = $1;")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Sends a notification to every webhook subscribed to it.
+///
+/// Webhooks the platform says are gone are disabled until they are added again.
+pub async fn send_webhooks(
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+    config: &Config,
+    notification_notify: &NotificationNotify,
+) {
+    let webhooks: Vec<Webhook> = match with_retry("webhooks", || {
+        sqlx::query_as(
+            r#"select id, platform, url, "type", "offset", sendable from webhooks where "type" in ($1, $2) and "offset" = $3 and sendable is true;"#,
+        )
+        .bind(notification_notify.r#type)
+        .bind(
+            notification_notify
+                .r#type
+                .bundle()
+                .unwrap_or(notification_notify.r#type),
+        )
+        .bind(notification_notify.time_until_start as i16)
+        .fetch_all(pool)
+    })
+    .await
+    {
+        Ok(webhooks) => webhooks,
+        Err(error) => {
+            tracing::error!(
+                r#type = ?notification_notify.r#type,
+                "Failed to retrieve webhooks: {error:?}"
+            );
+
+            return;
+        }
+    };
+
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let slack = slack_message(config.flavor_text, notification_notify);
+
+    let futures = webhooks.iter().map(|webhook| {
+        let payload = match webhook.platform {
+            WebhookPlatform::Slack => &slack,
+        };
+
+        async move {
+            let _permit = dispatcher
+                .acquire(notification_notify.urgency(), webhook.id as u64)
+                .await;
+
+            let status = match CLIENT.post(&webhook.url).json(payload).send().await {
+                Ok(response) if response.status().is_success() => {
+                    metrics::increment("webhooks_sent");
+                    return;
+                }
+                Ok(response) => response.status(),
+                Err(error) => {
+                    tracing::error!(id = webhook.id, "Failed to send to a webhook: {error:?}");
+                    return;
+                }
+            };
+
+            if !matches!(
+                status,
+                StatusCode::FORBIDDEN | StatusCode::NOT_FOUND | StatusCode::GONE
+            ) {
+                tracing::error!(id = webhook.id, %status, "A webhook refused a notification.");
+                return;
+            }
+
+            tracing::info!(id = webhook.id, %status, "Disabling a webhook that no longer exists.");
+            metrics::increment("webhooks_disabled");
+
+            let result = sqlx::query("update webhooks set sendable = false where id = $1;")
+                .bind(webhook.id)
+                .execute(pool)
+                .await;
+
+            match result {
+                Ok(_) => {
+                    audit(
+                        pool,
+                        AuditEvent {
+                            actor: "webhooks",
+                            action: "disabled",
+                            reason: Some(&format!(
+                                "Webhook {} responded with {status}.",
+                                webhook.id
+                            )),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+                }
+                Err(error) => {
+                    tracing::error!(id = webhook.id, "Failed to disable a webhook: {error:?}")
+                }
+            }
+        }
+    });
+
+    join_all(futures).await;
+}
@@ -0,0 +1,108 @@
+use super::{
+    international_space_station::InternationalSpaceStationSchedule,
+    travelling_spirit::TravellingSpirit,
+};
+use crate::utility::{
+    functions::format_list, shard_eruption::calculate_shard_eruption,
+    wind_paths::ShardEruptionResponse,
+};
+use chrono::{DateTime, TimeDelta};
+use chrono_tz::Tz;
+
+/// Summarises the week starting at `now`, the Sunday reset.
+///
+/// Today's shard eruption comes from the wind paths, like the rest of the day's notifications. The days after are calculated.
+pub fn weekly_digest(
+    now: DateTime<Tz>,
+    shard_eruption: Option<&ShardEruptionResponse>,
+    international_space_station_schedule: &InternationalSpaceStationSchedule,
+    current_travelling_spirit: Option<&str>,
+    travelling_spirits: &[TravellingSpirit],
+    cdn_url: &str,
+) -> String {
+    let today = now.date_naive();
+    let week = today.iter_days().take(7).collect::<Vec<_>>();
+    let mut lines = vec!["Here is the week ahead!".to_string(), String::new()];
+
+    lines.push("**Shard eruptions**".to_string());
+
+    for (index, date) in week.iter().enumerate() {
+        let calculated;
+
+        let shard_eruption = if index == 0 {
+            shard_eruption
+        } else {
+            calculated = calculate_shard_eruption(*date, cdn_url);
+            calculated.as_ref()
+        };
+
+        let description = match shard_eruption {
+            Some(shard_eruption) => format!(
+                "{} in the {} ({})",
+                if shard_eruption.strong {
+                    "Strong"
+                } else {
+                    "Regular"
+                },
+                shard_eruption.realm,
+                shard_eruption.sky_map
+            ),
+            None => "None".to_string(),
+        };
+
+        lines.push(format!("- {}: {description}", date.format("%A")));
+    }
+
+    lines.push(String::new());
+
+    lines.push(format!(
+        "**Eye of Eden**\nStatues reset now and again <t:{}:F>.",
+        (now + TimeDelta::weeks(1)).timestamp()
+    ));
+
+    let international_space_station_days = week
+        .iter()
+        .filter(|date| international_space_station_schedule.is_accessible(**date))
+        .map(|date| date.format("%A").to_string())
+        .collect::<Vec<_>>();
+
+    lines.push(format!(
+        "**International Space Station**\n{}",
+        if international_space_station_days.is_empty() {
+            "Not accessible this week.".to_string()
+        } else {
+            format!(
+                "Accessible on {}.",
+                format_list(&international_space_station_days)
+            )
+        }
+    ));
+
+    let mut travelling_spirit_lines = vec![];
+
+    if let Some(current_travelling_spirit) = current_travelling_spirit {
+        travelling_spirit_lines.push(format!("{current_travelling_spirit} is visiting."));
+    }
+
+    if let Some(travelling_spirit) = travelling_spirits
+        .iter()
+        .find(|travelling_spirit| travelling_spirit.start >= now)
+    {
+        travelling_spirit_lines.push(format!(
+            "{} arrives <t:{}:R>.",
+            travelling_spirit.entity,
+            travelling_spirit.start.timestamp()
+        ));
+    }
+
+    if travelling_spirit_lines.is_empty() {
+        travelling_spirit_lines.push("No travelling spirit has been announced yet.".to_string());
+    }
+
+    lines.push(format!(
+        "**Travelling spirit**\n{}",
+        travelling_spirit_lines.join(" ")
+    ));
+
+    lines.join("\n")
+}
@@ -0,0 +1,518 @@
+use super::{
+    channel_circuit, credentials,
+    discord_clients::DiscordClients,
+    event_stream,
+    graphql::{schema, DashboardSchema},
+    latency, log_filter,
+    permissions::{channel_problem, GuildView},
+};
+use crate::structures::{
+    audit_log::{audit, get_audit_log, AuditEvent},
+    config::Config,
+    delivery_statistics::get_delivery_statistics,
+    guild_pause::{get_pause, pause_guild, resume_guild},
+    notification::NotificationType,
+    subscription::{
+        get_guild_configuration, get_subscriptions, migrate_channel, replace_guild_configuration,
+        GuildConfiguration,
+    },
+    test_send::test_send,
+    webhook::{create_webhook, delete_webhook, get_webhooks, Webhook},
+};
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+struct AdminState {
+    clients: Arc<DiscordClients>,
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+    schema: DashboardSchema,
+}
+
+/// Serves the admin API until the listener fails.
+pub async fn serve(
+    config: Arc<Config>,
+    pool: Pool<Postgres>,
+    clients: Arc<DiscordClients>,
+) -> Result<()> {
+    let Some(address) = config.admin.address.clone() else {
+        return Ok(());
+    };
+
+    let state = Arc::new(AdminState {
+        clients,
+        pool: pool.clone(),
+        config: config.clone(),
+        schema: schema(pool, config.clone()),
+    });
+
+    let router = Router::new()
+        .route("/channel-circuits", get(channel_circuits))
+        .route(
+            "/channel-circuits/:channel_id",
+            delete(reset_channel_circuit),
+        )
+        .route("/delivery-statistics", get(delivery_statistics))
+        .route("/credentials/reload", post(reload_credentials))
+        .route("/discord-tokens", get(discord_tokens))
+        .route("/events", get(events))
+        .route("/graphql", post(graphql))
+        .route("/latency", get(latency))
+        .route("/log-filter", get(log_filter).put(set_log_filter))
+        .route(
+            "/guilds/:guild_id/pause",
+            get(guild_pause).put(pause).delete(resume),
+        )
+        .route(
+            "/guilds/:guild_id/subscriptions",
+            get(export_subscriptions).put(import_subscriptions),
+        )
+        .route("/guilds/:guild_id/audit-log", get(audit_log))
+        .route(
+            "/guilds/:guild_id/channel-migration",
+            post(migrate_subscriptions),
+        )
+        .route("/guilds/:guild_id/test-send", post(send_test))
+        .route("/webhooks", get(webhooks).post(add_webhook))
+        .route("/webhooks/:id", delete(remove_webhook))
+        .with_state(state)
+        .layer(middleware::from_fn_with_state(config, authorise));
+
+    let listener = TcpListener::bind(&address).await?;
+    tracing::info!("Admin API listening on {address}.");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+async fn authorise(State(config): State<Arc<Config>>, request: Request, next: Next) -> Response {
+    let authorised = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token == config.admin.token);
+
+    if !authorised {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
+}
+
+async fn channel_circuits() -> impl IntoResponse {
+    Json(channel_circuit::snapshot())
+}
+
+/// Rotates the Discord tokens and database credentials to those now in the environment file and configuration.
+async fn reload_credentials(State(state): State<Arc<AdminState>>) -> Response {
+    match credentials::reload_credentials(&state.clients, &state.pool).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, format!("{error:#}")).into_response(),
+    }
+}
+
+/// Reports each Discord token's health and which one sends are using.
+async fn discord_tokens(State(state): State<Arc<AdminState>>) -> impl IntoResponse {
+    Json(state.clients.statuses())
+}
+
+/// Answers a GraphQL request, for the web dashboard.
+async fn graphql(
+    State(state): State<Arc<AdminState>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(state.schema.execute(request).await)
+}
+
+/// Streams notifications as they are dispatched, for dashboards and companion services.
+async fn events() -> impl IntoResponse {
+    event_stream::subscribe()
+}
+
+async fn reset_channel_circuit(
+    State(state): State<Arc<AdminState>>,
+    Path(channel_id): Path<ChannelId>,
+) -> StatusCode {
+    if channel_circuit::reset(channel_id.get()) {
+        audit(
+            &state.pool,
+            AuditEvent {
+                channel_id: Some(channel_id),
+                actor: "admin",
+                action: "circuit_reset",
+                ..Default::default()
+            },
+        )
+        .await;
+
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct GuildPause {
+    paused_until: DateTime<Utc>,
+}
+
+async fn guild_pause(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+) -> Response {
+    match get_pause(&state.pool, guild_id).await {
+        Ok(Some(paused_until)) => Json(GuildPause { paused_until }).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to retrieve a guild pause: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn pause(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+    Json(guild_pause): Json<GuildPause>,
+) -> StatusCode {
+    match pause_guild(&state.pool, guild_id, guild_pause.paused_until).await {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to pause a guild: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn resume(State(state): State<Arc<AdminState>>, Path(guild_id): Path<GuildId>) -> StatusCode {
+    match resume_guild(&state.pool, guild_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to resume a guild: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn export_subscriptions(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+) -> Response {
+    match get_guild_configuration(&state.pool, guild_id).await {
+        Ok(guild_configuration) => Json(guild_configuration).into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to export subscriptions: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Replaces a guild's subscriptions, realm roles, and pause with exported ones. Channels and roles are kept as exported.
+async fn import_subscriptions(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+    Json(guild_configuration): Json<GuildConfiguration>,
+) -> Response {
+    match replace_guild_configuration(&state.pool, &state.config, guild_id, &guild_configuration)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(error) if error.is::<sqlx::Error>() => {
+            tracing::error!(%guild_id, "Failed to import subscriptions: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(error) => (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct ChannelMigration {
+    from: ChannelId,
+    to: ChannelId,
+}
+
+/// Re-points every subscription in a channel to another, once the bot is known to be able to send there with each subscription's role.
+async fn migrate_subscriptions(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+    Json(channel_migration): Json<ChannelMigration>,
+) -> Response {
+    let subscriptions = match get_subscriptions(&state.pool, guild_id).await {
+        Ok(subscriptions) => subscriptions
+            .into_iter()
+            .filter(|subscription| subscription.channel_id == channel_migration.from)
+            .collect::<Vec<_>>(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to retrieve subscriptions to migrate: {error:?}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if subscriptions.is_empty() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let client = state.clients.primary();
+
+    let guild_view = match GuildView::fetch(&client, guild_id).await {
+        Ok(Some(guild_view)) => guild_view,
+        Ok(None) => {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The bot is not in the server.",
+            )
+                .into_response()
+        }
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to look up a guild: {error:?}");
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    for subscription in &subscriptions {
+        match guild_view
+            .channel_problem(&client, channel_migration.to, subscription.role_id)
+            .await
+        {
+            Ok(None) => {}
+            Ok(Some(problem)) => {
+                return (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    format!("{}: {problem}", subscription.r#type),
+                )
+                    .into_response()
+            }
+            Err(error) => {
+                tracing::error!(%guild_id, "Failed to check a channel: {error:?}");
+                return StatusCode::BAD_GATEWAY.into_response();
+            }
+        }
+    }
+
+    match migrate_channel(
+        &state.pool,
+        guild_id,
+        channel_migration.from,
+        channel_migration.to,
+    )
+    .await
+    {
+        Ok(0) => StatusCode::NOT_FOUND.into_response(),
+        Ok(migrated) => {
+            audit(
+                &state.pool,
+                AuditEvent {
+                    guild_id: Some(guild_id),
+                    channel_id: Some(channel_migration.to),
+                    actor: "admin",
+                    action: "channel_migrated",
+                    reason: Some(&format!("Moved from <#{}>.", channel_migration.from)),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            Json(serde_json::json!({ "migrated": migrated })).into_response()
+        }
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to migrate subscriptions: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TestSend {
+    channel_id: ChannelId,
+    r#type: NotificationType,
+}
+
+/// Sends a notification to a channel now, so permissions and formatting can be checked.
+async fn send_test(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+    Json(request): Json<TestSend>,
+) -> Response {
+    match channel_problem(&state.clients.primary(), guild_id, request.channel_id).await {
+        Ok(None) => {}
+        Ok(Some(problem)) => return (StatusCode::UNPROCESSABLE_ENTITY, problem).into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to check a channel: {error:?}");
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    }
+
+    match test_send(
+        &state.clients.primary(),
+        &state.pool,
+        &state.config,
+        guild_id,
+        request.channel_id,
+        request.r#type,
+    )
+    .await
+    {
+        Ok(message) => {
+            audit(
+                &state.pool,
+                AuditEvent {
+                    guild_id: Some(guild_id),
+                    channel_id: Some(request.channel_id),
+                    actor: "admin",
+                    action: "test_sent",
+                    reason: Some(request.r#type.name()),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            Json(serde_json::json!({ "message_id": message.id })).into_response()
+        }
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to send a test notification: {error:?}");
+            (StatusCode::BAD_GATEWAY, error.to_string()).into_response()
+        }
+    }
+}
+
+async fn audit_log(
+    State(state): State<Arc<AdminState>>,
+    Path(guild_id): Path<GuildId>,
+) -> Response {
+    match get_audit_log(&state.pool, guild_id).await {
+        Ok(entries) => Json(entries).into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to retrieve the audit log: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn log_filter() -> Response {
+    match log_filter::current() {
+        Ok(filter) => filter.into_response(),
+        Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+    }
+}
+
+/// Replaces the log filter, given in RUST_LOG syntax as the body.
+async fn set_log_filter(directives: String) -> Response {
+    match log_filter::set(directives.trim()) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(error) => (StatusCode::BAD_REQUEST, error.to_string()).into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+struct DateRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+/// Counts per notification type per day. Defaults to the last week.
+async fn delivery_statistics(
+    State(state): State<Arc<AdminState>>,
+    Query(date_range): Query<DateRange>,
+) -> Response {
+    let today = Utc::now()
+        .with_timezone(&chrono_tz::America::Los_Angeles)
+        .date_naive();
+
+    let to = date_range.to.unwrap_or(today);
+    let from = date_range.from.unwrap_or(to - TimeDelta::days(6));
+
+    match get_delivery_statistics(&state.pool, from, to).await {
+        Ok(delivery_statistics) => Json(delivery_statistics).into_response(),
+        Err(error) => {
+            tracing::error!("Failed to retrieve delivery statistics: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Summarises how late sends since the last latency check were.
+async fn latency() -> Response {
+    match latency::snapshot() {
+        Some(summary) => Json(summary).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    }
+}
+
+async fn webhooks(State(state): State<Arc<AdminState>>) -> Response {
+    match get_webhooks(&state.pool).await {
+        Ok(webhooks) => Json(webhooks).into_response(),
+        Err(error) => {
+            tracing::error!("Failed to retrieve webhooks: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Adds a webhook for a notification type, such as a Slack incoming webhook.
+async fn add_webhook(
+    State(state): State<Arc<AdminState>>,
+    Json(webhook): Json<Webhook>,
+) -> Response {
+    if let Err(error) = webhook.validate(&state.config) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response();
+    }
+
+    match create_webhook(&state.pool, &webhook).await {
+        Ok(id) => {
+            audit(
+                &state.pool,
+                AuditEvent {
+                    actor: "admin",
+                    action: "webhook_added",
+                    reason: Some(&format!("Webhook {id} for {}.", webhook.r#type)),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            Json(serde_json::json!({ "id": id })).into_response()
+        }
+        Err(error) => {
+            tracing::error!("Failed to add a webhook: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn remove_webhook(State(state): State<Arc<AdminState>>, Path(id): Path<i64>) -> Response {
+    match delete_webhook(&state.pool, id).await {
+        Ok(true) => {
+            audit(
+                &state.pool,
+                AuditEvent {
+                    actor: "admin",
+                    action: "webhook_removed",
+                    reason: Some(&format!("Webhook {id}.")),
+                    ..Default::default()
+                },
+            )
+            .await;
+
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!("Failed to remove a webhook: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
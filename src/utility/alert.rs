@@ -0,0 +1,22 @@
+use super::http::CLIENT;
+use serde_json::json;
+
+/// Logs a message for the operator and, if configured, posts it to the alert webhook.
+pub async fn alert(webhook_url: Option<&str>, message: &str) {
+    tracing::warn!("{message}");
+
+    let Some(webhook_url) = webhook_url else {
+        return;
+    };
+
+    let result = CLIENT
+        .post(webhook_url)
+        .json(&json!({ "content": message }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    if let Err(error) = result {
+        tracing::error!("Failed to send an alert: {error:?}");
+    }
+}
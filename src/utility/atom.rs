@@ -0,0 +1,74 @@
+use super::markup::{format_timestamp, segments, Segment};
+use crate::structures::{
+    dispatched_notification::DispatchedNotification, notification::NotificationType,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Identifies feeds and their entries, as Atom requires IDs that never change.
+const TAG: &str = "tag:thatskyapplication.com,2024:notifications";
+
+/// Renders dispatched notifications, newest first, as an Atom feed of one type or of every type.
+pub fn atom_feed(r#type: Option<NotificationType>, entries: &[DispatchedNotification]) -> String {
+    let (id, title) = match r#type {
+        Some(r#type) => (format!("{TAG}:{type}"), format!("Sky: {}", r#type.name())),
+        None => (TAG.to_string(), "Sky notifications".to_string()),
+    };
+
+    // An empty feed has not changed since the epoch, so readers are not told it was just updated.
+    let updated = entries
+        .first()
+        .map(|entry| entry.dispatched_at)
+        .unwrap_or_default();
+
+    let mut feed = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><feed xmlns="http://www.w3.org/2005/Atom"><id>{id}</id><title>{}</title><updated>{}</updated><author><name>thatskyapplication</name></author>"#,
+        escape(&title),
+        rfc3339(updated)
+    );
+
+    for entry in entries {
+        feed.push_str(&format!(
+            r#"<entry><id>{TAG}:{}:{}:{}</id><title>{}</title><updated>{}</updated><content type="html">{}</content></entry>"#,
+            entry.r#type,
+            entry.start_time,
+            entry.offset,
+            escape(entry.r#type.name()),
+            rfc3339(entry.dispatched_at),
+            escape(&html(entry))
+        ));
+    }
+
+    feed.push_str("</feed>");
+    feed
+}
+
+/// Rewrites Discord's links and timestamps in HTML. Relative timestamps are written relative to when the notification was due, as dispatching takes moments longer.
+fn html(entry: &DispatchedNotification) -> String {
+    let due_at = DateTime::from_timestamp(entry.start_time - i64::from(entry.offset) * 60, 0)
+        .unwrap_or(entry.dispatched_at);
+
+    segments(&entry.text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => escape(text),
+            Segment::Link { label, url } => {
+                format!(r#"<a href="{}">{}</a>"#, escape(url), escape(label))
+            }
+            Segment::Timestamp { timestamp, style } => {
+                escape(&format_timestamp(timestamp, style, due_at))
+            }
+        })
+        .collect()
+}
+
+fn rfc3339(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Escapes the characters XML and HTML reserve.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,56 @@
+use super::metrics;
+use crate::structures::{config::NatsConfig, notification::NotificationNotify};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// The NATS connection events are published through, and the prefix of their subjects.
+static BROKER: OnceLock<(async_nats::Client, String)> = OnceLock::new();
+
+/// Connects to NATS so events are published. Nothing is published if no URL is configured.
+pub async fn connect(config: &NatsConfig) -> Result<()> {
+    if config.url.is_empty() {
+        return Ok(());
+    }
+
+    let client = async_nats::connect(&config.url)
+        .await
+        .context("Error connecting to NATS.")?;
+
+    BROKER.get_or_init(|| (client, config.subject_prefix.clone()));
+    tracing::info!("Publishing events to NATS.");
+    Ok(())
+}
+
+/// Publishes an event as JSON to a subject under the prefix, such as `notifications.dispatched.DailyReset`.
+///
+/// Other services only mirror these, so a failure is logged rather than interrupting sends.
+pub async fn publish(subject: &str, payload: &impl Serialize) {
+    let Some((client, subject_prefix)) = BROKER.get() else {
+        return;
+    };
+
+    let subject = format!("{subject_prefix}.{subject}");
+
+    let payload = match serde_json::to_vec(payload) {
+        Ok(payload) => payload,
+        Err(error) => {
+            tracing::error!(subject, "Failed to serialise an event: {error:?}");
+            return;
+        }
+    };
+
+    match client.publish(subject.clone(), payload.into()).await {
+        Ok(()) => metrics::increment("broker_published"),
+        Err(error) => tracing::error!(subject, "Failed to publish an event: {error:?}"),
+    }
+}
+
+/// Publishes a notification as it is dispatched, under its type.
+pub async fn publish_notification(notification_notify: &NotificationNotify) {
+    publish(
+        &format!("dispatched.{:?}", notification_notify.r#type),
+        notification_notify,
+    )
+    .await;
+}
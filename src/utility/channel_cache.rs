@@ -0,0 +1,126 @@
+use super::constants::CHANNEL_CACHE_TTL;
+use anyhow::{bail, Result};
+use serenity::{
+    all::{Channel, ChannelType, GuildChannel},
+    http::Http,
+    model::id::ChannelId,
+};
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+/// What notifications need to know about the channel they are sent to.
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelMetadata {
+    pub kind: ChannelType,
+    /// The channel a thread was started in, or the category a channel is in.
+    pub parent_id: Option<ChannelId>,
+    /// Whether the channel is a thread that has been archived.
+    pub archived: bool,
+    /// Whether the channel is a thread only moderators may unarchive or send to.
+    pub locked: bool,
+    /// Whether the bot has joined the thread since its metadata was fetched.
+    pub joined: bool,
+    fetched_at: Instant,
+}
+
+impl ChannelMetadata {
+    fn new(channel: &GuildChannel) -> Self {
+        Self {
+            kind: channel.kind,
+            parent_id: channel.parent_id,
+            archived: channel
+                .thread_metadata
+                .is_some_and(|thread_metadata| thread_metadata.archived),
+            locked: channel
+                .thread_metadata
+                .is_some_and(|thread_metadata| thread_metadata.locked),
+            joined: false,
+            fetched_at: Instant::now(),
+        }
+    }
+
+    /// Whether the channel is a thread.
+    pub fn is_thread(&self) -> bool {
+        matches!(
+            self.kind,
+            ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+        )
+    }
+
+    /// Describes why notifications cannot be sent to the channel, if they cannot.
+    pub fn problem(&self) -> Option<&'static str> {
+        if self.locked {
+            return Some("The thread is locked.");
+        }
+
+        kind_problem(self.kind)
+    }
+
+    /// Whether messages sent to the channel may start threads. Threads cannot be nested, and the chat of voice and stage channels has none.
+    pub fn supports_threads(&self) -> bool {
+        matches!(self.kind, ChannelType::Text | ChannelType::News)
+    }
+}
+
+static CHANNELS: LazyLock<Mutex<HashMap<ChannelId, ChannelMetadata>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Retrieves a channel's metadata, fetching it only if it is not cached or has gone stale.
+pub async fn channel_metadata(client: &Http, channel_id: ChannelId) -> Result<ChannelMetadata> {
+    let cached = CHANNELS
+        .lock()
+        .unwrap()
+        .get(&channel_id)
+        .filter(|metadata| metadata.fetched_at.elapsed() < CHANNEL_CACHE_TTL)
+        .copied();
+
+    if let Some(metadata) = cached {
+        return Ok(metadata);
+    }
+
+    let Channel::Guild(channel) = client.get_channel(channel_id).await? else {
+        bail!("The channel is not in a server.");
+    };
+
+    Ok(remember(&channel))
+}
+
+/// Caches a channel's metadata, such as after fetching or editing it elsewhere.
+pub fn remember(channel: &GuildChannel) -> ChannelMetadata {
+    let metadata = ChannelMetadata::new(channel);
+    CHANNELS.lock().unwrap().insert(channel.id, metadata);
+    metadata
+}
+
+/// Notes the bot has joined a thread, so it is not joined again until its metadata is fetched again.
+pub fn mark_joined(channel_id: ChannelId) {
+    if let Some(metadata) = CHANNELS.lock().unwrap().get_mut(&channel_id) {
+        metadata.joined = true;
+    }
+}
+
+/// Forgets a channel's metadata, so it is fetched again before the next send.
+pub fn forget(channel_id: ChannelId) {
+    CHANNELS.lock().unwrap().remove(&channel_id);
+}
+
+/// Describes why notifications cannot be sent to a kind of channel, if they cannot.
+fn kind_problem(kind: ChannelType) -> Option<&'static str> {
+    match kind {
+        ChannelType::Text
+        | ChannelType::News
+        | ChannelType::Voice
+        | ChannelType::Stage
+        | ChannelType::PublicThread
+        | ChannelType::PrivateThread
+        | ChannelType::NewsThread => None,
+        ChannelType::Category => {
+            Some("The channel is a category. Choose one of the channels in it instead.")
+        }
+        ChannelType::Forum => Some("The channel is a forum. Choose one of its posts instead."),
+        _ => Some("Messages cannot be sent to this kind of channel."),
+    }
+}
@@ -0,0 +1,97 @@
+use super::{
+    constants::{CHANNEL_CIRCUIT_BREAKER_PROBE_INTERVAL, CHANNEL_CIRCUIT_BREAKER_THRESHOLD},
+    metrics,
+};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+};
+
+#[derive(Clone, Default, Serialize)]
+pub struct ChannelCircuit {
+    pub consecutive_failures: u32,
+    /// When the circuit was opened, as a Unix timestamp in milliseconds.
+    pub opened_at: Option<i64>,
+    /// When the last send was let through, as a Unix timestamp in milliseconds.
+    pub last_attempt: Option<i64>,
+}
+
+static CIRCUITS: LazyLock<Mutex<HashMap<u64, ChannelCircuit>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Whether a send to the channel should be attempted.
+///
+/// Channels with an open circuit are skipped, except for a probe once every interval to find out whether they have recovered.
+pub fn allow(channel_id: u64) -> bool {
+    let mut circuits = CIRCUITS.lock().unwrap();
+
+    let Some(circuit) = circuits.get_mut(&channel_id) else {
+        return true;
+    };
+
+    let now = chrono::Utc::now().timestamp_millis();
+
+    if circuit.opened_at.is_some()
+        && circuit.last_attempt.is_some_and(|last_attempt| {
+            now - last_attempt < CHANNEL_CIRCUIT_BREAKER_PROBE_INTERVAL.as_millis() as i64
+        })
+    {
+        metrics::increment("channel_circuit_skips");
+        return false;
+    }
+
+    circuit.last_attempt = Some(now);
+    true
+}
+
+/// Closes the channel's circuit. Returns whether it was open.
+pub fn record_success(channel_id: u64) -> bool {
+    let closed = CIRCUITS
+        .lock()
+        .unwrap()
+        .remove(&channel_id)
+        .is_some_and(|circuit| circuit.opened_at.is_some());
+
+    if closed {
+        tracing::info!(channel_id, "Channel circuit closed.");
+    }
+
+    closed
+}
+
+/// Counts a failed send, opening the channel's circuit once the threshold is reached. Returns whether it opened.
+pub fn record_failure(channel_id: u64) -> bool {
+    let mut circuits = CIRCUITS.lock().unwrap();
+    let circuit = circuits.entry(channel_id).or_default();
+    circuit.consecutive_failures += 1;
+
+    if circuit.opened_at.is_none()
+        && circuit.consecutive_failures >= CHANNEL_CIRCUIT_BREAKER_THRESHOLD
+    {
+        let now = chrono::Utc::now().timestamp_millis();
+        circuit.opened_at = Some(now);
+        circuit.last_attempt = Some(now);
+        metrics::increment("channel_circuits_opened");
+
+        tracing::warn!(
+            channel_id,
+            "Channel circuit opened after {} consecutive failures.",
+            circuit.consecutive_failures
+        );
+
+        return true;
+    }
+
+    false
+}
+
+/// Closes a channel's circuit by hand. Returns whether the channel had one.
+pub fn reset(channel_id: u64) -> bool {
+    CIRCUITS.lock().unwrap().remove(&channel_id).is_some()
+}
+
+/// Every channel with failures, keyed by channel id.
+pub fn snapshot() -> HashMap<u64, ChannelCircuit> {
+    CIRCUITS.lock().unwrap().clone()
+}
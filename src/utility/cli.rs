@@ -0,0 +1,210 @@
+use super::{
+    http::discord_client,
+    shard_eruption::{calculate_shard_eruption, shard_eruption_mismatch},
+    wind_paths::{shard_eruption, ShardEruptionResponse},
+};
+use crate::structures::{
+    config::Config, notification::NotificationType, schedule::run_simulation, test_send::test_send,
+};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone, Utc};
+use chrono_tz::{America::Los_Angeles, Tz};
+use clap::{Parser, Subcommand};
+use serenity::model::id::{ChannelId, GuildId};
+use sqlx::{Pool, Postgres};
+
+#[derive(Parser)]
+#[command(about = "Sends notifications for Sky: Children of the Light.")]
+pub struct Cli {
+    /// Runs a one-off command instead of the service.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Prints every notification the scheduler would emit between two dates, without sending anything.
+    Simulate {
+        /// The first day to simulate, in Sky time.
+        #[arg(long)]
+        from: NaiveDate,
+        /// The last day to simulate, in Sky time.
+        #[arg(long)]
+        to: NaiveDate,
+        /// Prints each notification as a JSON object per line.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Prints the calculated shard eruption for a day.
+    Shard {
+        /// The day, in Sky time. Defaults to today.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Also fetches today's shard eruption from the wind paths to compare.
+        #[arg(long)]
+        remote: bool,
+        /// Prints the shard eruptions as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Sends a notification to a channel now, using the server's settings for the type.
+    TestSend {
+        /// The server whose settings to use.
+        #[arg(long)]
+        guild_id: GuildId,
+        /// The channel to send to.
+        #[arg(long)]
+        channel_id: ChannelId,
+        /// The notification type, by number.
+        #[arg(long)]
+        r#type: NotificationType,
+    },
+}
+
+impl Command {
+    pub async fn run(self, config: &Config, pool: &Pool<Postgres>) -> Result<()> {
+        match self {
+            Self::Simulate { from, to, json } => simulate(config, pool, from, to, json).await,
+            Self::Shard { date, remote, json } => shard(config, date, remote, json).await,
+            Self::TestSend {
+                guild_id,
+                channel_id,
+                r#type,
+            } => {
+                let client = discord_client(&config.discord_token, &config.discord);
+                let message =
+                    test_send(&client, pool, config, guild_id, channel_id, r#type).await?;
+
+                println!("Sent {}.", message.link());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The start of a day in Sky time.
+fn start_of_day(date: NaiveDate) -> Result<DateTime<Tz>> {
+    Los_Angeles
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .with_context(|| format!("{date} has no midnight in Sky time."))
+}
+
+/// Runs the scheduler minute by minute over a date range.
+async fn simulate(
+    config: &Config,
+    pool: &Pool<Postgres>,
+    from: NaiveDate,
+    to: NaiveDate,
+    json: bool,
+) -> Result<()> {
+    if from > to {
+        bail!("--from must not be after --to.");
+    }
+
+    run_simulation(
+        pool,
+        config,
+        start_of_day(from)?,
+        start_of_day(to + TimeDelta::days(1))?,
+        |now, notification_notify| {
+            if json {
+                println!(
+                    "{}",
+                    serde_json::json!({ "at": now.to_rfc3339(), "notification": notification_notify })
+                );
+            } else {
+                println!(
+                    "{} {:?} in {} minutes",
+                    now.format("%Y-%m-%d %H:%M %Z"),
+                    notification_notify.r#type,
+                    notification_notify.time_until_start
+                );
+            }
+        },
+    )
+    .await
+}
+
+/// Prints the calculated shard eruption for a day, and today's from the wind paths if asked.
+async fn shard(config: &Config, date: Option<NaiveDate>, remote: bool, json: bool) -> Result<()> {
+    let today = Utc::now().with_timezone(&Los_Angeles).date_naive();
+    let date = date.unwrap_or(today);
+
+    if remote && date != today {
+        bail!("The wind paths only know today's shard eruption.");
+    }
+
+    let calculated = calculate_shard_eruption(date, &config.cdn_url);
+
+    let fetched = if remote {
+        Some(
+            shard_eruption(&config.wind_paths_url)
+                .await?
+                .map(ShardEruptionResponse::from),
+        )
+    } else {
+        None
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "date": date, "calculated": calculated, "remote": fetched })
+        );
+
+        return Ok(());
+    }
+
+    print_shard_eruption(&format!("Calculated for {date}"), calculated.as_ref());
+
+    if let Some(fetched) = fetched {
+        println!();
+        print_shard_eruption("From the wind paths", fetched.as_ref());
+        println!();
+
+        match shard_eruption_mismatch(fetched.as_ref(), calculated.as_ref()) {
+            Some(mismatch) => println!("{mismatch}"),
+            None => println!("The wind paths agree with the calculation."),
+        }
+    }
+
+    Ok(())
+}
+
+fn print_shard_eruption(heading: &str, shard_eruption: Option<&ShardEruptionResponse>) {
+    println!("{heading}:");
+
+    let Some(shard_eruption) = shard_eruption else {
+        println!("  No shard eruption.");
+        return;
+    };
+
+    println!("  Realm: {}", shard_eruption.realm);
+    println!("  Map: {}", shard_eruption.sky_map);
+
+    println!(
+        "  Strength: {}",
+        if shard_eruption.strong {
+            "Strong"
+        } else {
+            "Regular"
+        }
+    );
+
+    if let Some(variant) = &shard_eruption.variant {
+        println!("  Variant: {variant}");
+    }
+
+    println!("  Reward: {}", shard_eruption.reward_text());
+    println!("  Landings:");
+
+    for (index, dates) in shard_eruption.timestamps.iter().enumerate() {
+        println!(
+            "    {}. {} to {}",
+            index + 1,
+            dates.start.format("%H:%M:%S %Z"),
+            dates.end.format("%H:%M:%S %Z")
+        );
+    }
+}
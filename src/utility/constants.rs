@@ -1,9 +1,119 @@
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, time::Duration};
 
+/// How many minutes back the scheduler may look for occurrences it missed while down.
+pub const CATCH_UP_MAXIMUM: u32 = 120;
+pub const BLUESKY_MAXIMUM_LENGTH: usize = 300;
+pub const BLUESKY_SERVICE: &str = "https://bsky.social";
+/// How long a channel's kind and parent are trusted before Discord is asked again.
+pub const CHANNEL_CACHE_TTL: Duration = Duration::from_secs(600);
+pub const CDN_URL: &str = "https://cdn.thatskyapplication.com";
+pub const CHANNEL_CIRCUIT_BREAKER_THRESHOLD: u32 = 5;
+pub const CHANNEL_CIRCUIT_BREAKER_PROBE_INTERVAL: Duration = Duration::from_secs(1800);
+pub const COUNTDOWN_MAXIMUM_LENGTH: usize = 5;
+/// How long a dashboard caller's guilds are trusted before Discord is asked again.
+pub const DASHBOARD_CALLER_TTL: Duration = Duration::from_secs(60);
+/// How long channel deliveries are remembered, which must outlast any replay of a notification.
+pub const DELIVERY_RETENTION: chrono::TimeDelta = chrono::TimeDelta::days(2);
+pub const DISCORD_API_URL: &str = "https://discord.com/api/v10";
+/// A global rate limit lasting at least this long fails sends over to the secondary Discord token.
+pub const DISCORD_TOKEN_FAILOVER_RATE_LIMIT: Duration = Duration::from_secs(10);
+pub const DISPATCHER_CONCURRENCY: usize = 25;
+pub const DISPATCHER_JITTER: u64 = 2000;
+/// Notifications of occurrences starting now lose their value if spread out much further.
+pub const DISPATCHER_START_JITTER_MAXIMUM: u64 = 10000;
+pub const EMAIL_DIGEST_INTERVAL: Duration = Duration::from_secs(60);
+/// Digests due longer ago than this, such as while the service was down, are skipped as out of date.
+pub const EMAIL_DIGEST_MAXIMUM_LATENESS: chrono::TimeDelta = chrono::TimeDelta::hours(2);
+pub const GUILD_CLEANUP_INTERVAL: u32 = 1440;
+/// How many notifications the event stream holds for a listener that is behind.
+pub const EVENT_STREAM_CAPACITY: usize = 256;
+pub const FEED_MAXIMUM_ENTRIES: i64 = 50;
+/// How long dispatched notifications are kept for feeds.
+pub const FEED_RETENTION: chrono::TimeDelta = chrono::TimeDelta::days(7);
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long idle connections to external services are kept open for reuse.
+pub const HTTP_KEEPALIVE: Duration = Duration::from_secs(90);
+pub const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DATABASE_MAXIMUM_ATTEMPTS: u32 = 3;
+pub const DATABASE_MAX_CONNECTIONS: u32 = 2;
+pub const DATABASE_ACQUIRE_TIMEOUT: u64 = 10000;
+pub const DATABASE_STATEMENT_TIMEOUT: u64 = 10000;
+/// How often the time taken to get a connection from the pool is measured.
+pub const DATABASE_POOL_SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+/// Waiting at least this long for a connection from the pool is logged, as the pool is too small for the load.
+pub const DATABASE_POOL_SLOW_WAIT: Duration = Duration::from_secs(1);
+pub const DATABASE_CIRCUIT_BREAKER_THRESHOLD: u32 = 10;
+pub const DATABASE_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+pub const NATS_SUBJECT_PREFIX: &str = "notifications";
+pub const NOTIFY_RESTART_MAXIMUM_BACKOFF: Duration = Duration::from_secs(300);
+pub const NOTIFY_HEALTHY_RUN: Duration = Duration::from_secs(300);
+pub const NOTIFY_PANIC_ALERT_THRESHOLD: u32 = 3;
+pub const LATENCY_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+pub const LATENCY_MAXIMUM_SAMPLES: usize = 100_000;
+pub const LATENCY_OBJECTIVE: u32 = 60;
+/// The most minutes before an occurrence any type's notifications may be requested.
+pub const LEAD_TIME_MAXIMUM: u32 = 60;
+pub const LEADER_ELECTION_INTERVAL: Duration = Duration::from_secs(15);
+/// An arbitrary key identifying this service's advisory lock.
+pub const LEADER_ELECTION_LOCK_KEY: i64 = 0x534b_594e_4f54;
+pub const MASTODON_MAXIMUM_LENGTH: usize = 500;
 pub const MAXIMUM_CHANNEL_CAPACITY: usize = 10;
+pub const MAXIMUM_NOTIFICATION_AGE: u32 = 300;
+pub const OUTBOX_DRAIN_INTERVAL: Duration = Duration::from_secs(5);
+pub const PERMISSION_CHECK_INTERVAL: u32 = 360;
+/// How long a rendered public API response is served before it is rendered again.
+pub const PUBLIC_API_CACHE_TTL: Duration = Duration::from_secs(60);
+pub const PUBLIC_API_RATE_LIMIT: u32 = 60;
+pub const PUBLIC_API_RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+/// How often expired responses and request windows are forgotten, so the maps do not grow without bound.
+pub const PUBLIC_API_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+pub const REDIS_STREAM: &str = "notifications";
+pub const REDIS_GROUP: &str = "senders";
+pub const REDIS_BATCH_SIZE: usize = 10;
+pub const REDIS_BLOCK: Duration = Duration::from_secs(5);
+/// How long a worker may hold a message before another worker claims it.
+pub const REDIS_CLAIM_IDLE: Duration = Duration::from_secs(60);
+/// How often a worker renews its claim on the message it is sending, well within the idle time so long fan-outs are not taken over.
+pub const REDIS_CLAIM_HEARTBEAT: Duration = Duration::from_secs(15);
+pub const REDIS_RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+pub const REMINDER_INTERVAL: Duration = Duration::from_secs(15);
+pub const REMINDER_MAXIMUM_LATENESS: chrono::TimeDelta = chrono::TimeDelta::minutes(15);
+/// How long a send may take before it is given up on, unless configured for its type.
+pub const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+/// Sends of occurrences starting now are worthless once late, so are given up on sooner.
+pub const START_SEND_TIMEOUT: Duration = Duration::from_secs(10);
 pub const INTERNATIONAL_SPACE_STATION_DATES: [u32; 4] = [6, 14, 22, 30];
-pub const INTERNATIONAL_SPACE_STATION_PRIOR_DATES: [u32; 4] = [5, 13, 21, 29];
+/// How many minutes each wax event lasts once it starts.
+pub const POLLUTED_GEYSER_DURATION: u32 = 10;
+pub const GRANDMA_DURATION: u32 = 10;
+pub const TURTLE_DURATION: u32 = 10;
+pub const SHARD_ERUPTION_LEAD_TIME: u32 = 10;
+pub const SHARD_ERUPTION_END_LEAD_TIME: u32 = 10;
+/// How often the types and offsets anything is subscribed to are reloaded.
+pub const SUBSCRIBER_INDEX_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// How long to wait before listening for subscription changes again after the connection is lost.
+pub const SUBSCRIBER_INDEX_LISTEN_RETRY: Duration = Duration::from_secs(5);
+/// How many subscriptions are retrieved and sent to at a time.
+pub const SUBSCRIPTION_PAGE_SIZE: i64 = 1000;
+pub const TRAVELLING_SPIRIT_LEAD_TIME: u32 = 15;
+/// The marks, in minutes, at which events known days in advance may also be notified of: an hour, 12 hours, and a day before.
+pub const LONG_LEAD_OFFSETS: [u32; 3] = [60, 720, 1440];
+pub const TRAVELLING_SPIRIT_IMPORT_INTERVAL: u32 = 60;
+/// Travelling spirits further ahead than this are assumed to be mistakes.
+pub const TRAVELLING_SPIRIT_MAXIMUM_HORIZON: chrono::TimeDelta = chrono::TimeDelta::days(365);
+pub const WIND_PATHS_TIMEOUT: Duration = Duration::from_secs(15);
+pub const WIND_PATHS_MAXIMUM_ATTEMPTS: u32 = 3;
+
+/// The realms shard eruptions land in, as named by [`SkyMap::realm`].
+pub const REALMS: [&str; 5] = [
+    "Daylight Prairie",
+    "Hidden Forest",
+    "Valley of Triumph",
+    "Golden Wasteland",
+    "Vault of Knowledge",
+];
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum SkyMap {
@@ -51,6 +161,10 @@ pub enum SkyMap {
     JellyfishCove,
     #[serde(rename = "Starlight Desert")]
     StarlightDesert,
+
+    // A map this version does not know about yet.
+    #[serde(untagged)]
+    Other(String),
 }
 
 impl fmt::Display for SkyMap {
@@ -62,3 +176,28 @@ impl fmt::Display for SkyMap {
         )
     }
 }
+
+impl SkyMap {
+    pub fn realm(&self) -> &'static str {
+        match self {
+            SkyMap::BirdNest
+            | SkyMap::ButterflyFields
+            | SkyMap::Cave
+            | SkyMap::KoiPond
+            | SkyMap::SanctuaryIslands => "Daylight Prairie",
+            SkyMap::Boneyard
+            | SkyMap::ElevatedClearing
+            | SkyMap::ForestBrook
+            | SkyMap::ForestEnd
+            | SkyMap::Treehouse => "Hidden Forest",
+            SkyMap::IceRink | SkyMap::HermitValley | SkyMap::VillageOfDreams => "Valley of Triumph",
+            SkyMap::Battlefield
+            | SkyMap::BrokenTemple
+            | SkyMap::CrabFields
+            | SkyMap::ForgottenArk
+            | SkyMap::Graveyard => "Golden Wasteland",
+            SkyMap::JellyfishCove | SkyMap::StarlightDesert => "Vault of Knowledge",
+            SkyMap::Other(_) => "Unknown",
+        }
+    }
+}
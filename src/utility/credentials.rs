@@ -0,0 +1,70 @@
+use super::{
+    database::{connect_options, read_replica},
+    discord_clients::DiscordClients,
+};
+use crate::structures::config::Config;
+use anyhow::{Context, Result};
+use sqlx::{Connection, PgConnection, Pool, Postgres};
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Rotates the Discord tokens and database credentials without a restart, as they are now in the environment file and configuration.
+///
+/// Both are checked before either is switched to, so a mistake leaves the old credentials in use. Database connections already open carry on until they are recycled.
+pub async fn reload_credentials(clients: &DiscordClients, pool: &Pool<Postgres>) -> Result<()> {
+    // The process's environment cannot be changed from outside, so the environment file is read again over it.
+    dotenvy::dotenv_override().ok();
+    let config = Config::load()?;
+
+    let options = connect_options(&config.database_url, &config.database)
+        .context("Error parsing DATABASE_URL.")?;
+
+    PgConnection::connect_with(&options)
+        .await
+        .context("The database rejected the new credentials.")?
+        .close()
+        .await?;
+
+    // A replica added since startup is only connected to after a restart.
+    let read_options = match (read_replica(), config.database_read_url.as_deref()) {
+        (Some(read_pool), Some(database_read_url)) => {
+            let read_options = connect_options(database_read_url, &config.database)
+                .context("Error parsing DATABASE_READ_URL.")?;
+
+            PgConnection::connect_with(&read_options)
+                .await
+                .context("The read replica rejected the new credentials.")?
+                .close()
+                .await?;
+
+            Some((read_pool, read_options))
+        }
+        _ => None,
+    };
+
+    clients.reload(&config).await?;
+    pool.set_connect_options(options);
+
+    if let Some((read_pool, read_options)) = read_options {
+        read_pool.set_connect_options(read_options);
+    }
+    tracing::info!("Reloaded credentials.");
+    Ok(())
+}
+
+/// Reloads the credentials whenever the process is sent SIGHUP.
+pub async fn reload_on_hangup(clients: Arc<DiscordClients>, pool: Pool<Postgres>) {
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(error) => {
+            tracing::error!("Failed to listen for SIGHUP: {error:?}");
+            return;
+        }
+    };
+
+    while hangups.recv().await.is_some() {
+        if let Err(error) = reload_credentials(&clients, &pool).await {
+            tracing::error!("Failed to reload credentials: {error:?}");
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use super::{
+    constants::{DASHBOARD_CALLER_TTL, DISCORD_API_URL},
+    discord_clients::DiscordClients,
+    http::CLIENT,
+    permissions::GuildView,
+};
+use crate::structures::{
+    audit_log::{audit, AuditEvent},
+    config::Config,
+    notification::NotificationType,
+    subscription::{
+        get_subscriptions, replace_subscriptions, subscribe, unsubscribe, Subscription,
+    },
+};
+use anyhow::Result;
+use axum::{
+    extract::{Path, Request, State},
+    http::{header::AUTHORIZATION, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Extension, Json, Router,
+};
+use serde::Deserialize;
+use serenity::{
+    all::Permissions,
+    model::id::{GuildId, UserId},
+};
+use sqlx::{Pool, Postgres};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock, Mutex},
+    time::Instant,
+};
+use tokio::net::TcpListener;
+
+/// Callers recently looked up, by access token, so Discord is not asked on every request.
+static CALLERS: LazyLock<Mutex<HashMap<String, Arc<Caller>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct DashboardState {
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+    clients: Arc<DiscordClients>,
+}
+
+/// A Discord user signed in to the dashboard.
+struct Caller {
+    looked_up_at: Instant,
+    user_id: UserId,
+    /// The guilds the user may manage the notifications of.
+    guild_ids: HashSet<GuildId>,
+}
+
+#[derive(Deserialize)]
+struct CurrentUser {
+    id: UserId,
+}
+
+#[derive(Deserialize)]
+struct CurrentUserGuild {
+    id: GuildId,
+    owner: bool,
+    /// The user's permissions in the guild, as a string as it may not fit in a JavaScript number.
+    permissions: String,
+}
+
+impl CurrentUserGuild {
+    fn is_manageable(&self) -> bool {
+        let permissions = Permissions::from_bits_truncate(self.permissions.parse().unwrap_or(0));
+
+        self.owner
+            || permissions.contains(Permissions::ADMINISTRATOR)
+            || permissions.contains(Permissions::MANAGE_GUILD)
+    }
+}
+
+/// Serves the dashboard API until the listener fails.
+///
+/// Requests carry a Discord OAuth2 access token with the `identify` and `guilds` scopes. A guild's notifications may be managed by whoever has Manage Server there.
+pub async fn serve(
+    config: Arc<Config>,
+    pool: Pool<Postgres>,
+    clients: Arc<DiscordClients>,
+) -> Result<()> {
+    let Some(address) = config.dashboard.address.clone() else {
+        return Ok(());
+    };
+
+    let router = Router::new()
+        .route("/guilds", get(guilds))
+        .route(
+            "/guilds/:guild_id/subscriptions",
+            get(export_subscriptions).put(import_subscriptions),
+        )
+        .route(
+            "/guilds/:guild_id/subscriptions/:type",
+            put(update_subscription).delete(delete_subscription),
+        )
+        .layer(middleware::from_fn(authenticate))
+        .with_state(Arc::new(DashboardState {
+            pool,
+            config: config.clone(),
+            clients,
+        }));
+
+    let listener = TcpListener::bind(&address).await?;
+    tracing::info!("Dashboard API listening on {address}.");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+async fn authenticate(mut request: Request, next: Next) -> Response {
+    let Some(access_token) = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+    else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    let caller = match caller(&access_token).await {
+        Ok(Some(caller)) => caller,
+        Ok(None) => return StatusCode::UNAUTHORIZED.into_response(),
+        Err(error) => {
+            tracing::error!("Failed to look up a dashboard caller: {error:?}");
+            return StatusCode::BAD_GATEWAY.into_response();
+        }
+    };
+
+    request.extensions_mut().insert(caller);
+    next.run(request).await
+}
+
+/// Looks up who an access token belongs to and which guilds they manage. Returns `None` if Discord rejects the token.
+async fn caller(access_token: &str) -> Result<Option<Arc<Caller>>> {
+    if let Some(caller) = CALLERS.lock().unwrap().get(access_token) {
+        if caller.looked_up_at.elapsed() < DASHBOARD_CALLER_TTL {
+            return Ok(Some(caller.clone()));
+        }
+    }
+
+    let current_user = CLIENT
+        .get(format!("{DISCORD_API_URL}/users/@me"))
+        .bearer_auth(access_token)
+        .send()
+        .await?;
+
+    if current_user.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Ok(None);
+    }
+
+    let current_user = current_user
+        .error_for_status()?
+        .json::<CurrentUser>()
+        .await?;
+
+    let guild_ids = CLIENT
+        .get(format!("{DISCORD_API_URL}/users/@me/guilds"))
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Vec<CurrentUserGuild>>()
+        .await?
+        .into_iter()
+        .filter(CurrentUserGuild::is_manageable)
+        .map(|guild| guild.id)
+        .collect();
+
+    let caller = Arc::new(Caller {
+        looked_up_at: Instant::now(),
+        user_id: current_user.id,
+        guild_ids,
+    });
+
+    let mut callers = CALLERS.lock().unwrap();
+    callers.retain(|_, caller| caller.looked_up_at.elapsed() < DASHBOARD_CALLER_TTL);
+    callers.insert(access_token.to_string(), caller.clone());
+    Ok(Some(caller))
+}
+
+/// Records a change made through the dashboard.
+async fn audit_change(pool: &Pool<Postgres>, caller: &Caller, guild_id: GuildId, action: &str) {
+    audit(
+        pool,
+        AuditEvent {
+            guild_id: Some(guild_id),
+            user_id: Some(caller.user_id),
+            actor: "dashboard",
+            action,
+            ..Default::default()
+        },
+    )
+    .await;
+}
+
+/// The guilds the caller may manage the notifications of.
+async fn guilds(Extension(caller): Extension<Arc<Caller>>) -> impl IntoResponse {
+    Json(
+        caller
+            .guild_ids
+            .iter()
+            .map(GuildId::to_string)
+            .collect::<Vec<_>>(),
+    )
+}
+
+async fn export_subscriptions(
+    State(state): State<Arc<DashboardState>>,
+    Extension(caller): Extension<Arc<Caller>>,
+    Path(guild_id): Path<GuildId>,
+) -> Response {
+    if !caller.guild_ids.contains(&guild_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    match get_subscriptions(&state.pool, guild_id).await {
+        Ok(subscriptions) => Json(subscriptions).into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to export subscriptions: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn import_subscriptions(
+    State(state): State<Arc<DashboardState>>,
+    Extension(caller): Extension<Arc<Caller>>,
+    Path(guild_id): Path<GuildId>,
+    Json(subscriptions): Json<Vec<Subscription>>,
+) -> Response {
+    if !caller.guild_ids.contains(&guild_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if let Some(response) = check_channels(&state.clients, guild_id, &subscriptions).await {
+        return response;
+    }
+
+    match replace_subscriptions(&state.pool, &state.config, guild_id, &subscriptions).await {
+        Ok(()) => {
+            audit_change(&state.pool, &caller, guild_id, "subscriptions_replaced").await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(error) if error.is::<sqlx::Error>() => {
+            tracing::error!(%guild_id, "Failed to import subscriptions: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(error) => (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response(),
+    }
+}
+
+/// Checks the bot can send to each subscription's channel and mention its role, which also keeps them within the guild. Returns the response to refuse with if not.
+async fn check_channels(
+    clients: &DiscordClients,
+    guild_id: GuildId,
+    subscriptions: &[Subscription],
+) -> Option<Response> {
+    let client = clients.primary();
+
+    let guild_view = match GuildView::fetch(&client, guild_id).await {
+        Ok(Some(guild_view)) => guild_view,
+        Ok(None) => {
+            return Some(
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "The bot is not in the server.",
+                )
+                    .into_response(),
+            )
+        }
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to look up a guild: {error:?}");
+            return Some(StatusCode::BAD_GATEWAY.into_response());
+        }
+    };
+
+    for subscription in subscriptions {
+        match guild_view
+            .channel_problem(&client, subscription.channel_id, subscription.role_id)
+            .await
+        {
+            Ok(None) => {}
+            Ok(Some(problem)) => {
+                return Some(
+                    (
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("{}: {problem}", subscription.r#type),
+                    )
+                        .into_response(),
+                )
+            }
+            Err(error) => {
+                tracing::error!(%guild_id, "Failed to check a channel: {error:?}");
+                return Some(StatusCode::BAD_GATEWAY.into_response());
+            }
+        }
+    }
+
+    None
+}
+
+/// Subscribes to a type, replacing any existing subscription to it.
+async fn update_subscription(
+    State(state): State<Arc<DashboardState>>,
+    Extension(caller): Extension<Arc<Caller>>,
+    Path((guild_id, r#type)): Path<(GuildId, NotificationType)>,
+    Json(subscription): Json<Subscription>,
+) -> Response {
+    if !caller.guild_ids.contains(&guild_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    if subscription.r#type != r#type {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The subscription's type does not match the path.",
+        )
+            .into_response();
+    }
+
+    if let Err(error) = subscription.validate(&state.config) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response();
+    }
+
+    if let Some(response) = check_channels(
+        &state.clients,
+        guild_id,
+        std::slice::from_ref(&subscription),
+    )
+    .await
+    {
+        return response;
+    }
+
+    match subscribe(&state.pool, guild_id, &subscription).await {
+        Ok(()) => {
+            audit_change(&state.pool, &caller, guild_id, "subscribed").await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to subscribe: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn delete_subscription(
+    State(state): State<Arc<DashboardState>>,
+    Extension(caller): Extension<Arc<Caller>>,
+    Path((guild_id, r#type)): Path<(GuildId, NotificationType)>,
+) -> Response {
+    if !caller.guild_ids.contains(&guild_id) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    match unsubscribe(&state.pool, guild_id, r#type).await {
+        Ok(true) => {
+            audit_change(&state.pool, &caller, guild_id, "unsubscribed").await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!(%guild_id, "Failed to unsubscribe: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
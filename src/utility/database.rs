@@ -0,0 +1,212 @@
+use super::{
+    constants::{
+        DATABASE_CIRCUIT_BREAKER_COOLDOWN, DATABASE_CIRCUIT_BREAKER_THRESHOLD,
+        DATABASE_MAXIMUM_ATTEMPTS, DATABASE_POOL_SAMPLE_INTERVAL, DATABASE_POOL_SLOW_WAIT,
+    },
+    metrics,
+};
+use crate::structures::config::DatabaseConfig;
+use anyhow::{Context, Result};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Pool, Postgres,
+};
+use std::{
+    fmt,
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicI64, AtomicU32, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::time::sleep;
+
+/// The read replica subscriptions are looked up from, if one is configured.
+static READ_POOL: OnceLock<Pool<Postgres>> = OnceLock::new();
+
+/// Parses a database URL into options that apply the statement timeout to every connection.
+pub fn connect_options(url: &str, config: &DatabaseConfig) -> Result<PgConnectOptions> {
+    let options: PgConnectOptions = url.parse()?;
+
+    Ok(if config.statement_timeout > 0 {
+        options.options([(
+            "statement_timeout",
+            format!("{}ms", config.statement_timeout),
+        )])
+    } else {
+        options
+    })
+}
+
+/// Opens a pool of connections to a database, sized and timed out as configured.
+pub async fn connect(url: &str, config: &DatabaseConfig) -> Result<Pool<Postgres>> {
+    Ok(PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .acquire_timeout(Duration::from_millis(config.acquire_timeout))
+        .connect_with(connect_options(url, config)?)
+        .await?)
+}
+
+/// Connects to the read replica, if a URL is configured.
+pub async fn connect_read_replica(url: Option<&str>, config: &DatabaseConfig) -> Result<()> {
+    let Some(url) = url else {
+        return Ok(());
+    };
+
+    let pool = connect(url, config)
+        .await
+        .context("Error connecting to the read replica.")?;
+
+    tokio::spawn(monitor_pool(pool.clone(), "read replica"));
+    READ_POOL.get_or_init(|| pool);
+    tracing::info!("Looking up subscriptions from the read replica.");
+    Ok(())
+}
+
+/// The pool to run reads that tolerate replication lag on: the read replica, or the primary if there is none.
+pub fn read_pool(primary: &Pool<Postgres>) -> &Pool<Postgres> {
+    READ_POOL.get().unwrap_or(primary)
+}
+
+/// The read replica's pool, if one is connected.
+pub fn read_replica() -> Option<&'static Pool<Postgres>> {
+    READ_POOL.get()
+}
+
+/// Consecutive failed operations across the whole process.
+static CONSECUTIVE_FAILURES: AtomicU32 = AtomicU32::new(0);
+
+/// When the circuit was opened, as a Unix timestamp in milliseconds. 0 means it is closed.
+static OPENED_AT: AtomicI64 = AtomicI64::new(0);
+
+fn circuit_open() -> bool {
+    let opened_at = OPENED_AT.load(Ordering::Relaxed);
+
+    opened_at != 0
+        && chrono::Utc::now().timestamp_millis() - opened_at
+            < DATABASE_CIRCUIT_BREAKER_COOLDOWN.as_millis() as i64
+}
+
+/// The error operations fail with while the circuit is open, without reaching the database.
+#[derive(Debug)]
+pub struct CircuitOpen;
+
+impl fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The database circuit is open after repeated failures.")
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+/// Whether an error may go away if the operation is tried again, as when the database cannot be reached. Others, such as constraint violations, would fail the same way again.
+fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => true,
+        // Connection exceptions, too many connections, and the server shutting down or starting up.
+        sqlx::Error::Database(error) => error.code().is_some_and(|code| {
+            code.starts_with("08") || matches!(&*code, "53300" | "57P01" | "57P02" | "57P03")
+        }),
+        _ => false,
+    }
+}
+
+/// Periodically measures how long getting a connection from a pool takes, as a pool too small for the load makes every query wait.
+///
+/// The waits are added up in the `database_pool_wait_milliseconds` metric over `database_pool_samples` samples.
+pub async fn monitor_pool(pool: Pool<Postgres>, name: &'static str) {
+    loop {
+        sleep(DATABASE_POOL_SAMPLE_INTERVAL).await;
+        let started = Instant::now();
+
+        match pool.acquire().await {
+            Ok(connection) => {
+                drop(connection);
+                let waited = started.elapsed();
+                metrics::increment("database_pool_samples");
+                metrics::increment_by("database_pool_wait_milliseconds", waited.as_millis() as u64);
+
+                if waited >= DATABASE_POOL_SLOW_WAIT {
+                    tracing::warn!(
+                        size = pool.size(),
+                        "Waited {waited:?} for a connection to the {name}."
+                    );
+                }
+            }
+            Err(error) => {
+                metrics::increment("database_pool_timeouts");
+                tracing::warn!("Failed to get a connection to the {name}: {error:?}");
+            }
+        }
+    }
+}
+
+/// Runs a database operation, retrying with exponential backoff if it fails transiently. Other errors are returned at once.
+///
+/// After repeated transient failures the circuit opens and operations fail immediately with [`CircuitOpen`] until the cooldown passes,
+/// so callers fall back to their caches instead of piling onto an unreachable database.
+pub async fn with_retry<T, F, Fut>(operation: &str, mut f: F) -> Result<T, sqlx::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, sqlx::Error>>,
+{
+    if circuit_open() {
+        return Err(sqlx::Error::Io(io::Error::other(CircuitOpen)));
+    }
+
+    let mut attempt = 1;
+
+    loop {
+        match f().await {
+            Ok(value) => {
+                CONSECUTIVE_FAILURES.store(0, Ordering::Relaxed);
+                OPENED_AT.store(0, Ordering::Relaxed);
+                return Ok(value);
+            }
+            Err(error) if !is_transient(&error) => return Err(error),
+            Err(error) => {
+                let failures = CONSECUTIVE_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+
+                if failures >= DATABASE_CIRCUIT_BREAKER_THRESHOLD {
+                    if OPENED_AT.swap(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed) == 0
+                    {
+                        tracing::error!(
+                            "Database circuit opened after {failures} consecutive failures."
+                        );
+                    }
+
+                    return Err(error);
+                }
+
+                if attempt >= DATABASE_MAXIMUM_ATTEMPTS {
+                    return Err(error);
+                }
+
+                tracing::warn!(
+                    "Database operation {operation} failed (attempt {attempt} of {DATABASE_MAXIMUM_ATTEMPTS}): {error:?}"
+                );
+
+                sleep(Duration::from_millis(250 * 2u64.pow(attempt))).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Replaces a cached value with freshly retrieved data, keeping the cached value if retrieval failed.
+///
+/// Returns whether the cache was updated.
+pub fn refresh<T>(cache: &mut T, result: Result<T, sqlx::Error>, name: &str) -> bool {
+    match result {
+        Ok(value) => {
+            *cache = value;
+            true
+        }
+        Err(error) => {
+            tracing::error!("Failed to refresh the {name}. Continuing with cached data: {error:?}");
+            false
+        }
+    }
+}
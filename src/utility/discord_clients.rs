@@ -0,0 +1,210 @@
+use super::{
+    alert::alert, constants::DISCORD_TOKEN_FAILOVER_RATE_LIMIT, dispatcher::Dispatcher,
+    http::discord_client, metrics,
+};
+use crate::structures::config::Config;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serenity::http::{Http, HttpError, RatelimitInfo};
+use std::sync::{
+    atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering},
+    Arc, RwLock,
+};
+
+/// What is known of whether a token can be sent with.
+#[derive(Default)]
+struct TokenHealth {
+    /// Whether Discord rejected the token, which only a new token fixes.
+    invalid: AtomicBool,
+    /// Until when the token is severely rate limited, as a Unix timestamp in milliseconds.
+    rate_limited_until: AtomicI64,
+}
+
+impl TokenHealth {
+    fn is_healthy(&self, now: i64) -> bool {
+        !self.invalid.load(Ordering::Relaxed)
+            && self.rate_limited_until.load(Ordering::Relaxed) <= now
+    }
+
+    /// Notes a rate limit. Only global ones lasting long enough make the token unhealthy, as route limits only hold back one channel.
+    fn on_ratelimit(&self, ratelimit_info: &RatelimitInfo) {
+        if ratelimit_info.global && ratelimit_info.timeout >= DISCORD_TOKEN_FAILOVER_RATE_LIMIT {
+            let until =
+                chrono::Utc::now().timestamp_millis() + ratelimit_info.timeout.as_millis() as i64;
+
+            self.rate_limited_until.fetch_max(until, Ordering::Relaxed);
+        }
+    }
+}
+
+struct Token {
+    /// How the token is referred to, such as "primary".
+    name: &'static str,
+    client: Arc<Http>,
+    health: Arc<TokenHealth>,
+}
+
+#[derive(Serialize)]
+pub struct TokenStatus {
+    pub name: &'static str,
+    pub active: bool,
+    pub invalid: bool,
+    /// Until when the token is severely rate limited, as a Unix timestamp in milliseconds.
+    pub rate_limited_until: Option<i64>,
+}
+
+/// The Discord clients notifications are sent with, one per configured token.
+///
+/// Sends use the primary token while it is healthy and fail over to the secondary one while it is not. The secondary token belongs to another bot, so only sends fail over. Checks of what the bot can see always use the primary token.
+pub struct DiscordClients {
+    /// Replaced as a whole when the tokens are rotated.
+    tokens: RwLock<Vec<Token>>,
+    active: AtomicUsize,
+    dispatcher: Arc<Dispatcher>,
+    alert_webhook_url: Option<String>,
+}
+
+impl DiscordClients {
+    /// Builds a client for each configured token, reporting their rate limits to the dispatcher.
+    pub fn new(config: &Config, dispatcher: Arc<Dispatcher>) -> Self {
+        Self {
+            tokens: RwLock::new(build_tokens(config, &dispatcher)),
+            active: AtomicUsize::new(0),
+            dispatcher,
+            alert_webhook_url: config.alert_webhook_url.clone(),
+        }
+    }
+
+    /// Switches to the tokens of a newly loaded configuration, with their health forgotten.
+    ///
+    /// Every token is checked with Discord first, so the clients are only replaced if all of them work.
+    pub async fn reload(&self, config: &Config) -> Result<()> {
+        let tokens = build_tokens(config, &self.dispatcher);
+
+        for token in &tokens {
+            token
+                .client
+                .get_current_user()
+                .await
+                .with_context(|| format!("Discord rejected the new {} token.", token.name))?;
+        }
+
+        *self.tokens.write().unwrap() = tokens;
+        self.active.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The primary token's client, for requests whose answers depend on the bot making them.
+    pub fn primary(&self) -> Arc<Http> {
+        self.tokens.read().unwrap()[0].client.clone()
+    }
+
+    /// The client to send with: the first healthy token's, or the primary's if none is. Switching tokens is alerted.
+    pub fn current(&self) -> Arc<Http> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let tokens = self.tokens.read().unwrap();
+
+        let index = tokens
+            .iter()
+            .position(|token| token.health.is_healthy(now))
+            .unwrap_or(0);
+
+        let previous = self.active.swap(index, Ordering::Relaxed);
+
+        if previous != index {
+            metrics::increment("discord_token_failovers");
+
+            let message = format!(
+                "Sending with the {} Discord token instead of the {} one.",
+                tokens[index].name, tokens[previous].name
+            );
+
+            let alert_webhook_url = self.alert_webhook_url.clone();
+            tokio::spawn(async move { alert(alert_webhook_url.as_deref(), &message).await });
+        }
+
+        tokens[index].client.clone()
+    }
+
+    /// Notes a failed request made with a client, marking its token invalid if Discord rejected it. Returns whether it did.
+    pub fn record_error(&self, client: &Http, error: &anyhow::Error) -> bool {
+        let unauthorized = matches!(
+            error.downcast_ref::<serenity::Error>(),
+            Some(serenity::Error::Http(HttpError::UnsuccessfulRequest(response)))
+                if response.status_code.as_u16() == 401
+        );
+
+        if !unauthorized {
+            return false;
+        }
+
+        let tokens = self.tokens.read().unwrap();
+
+        let Some(token) = tokens
+            .iter()
+            .find(|token| std::ptr::eq(token.client.as_ref(), client))
+        else {
+            return false;
+        };
+
+        if !token.health.invalid.swap(true, Ordering::Relaxed) {
+            tracing::error!("Discord rejected the {} token.", token.name);
+        }
+
+        true
+    }
+
+    /// Each token's health, primary first.
+    pub fn statuses(&self) -> Vec<TokenStatus> {
+        let active = self.active.load(Ordering::Relaxed);
+        let now = chrono::Utc::now().timestamp_millis();
+
+        self.tokens
+            .read()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .map(|(index, token)| TokenStatus {
+                name: token.name,
+                active: index == active,
+                invalid: token.health.invalid.load(Ordering::Relaxed),
+                rate_limited_until: Some(token.health.rate_limited_until.load(Ordering::Relaxed))
+                    .filter(|until| *until > now),
+            })
+            .collect()
+    }
+}
+
+fn build_tokens(config: &Config, dispatcher: &Arc<Dispatcher>) -> Vec<Token> {
+    [
+        Some(("primary", config.discord_token.as_str())),
+        config
+            .secondary_discord_token
+            .as_deref()
+            .map(|token| ("secondary", token)),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(name, token)| {
+        let health = Arc::new(TokenHealth::default());
+        let mut client = discord_client(token, &config.discord);
+
+        // A proxy handles rate limits itself, so there is no rate limiter to hear from.
+        if let Some(ratelimiter) = client.ratelimiter.as_mut() {
+            let dispatcher = dispatcher.clone();
+            let health = health.clone();
+
+            ratelimiter.set_ratelimit_callback(Box::new(move |ratelimit_info| {
+                health.on_ratelimit(&ratelimit_info);
+                dispatcher.on_ratelimit(ratelimit_info);
+            }));
+        }
+
+        Token {
+            name,
+            client: Arc::new(client),
+            health,
+        }
+    })
+    .collect()
+}
@@ -0,0 +1,129 @@
+use super::metrics;
+use futures::future::join_all;
+use serenity::http::RatelimitInfo;
+use std::{
+    future::Future,
+    sync::atomic::{AtomicI64, Ordering},
+    time::Duration,
+};
+use tokio::{
+    sync::{Semaphore, SemaphorePermit},
+    time::sleep,
+};
+
+/// How soon a send must happen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Urgency {
+    /// Loses its value if delayed at all, as a shard eruption does.
+    Immediate,
+    /// Of an occurrence starting now, which may be spread across the start jitter.
+    Starting,
+    /// Everything else, spread across the jitter and held back while the global rate limit is exhausted.
+    Routine,
+}
+
+/// Coordinates sends so a large fan-out does not starve time-sensitive notifications of rate limit budget.
+pub struct Dispatcher {
+    /// Time-sensitive sends have their own permits so they never queue behind routine ones.
+    urgent: Semaphore,
+    regular: Semaphore,
+    jitter: Duration,
+    /// Spreads sends of occurrences starting now, so thousands of channels are not sent to in the same instant.
+    start_jitter: Duration,
+    /// When the global rate limit resets, as a Unix timestamp in milliseconds.
+    global_ratelimit_reset: AtomicI64,
+}
+
+impl Dispatcher {
+    pub fn new(concurrency: usize, jitter: Duration, start_jitter: Duration) -> Self {
+        Self {
+            urgent: Semaphore::new(concurrency),
+            regular: Semaphore::new((concurrency / 2).max(1)),
+            jitter,
+            start_jitter,
+            global_ratelimit_reset: AtomicI64::new(0),
+        }
+    }
+
+    /// Records rate limit information reported by serenity's ratelimiter.
+    pub fn on_ratelimit(&self, ratelimit_info: RatelimitInfo) {
+        tracing::warn!(
+            global = ratelimit_info.global,
+            path = ratelimit_info.path,
+            limit = ratelimit_info.limit,
+            "Rate limited for {:?}.",
+            ratelimit_info.timeout
+        );
+
+        if ratelimit_info.global {
+            metrics::increment("discord_global_ratelimits");
+
+            let reset =
+                chrono::Utc::now().timestamp_millis() + ratelimit_info.timeout.as_millis() as i64;
+
+            self.global_ratelimit_reset
+                .fetch_max(reset, Ordering::Relaxed);
+        } else {
+            metrics::increment("discord_route_ratelimits");
+        }
+    }
+
+    /// Waits for a send slot.
+    ///
+    /// Sends other than immediate ones are spread out deterministically by `key`. Routine sends also hold back while the global rate limit is exhausted.
+    pub async fn acquire(&self, urgency: Urgency, key: u64) -> SemaphorePermit<'_> {
+        let jitter = match urgency {
+            Urgency::Immediate => Duration::ZERO,
+            Urgency::Starting => self.start_jitter,
+            Urgency::Routine => self.jitter,
+        };
+
+        if !jitter.is_zero() {
+            sleep(Duration::from_millis(key % jitter.as_millis() as u64)).await;
+        }
+
+        if urgency != Urgency::Routine {
+            return self
+                .urgent
+                .acquire()
+                .await
+                .expect("The dispatcher is closed.");
+        }
+
+        let remaining = self.global_ratelimit_reset.load(Ordering::Relaxed)
+            - chrono::Utc::now().timestamp_millis();
+
+        if remaining > 0 {
+            sleep(Duration::from_millis(remaining as u64)).await;
+        }
+
+        self.regular
+            .acquire()
+            .await
+            .expect("The dispatcher is closed.")
+    }
+
+    /// Sends to every recipient at once, each waiting for its own send slot in the order given.
+    pub async fn fan_out<N, T, F, Fut>(
+        &self,
+        urgency: Urgency,
+        recipients: impl IntoIterator<Item = N>,
+        key: impl Fn(&N) -> u64,
+        send: F,
+    ) -> Vec<T>
+    where
+        F: Fn(N) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        join_all(recipients.into_iter().map(|recipient| {
+            let key = key(&recipient);
+            let send = &send;
+
+            async move {
+                let _permit = self.acquire(urgency, key).await;
+                send(recipient).await
+            }
+        }))
+        .await
+    }
+}
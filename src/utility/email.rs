@@ -0,0 +1,72 @@
+use crate::structures::config::EmailConfig;
+use anyhow::{Context, Result};
+use lettre::{
+    message::{
+        header::{ContentType, HeaderName, HeaderValue},
+        Mailbox,
+    },
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::sync::OnceLock;
+
+/// The SMTP server emails are sent through, and who they are from.
+static MAILER: OnceLock<(AsyncSmtpTransport<Tokio1Executor>, Mailbox)> = OnceLock::new();
+
+/// Connects to the SMTP server so emails are sent. Nothing is sent if no URL is configured.
+pub fn connect(config: &EmailConfig) -> Result<()> {
+    if config.smtp_url.is_empty() {
+        return Ok(());
+    }
+
+    let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(&config.smtp_url)
+        .context("Error parsing EMAIL_SMTP_URL.")?
+        .build();
+
+    let from = config.from.parse().context("Error parsing EMAIL_FROM.")?;
+    MAILER.get_or_init(|| (transport, from));
+    tracing::info!("Sending emails.");
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    MAILER.get().is_some()
+}
+
+/// Sends a plain text email. Emails a recipient may not have asked for can be unsubscribed from in one click.
+pub async fn send(
+    to: &str,
+    subject: &str,
+    body: String,
+    unsubscribe_url: Option<&str>,
+) -> Result<(), lettre::transport::smtp::Error> {
+    let Some((transport, from)) = MAILER.get() else {
+        return Ok(());
+    };
+
+    let mut builder = Message::builder()
+        .from(from.clone())
+        .to(to
+            .parse()
+            .expect("Email addresses are validated before they are stored."))
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+
+    if let Some(unsubscribe_url) = unsubscribe_url {
+        builder = builder
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                format!("<{unsubscribe_url}>"),
+            ))
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                "List-Unsubscribe=One-Click".to_string(),
+            ));
+    }
+
+    let message = builder
+        .body(body)
+        .expect("A plain text email always builds.");
+
+    transport.send(message).await?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+/// Whether a string is a single emoji Discord will render: either custom emoji markup such as `<:shard:123>` or `<a:candle:456>`, or a Unicode emoji.
+pub fn is_valid_emoji(value: &str) -> bool {
+    if let Some(markup) = value
+        .strip_prefix('<')
+        .and_then(|value| value.strip_suffix('>'))
+    {
+        let markup = markup.strip_prefix('a').unwrap_or(markup);
+
+        let Some((name, id)) = markup
+            .strip_prefix(':')
+            .and_then(|markup| markup.split_once(':'))
+        else {
+            return false;
+        };
+
+        return (2..=32).contains(&name.len())
+            && name
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '_')
+            && !id.is_empty()
+            && id.len() <= 20
+            && id.chars().all(|character| character.is_ascii_digit());
+    }
+
+    // Unicode emoji may combine several code points, but never plain ASCII or whitespace.
+    !value.is_empty()
+        && value.chars().count() <= 10
+        && value
+            .chars()
+            .all(|character| !character.is_ascii() && !character.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_custom_and_unicode_emoji() {
+        for emoji in ["<:shard:123>", "<a:candle:456>", "🕯️", "👨‍👩‍👧"] {
+            assert!(is_valid_emoji(emoji), "{emoji}");
+        }
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for emoji in [
+            "",
+            "shard",
+            ":shard:",
+            "<:s:123>",
+            "<:shard:>",
+            "<:shard:12a>",
+            "<:sh-ard:123>",
+            "🕯️ 🕯️",
+            "🕯️a",
+        ] {
+            assert!(!is_valid_emoji(emoji), "{emoji}");
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::structures::config::Config;
+use sentry::{ClientInitGuard, ClientOptions, Level};
+use std::fmt::Display;
+
+/// Starts reporting errors and panics to Sentry if a DSN is configured.
+///
+/// The guard must be held until exit so queued events are sent.
+pub fn init(config: &Config) -> Option<ClientInitGuard> {
+    let dsn = config.sentry_dsn.as_deref()?;
+
+    Some(sentry::init((
+        dsn,
+        ClientOptions {
+            release: sentry::release_name!(),
+            environment: Some(config.environment.clone().into()),
+            ..Default::default()
+        },
+    )))
+}
+
+/// Reports data from a source that could not be parsed, which usually means its format changed.
+pub fn report_parse_failure(source: &str, error: impl Display) {
+    tracing::warn!(source, "Failed to parse data: {error}");
+
+    sentry::with_scope(
+        |scope| scope.set_tag("data_source", source),
+        || sentry::capture_message(&format!("Failed to parse {source}: {error}"), Level::Error),
+    );
+}
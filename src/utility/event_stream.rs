@@ -0,0 +1,47 @@
+use super::constants::EVENT_STREAM_CAPACITY;
+use crate::structures::notification::NotificationNotify;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use futures::{stream, Stream};
+use std::{convert::Infallible, sync::LazyLock};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Each dispatched notification as JSON, for whoever is listening.
+static EVENTS: LazyLock<broadcast::Sender<String>> =
+    LazyLock::new(|| broadcast::channel(EVENT_STREAM_CAPACITY).0);
+
+/// Broadcasts a notification as it is dispatched. Nothing is serialised if no one is listening.
+pub fn publish(notification_notify: &NotificationNotify) {
+    if EVENTS.receiver_count() == 0 {
+        return;
+    }
+
+    match serde_json::to_string(notification_notify) {
+        Ok(json) => {
+            // Listeners may disconnect since the count was checked, which is fine.
+            let _ = EVENTS.send(json);
+        }
+        Err(error) => {
+            tracing::error!("Failed to serialise a notification for the event stream: {error:?}");
+        }
+    }
+}
+
+/// Streams notifications as they are dispatched, as server-sent events.
+///
+/// Each is a `notification` event. A listener that falls behind is sent a `lagged` event with how many it missed instead.
+pub fn subscribe() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(EVENTS.subscribe(), |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(json) => Event::default().event("notification").data(json),
+            Err(RecvError::Lagged(missed)) => {
+                tracing::warn!(missed, "An event stream listener fell behind.");
+                Event::default().event("lagged").data(missed.to_string())
+            }
+            Err(RecvError::Closed) => return None,
+        };
+
+        Some((Ok(event), receiver))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
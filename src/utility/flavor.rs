@@ -0,0 +1,47 @@
+use crate::structures::config::FlavorText;
+use chrono::{DateTime, Datelike};
+use chrono_tz::America::Los_Angeles;
+use rand::Rng;
+
+// The first variant of each is the original text, used when flavour text is fixed.
+
+pub const DAILY_RESET: [&str; 4] = [
+    "It's a new day. Time to forge candles again!",
+    "A new day has dawned. The candles are waiting!",
+    "The day has reset. Go light up the skies!",
+    "Fresh quests and fresh candles. Happy forging!",
+];
+
+pub const POLLUTED_GEYSER: [&str; 3] = [
+    "The Polluted Geyser is starting to erupt!",
+    "The Polluted Geyser is erupting. Time to cleanse it!",
+    "Wax ahoy! The Polluted Geyser is erupting!",
+];
+
+pub const GRANDMA: [&str; 3] = [
+    "Grandma has begun sharing her light!",
+    "Grandma's dinner is served. Come and collect some light!",
+    "Grandma is sharing her light. Don't keep her waiting!",
+];
+
+pub const TURTLE: [&str; 3] = [
+    "The turtle needs cleansing of darkness now!",
+    "The turtle is surrounded by darkness. Go help it!",
+    "Time to cleanse the darkness around the turtle!",
+];
+
+/// Picks a variant of a message for an occurrence starting at `start_time`.
+pub fn flavor(variants: &[&'static str], flavor_text: FlavorText, start_time: i64) -> &'static str {
+    let seed = match flavor_text {
+        FlavorText::Fixed => return variants[0],
+        FlavorText::Random => return variants[rand::thread_rng().gen_range(0..variants.len())],
+        FlavorText::Occurrence => start_time,
+        FlavorText::Day => DateTime::from_timestamp(start_time, 0)
+            .map(|date| date.with_timezone(&Los_Angeles).num_days_from_ce().into())
+            .unwrap_or_default(),
+    };
+
+    // Scatter consecutive seeds so neighbouring occurrences do not step through the variants in order.
+    let mixed = (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+    variants[(mixed % variants.len() as u64) as usize]
+}
@@ -13,3 +13,31 @@ pub fn last_day_of_month(now: DateTime<Tz>) -> u32 {
 
     (first_day_of_next_month - Duration::days(1)).day()
 }
+
+/// Joins items into a human-readable list, such as "A, B, and C".
+pub fn format_list(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [item] => item.clone(),
+        [first, second] => format!("{first} and {second}"),
+        [rest @ .., last] => format!("{}, and {last}", rest.join(", ")),
+    }
+}
+
+/// Writes a number in base 36, such as "2n9c" for 123456.
+pub fn base36(mut value: u64) -> String {
+    const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = vec![];
+
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
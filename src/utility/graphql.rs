@@ -0,0 +1,341 @@
+use crate::structures::{
+    audit_log::{get_audit_log, AuditLogEntry},
+    config::{Config, FlavorText},
+    delivery_statistics::{get_delivery_statistics, DeliveryStatistics},
+    notification::{NotificationNotify, NotificationType},
+    schedule::run_simulation,
+    subscription::{get_subscriptions, subscribe, unsubscribe, DeliveryMode, Subscription},
+};
+use async_graphql::{
+    Context, EmptySubscription, Error, InputObject, Object, Result, Schema, SimpleObject, ID,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Timelike, Utc};
+use serenity::model::id::{ChannelId, GuildId, RoleId};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+
+/// The schema the web dashboard queries and manages subscriptions through.
+pub type DashboardSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Builds the schema, with the database and configuration for resolvers to use.
+pub fn schema(pool: Pool<Postgres>, config: Arc<Config>) -> DashboardSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(pool)
+        .data(config)
+        .finish()
+}
+
+/// Parses a snowflake, which is sent as a string as it may not fit in a JavaScript number.
+fn snowflake(id: &ID) -> Result<u64> {
+    id.parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .ok_or_else(|| Error::new(format!("{} is not a valid snowflake.", id.as_str())))
+}
+
+/// Logs a database error and hides its details from the caller.
+fn internal_error(error: impl std::fmt::Debug) -> Error {
+    tracing::error!("A GraphQL resolver failed: {error:?}");
+    Error::new("Something went wrong.")
+}
+
+#[derive(SimpleObject)]
+struct Occurrence {
+    r#type: NotificationType,
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    /// The notification text, without any mention or customisation.
+    text: String,
+}
+
+impl From<NotificationNotify> for Occurrence {
+    fn from(notification_notify: NotificationNotify) -> Self {
+        Self {
+            r#type: notification_notify.r#type,
+            start_time: DateTime::from_timestamp(notification_notify.start_time, 0)
+                .unwrap_or_default(),
+            end_time: notification_notify
+                .end_time
+                .and_then(|end_time| DateTime::from_timestamp(end_time, 0)),
+            text: notification_notify.text(FlavorText::Fixed),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "NotificationSubscription")]
+struct SubscriptionObject {
+    r#type: NotificationType,
+    channel_id: ID,
+    role_id: Option<ID>,
+    offset: i16,
+    threaded: bool,
+    silent: bool,
+    template: Option<String>,
+    emoji: Option<String>,
+    minimum_interval: Option<i32>,
+    on_the_hour: bool,
+    delivery_mode: DeliveryMode,
+    countdown: Vec<i16>,
+    rich: bool,
+}
+
+impl From<Subscription> for SubscriptionObject {
+    fn from(subscription: Subscription) -> Self {
+        Self {
+            r#type: subscription.r#type,
+            channel_id: subscription.channel_id.into(),
+            role_id: subscription.role_id.map(ID::from),
+            offset: subscription.offset,
+            threaded: subscription.threaded,
+            silent: subscription.silent,
+            template: subscription.template,
+            emoji: subscription.emoji,
+            minimum_interval: subscription.minimum_interval,
+            on_the_hour: subscription.on_the_hour,
+            delivery_mode: subscription.delivery_mode,
+            countdown: subscription.countdown,
+            rich: subscription.rich,
+        }
+    }
+}
+
+#[derive(InputObject)]
+#[graphql(name = "NotificationSubscriptionInput")]
+struct SubscriptionInput {
+    r#type: NotificationType,
+    channel_id: ID,
+    role_id: Option<ID>,
+    #[graphql(default)]
+    offset: i16,
+    #[graphql(default)]
+    threaded: bool,
+    #[graphql(default)]
+    silent: bool,
+    template: Option<String>,
+    emoji: Option<String>,
+    minimum_interval: Option<i32>,
+    #[graphql(default)]
+    on_the_hour: bool,
+    #[graphql(default)]
+    delivery_mode: DeliveryMode,
+    #[graphql(default)]
+    countdown: Vec<i16>,
+    #[graphql(default)]
+    rich: bool,
+}
+
+impl TryFrom<SubscriptionInput> for Subscription {
+    type Error = Error;
+
+    fn try_from(input: SubscriptionInput) -> Result<Self> {
+        let subscription = Self {
+            r#type: input.r#type,
+            channel_id: ChannelId::new(snowflake(&input.channel_id)?),
+            role_id: input
+                .role_id
+                .as_ref()
+                .map(snowflake)
+                .transpose()?
+                .map(RoleId::new),
+            offset: input.offset,
+            threaded: input.threaded,
+            silent: input.silent,
+            template: input.template,
+            emoji: input.emoji,
+            minimum_interval: input.minimum_interval,
+            on_the_hour: input.on_the_hour,
+            delivery_mode: input.delivery_mode,
+            countdown: input.countdown,
+            rich: input.rich,
+            sendable: true,
+            unsendable_reason: None,
+        };
+
+        Ok(subscription)
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "DeliveryStatistics")]
+struct DeliveryStatisticsObject {
+    date: NaiveDate,
+    r#type: NotificationType,
+    sent: i32,
+    failed: i32,
+    skipped: i32,
+    coalesced: i32,
+}
+
+impl From<DeliveryStatistics> for DeliveryStatisticsObject {
+    fn from(delivery_statistics: DeliveryStatistics) -> Self {
+        Self {
+            date: delivery_statistics.date,
+            r#type: delivery_statistics.r#type,
+            sent: delivery_statistics.sent,
+            failed: delivery_statistics.failed,
+            skipped: delivery_statistics.skipped,
+            coalesced: delivery_statistics.coalesced,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+#[graphql(name = "AuditLogEntry")]
+struct AuditLogEntryObject {
+    channel_id: Option<ID>,
+    user_id: Option<ID>,
+    actor: String,
+    action: String,
+    reason: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<AuditLogEntry> for AuditLogEntryObject {
+    fn from(audit_log_entry: AuditLogEntry) -> Self {
+        Self {
+            channel_id: audit_log_entry.channel_id.map(ID::from),
+            user_id: audit_log_entry.user_id.map(ID::from),
+            actor: audit_log_entry.actor,
+            action: audit_log_entry.action,
+            reason: audit_log_entry.reason,
+            created_at: audit_log_entry.created_at,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The occurrences starting within the next few hours, soonest first.
+    async fn upcoming_occurrences(
+        &self,
+        context: &Context<'_>,
+        #[graphql(default = 24, validator(minimum = 1, maximum = 168))] hours: i64,
+    ) -> Result<Vec<Occurrence>> {
+        let pool = context.data::<Pool<Postgres>>()?;
+        let config = context.data::<Arc<Config>>()?;
+
+        let now = Utc::now()
+            .with_timezone(&chrono_tz::America::Los_Angeles)
+            .with_second(0)
+            .and_then(|now| now.with_nanosecond(0))
+            .ok_or_else(|| Error::new("The current time could not be determined."))?;
+
+        let mut occurrences = vec![];
+
+        run_simulation(
+            pool,
+            config,
+            now,
+            now + TimeDelta::hours(hours),
+            |_, notification_notify| {
+                if notification_notify.time_until_start == 0 {
+                    occurrences.push(Occurrence::from(notification_notify));
+                }
+            },
+        )
+        .await
+        .map_err(internal_error)?;
+
+        Ok(occurrences)
+    }
+
+    /// A guild's subscriptions.
+    async fn subscriptions(
+        &self,
+        context: &Context<'_>,
+        guild_id: ID,
+    ) -> Result<Vec<SubscriptionObject>> {
+        let pool = context.data::<Pool<Postgres>>()?;
+
+        Ok(get_subscriptions(pool, GuildId::new(snowflake(&guild_id)?))
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .map(SubscriptionObject::from)
+            .collect())
+    }
+
+    /// How many notifications of each type were delivered each day, over the last week unless given.
+    async fn delivery_statistics(
+        &self,
+        context: &Context<'_>,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Vec<DeliveryStatisticsObject>> {
+        let pool = context.data::<Pool<Postgres>>()?;
+
+        let today = Utc::now()
+            .with_timezone(&chrono_tz::America::Los_Angeles)
+            .date_naive();
+
+        let to = to.unwrap_or(today);
+        let from = from.unwrap_or(to - TimeDelta::days(6));
+
+        Ok(get_delivery_statistics(pool, from, to)
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .map(DeliveryStatisticsObject::from)
+            .collect())
+    }
+
+    /// The most recent changes made to a guild's notifications, newest first.
+    async fn audit_log(
+        &self,
+        context: &Context<'_>,
+        guild_id: ID,
+    ) -> Result<Vec<AuditLogEntryObject>> {
+        let pool = context.data::<Pool<Postgres>>()?;
+
+        Ok(get_audit_log(pool, GuildId::new(snowflake(&guild_id)?))
+            .await
+            .map_err(internal_error)?
+            .into_iter()
+            .map(AuditLogEntryObject::from)
+            .collect())
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Subscribes a guild to a notification type, replacing any existing subscription to it.
+    async fn subscribe(
+        &self,
+        context: &Context<'_>,
+        guild_id: ID,
+        subscription: SubscriptionInput,
+    ) -> Result<SubscriptionObject> {
+        let pool = context.data::<Pool<Postgres>>()?;
+        let config = context.data::<Arc<Config>>()?;
+        let subscription = Subscription::try_from(subscription)?;
+
+        subscription
+            .validate(config)
+            .map_err(|error| Error::new(error.to_string()))?;
+
+        subscribe(pool, GuildId::new(snowflake(&guild_id)?), &subscription)
+            .await
+            .map_err(internal_error)?;
+
+        Ok(subscription.into())
+    }
+
+    /// Unsubscribes a guild from a notification type. Returns whether it was subscribed.
+    async fn unsubscribe(
+        &self,
+        context: &Context<'_>,
+        guild_id: ID,
+        r#type: NotificationType,
+    ) -> Result<bool> {
+        let pool = context.data::<Pool<Postgres>>()?;
+
+        unsubscribe(pool, GuildId::new(snowflake(&guild_id)?), r#type)
+            .await
+            .map_err(internal_error)
+    }
+}
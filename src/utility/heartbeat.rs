@@ -0,0 +1,21 @@
+use super::{constants::HEARTBEAT_TIMEOUT, http::CLIENT};
+
+/// Pings the dead man's switch, if configured, so operators hear when the scheduler stops ticking.
+///
+/// The ping happens in the background so a slow endpoint cannot delay the next tick.
+pub fn heartbeat(url: Option<&str>) {
+    let Some(url) = url else {
+        return;
+    };
+
+    let request = CLIENT.get(url).timeout(HEARTBEAT_TIMEOUT).send();
+
+    tokio::spawn(async move {
+        if let Err(error) = request
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            tracing::warn!("Failed to send a heartbeat: {error:?}");
+        }
+    });
+}
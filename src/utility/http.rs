@@ -0,0 +1,59 @@
+use super::constants::{HTTP_CONNECT_TIMEOUT, HTTP_KEEPALIVE, HTTP_TIMEOUT};
+use crate::structures::config::DiscordConfig;
+use serenity::http::{Http, HttpBuilder};
+use serenity_reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::sync::LazyLock;
+
+/// Identifies requests to external services, so their operators know whom to contact.
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://thatskyapplication.com)"
+);
+
+/// The client for every request to external services, so they share one connection pool.
+///
+/// Requests that must finish sooner set their own timeout.
+pub static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_TIMEOUT)
+        .pool_idle_timeout(HTTP_KEEPALIVE)
+        .tcp_keepalive(HTTP_KEEPALIVE)
+        .build()
+        .expect("Failed to build the HTTP client.")
+});
+
+/// Builds a client for Discord's API, sending requests through the configured proxy, if any.
+///
+/// Serenity only sends requests to a proxy with its rate limiter disabled, which leaves rate limits to the proxy.
+pub fn discord_client(token: &str, config: &DiscordConfig) -> Http {
+    let Some(proxy_url) = &config.proxy_url else {
+        return Http::new(token);
+    };
+
+    let headers = config
+        .proxy_headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_bytes(name.as_bytes()).expect("Proxy headers are validated."),
+                HeaderValue::from_str(value).expect("Proxy headers are validated."),
+            )
+        })
+        .collect::<HeaderMap>();
+
+    let client = serenity_reqwest::Client::builder()
+        .use_rustls_tls()
+        .default_headers(headers)
+        .build()
+        .expect("Failed to build the Discord HTTP client.");
+
+    HttpBuilder::new(token)
+        .client(client)
+        .proxy(proxy_url)
+        .ratelimiter_disabled(true)
+        .build()
+}
@@ -0,0 +1,49 @@
+use super::http::CLIENT;
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+use tokio::sync::Mutex;
+
+/// Shard maps change daily, so only a handful of images are ever worth keeping.
+const MAXIMUM_CACHED_IMAGES: usize = 8;
+
+static IMAGE_CACHE: LazyLock<Mutex<HashMap<String, Arc<Vec<u8>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Retrieves an image, downloading it only if it is not already cached.
+///
+/// The lock is held while downloading so concurrent sends to many guilds result in a single request.
+pub async fn cached_image(url: &str) -> Option<Arc<Vec<u8>>> {
+    let mut cache = IMAGE_CACHE.lock().await;
+
+    if let Some(image) = cache.get(url) {
+        return Some(image.clone());
+    }
+
+    let image = match CLIENT
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        Ok(response) => match response.bytes().await {
+            Ok(bytes) => Arc::new(bytes.to_vec()),
+            Err(error) => {
+                tracing::warn!("Failed to read the image at {url}: {error:?}");
+                return None;
+            }
+        },
+        Err(error) => {
+            tracing::warn!("Failed to download the image at {url}: {error:?}");
+            return None;
+        }
+    };
+
+    if cache.len() >= MAXIMUM_CACHED_IMAGES {
+        cache.clear();
+    }
+
+    cache.insert(url.to_string(), image.clone());
+    Some(image)
+}
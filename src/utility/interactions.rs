@@ -0,0 +1,726 @@
+use super::{constants::REALMS, http::discord_client, template::TEMPLATE_MAXIMUM_LENGTH};
+use crate::structures::{
+    config::Config,
+    guild_pause::{get_pause, pause_guild, resume_guild},
+    notification::NotificationType,
+    realm_role::{remove_realm_role, set_realm_role, RealmRole},
+    reminder::{add_reminder, parse_reminder_custom_id, Reminder},
+    subscription::{
+        describe_countdown, get_subscriptions, subscribe, unsubscribe, DeliveryMode, Subscription,
+    },
+    user_subscription::{subscribe_user, unsubscribe_user, UserSubscription},
+};
+use anyhow::{anyhow, bail, Result};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serenity::all::{
+    ApplicationId, Command, CommandInteraction, CommandOptionType, ComponentInteraction,
+    CreateCommand, CreateCommandOption, CreateInteractionResponse,
+    CreateInteractionResponseMessage, Interaction, Permissions, ResolvedOption, ResolvedValue,
+    RoleId,
+};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+struct InteractionsState {
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+    public_key: VerifyingKey,
+}
+
+/// Parses the application's public key from its hexadecimal form in the developer portal.
+pub fn parse_public_key(public_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(public_key)?
+        .try_into()
+        .map_err(|_| anyhow!("The public key must be 32 bytes."))?;
+
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+/// Registers the slash commands and serves the interactions endpoint until the listener fails.
+pub async fn serve(config: Arc<Config>, pool: Pool<Postgres>) -> Result<()> {
+    let Some(address) = config.interactions.address.clone() else {
+        return Ok(());
+    };
+
+    if let Some(application_id) = config.interactions.application_id {
+        let client = discord_client(&config.discord_token, &config.discord);
+        client.set_application_id(ApplicationId::new(application_id));
+        Command::set_global_commands(&client, vec![notifications_command(), notify_me_command()])
+            .await?;
+        tracing::info!("Registered slash commands.");
+    }
+
+    let state = Arc::new(InteractionsState {
+        pool,
+        config: config.clone(),
+        public_key: parse_public_key(&config.interactions.public_key)?,
+    });
+
+    let router = Router::new()
+        .route("/interactions", post(interactions))
+        .with_state(state);
+
+    let listener = TcpListener::bind(&address).await?;
+    tracing::info!("Interactions endpoint listening on {address}.");
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+fn type_option(r#types: impl Iterator<Item = NotificationType>) -> CreateCommandOption {
+    r#types.fold(
+        CreateCommandOption::new(CommandOptionType::Integer, "type", "The notification type.")
+            .required(true),
+        |option, r#type| option.add_int_choice(r#type.name(), r#type as i32),
+    )
+}
+
+fn offset_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::Integer,
+        "offset",
+        "How many minutes in advance to notify. Spirits and special visits also allow 60, 720, or 1440.",
+    )
+    .min_int_value(0)
+    .max_int_value(1440)
+}
+
+fn notifications_command() -> CreateCommand {
+    let r#type = type_option(
+        NotificationType::ALL
+            .into_iter()
+            .filter(|r#type| *r#type != NotificationType::Dragon),
+    );
+
+    CreateCommand::new("notifications")
+        .description("Manage this server's notifications.")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "subscribe",
+                "Send a notification type to a channel.",
+            )
+            .add_sub_option(r#type.clone())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Channel,
+                    "channel",
+                    "The channel to send to.",
+                )
+                .required(true),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Role,
+                "role",
+                "The role to mention. Leave empty for no mention.",
+            ))
+            .add_sub_option(offset_option())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "delivery",
+                    "When to send: in advance by the offset, at the start, or both.",
+                )
+                .add_string_choice("In advance", "lead")
+                .add_string_choice("At the start", "start")
+                .add_string_choice("Both", "both"),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "countdown",
+                "Minutes in advance to count down through instead, such as 1440, 60, 15, 0.",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "thread",
+                "Give each shard eruption or travelling spirit its own thread for follow-ups.",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "silent",
+                "Send without a push notification, like @silent.",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "template",
+                    "Custom text. Placeholders: {start} {end} {spirit} {realm} {map} {entities} {reward} {digest}.",
+                )
+                .max_length(TEMPLATE_MAXIMUM_LENGTH as u16),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "emoji",
+                "An emoji to start the message with.",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "interval",
+                    "Send at most once every this many hours.",
+                )
+                .min_int_value(1)
+                .max_int_value(24),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "on_the_hour",
+                "Only send for occurrences starting on the hour.",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "rich",
+                "Include images and details, such as a travelling spirit's items or the shard map.",
+            )),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unsubscribe",
+                "Stop sending a notification type.",
+            )
+            .add_sub_option(r#type),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "List this server's notifications.",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "pause",
+                "Stop sending notifications for a while.",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "hours",
+                    "How many hours to pause for.",
+                )
+                .required(true)
+                .min_int_value(1)
+                .max_int_value(8760),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "resume",
+            "Resume sending notifications.",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "realm-role",
+                "Mention a role for shard eruptions in a realm.",
+            )
+            .add_sub_option(REALMS.into_iter().fold(
+                CreateCommandOption::new(CommandOptionType::String, "realm", "The realm.")
+                    .required(true),
+                |option, realm| option.add_string_choice(realm, realm),
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Role,
+                "role",
+                "The role to mention. Leave empty to stop mentioning one.",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "replace",
+                "Mention it instead of the notification's role, rather than as well.",
+            )),
+        )
+}
+
+fn notify_me_command() -> CreateCommand {
+    let r#type = type_option(
+        NotificationType::ALL
+            .into_iter()
+            .filter(NotificationType::is_direct_messageable),
+    );
+
+    CreateCommand::new("notify-me")
+        .description("Manage notifications sent to your direct messages.")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "subscribe",
+                "Receive a notification type in your direct messages.",
+            )
+            .add_sub_option(r#type.clone())
+            .add_sub_option(offset_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "unsubscribe",
+                "Stop receiving a notification type in your direct messages.",
+            )
+            .add_sub_option(r#type),
+        )
+}
+
+async fn interactions(
+    State(state): State<Arc<InteractionsState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !verify(&state.public_key, &headers, &body) {
+        return (StatusCode::UNAUTHORIZED, "Invalid request signature.").into_response();
+    }
+
+    let interaction: Interaction = match serde_json::from_slice(&body) {
+        Ok(interaction) => interaction,
+        Err(error) => {
+            tracing::warn!("Received an invalid interaction: {error:?}");
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+
+    let response = match interaction {
+        Interaction::Ping(_) => CreateInteractionResponse::Pong,
+        Interaction::Command(command) => {
+            let result = match command.data.name.as_str() {
+                "notifications" => handle_notifications(&state.pool, &state.config, &command).await,
+                "notify-me" => handle_notify_me(&state.pool, &state.config, &command).await,
+                name => Err(anyhow!("Unknown command {name}.")),
+            };
+
+            let content = match result {
+                Ok(content) => content,
+                Err(error) => {
+                    tracing::error!(
+                        command = command.data.name,
+                        "Error handling a command: {error:?}"
+                    );
+                    "Something went wrong. Please try again later.".to_string()
+                }
+            };
+
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            )
+        }
+        Interaction::Component(component) => {
+            let content = match handle_component(&state.pool, &component).await {
+                Ok(content) => content,
+                Err(error) => {
+                    tracing::error!(
+                        custom_id = component.data.custom_id,
+                        "Error handling a component: {error:?}"
+                    );
+
+                    "Something went wrong. Please try again later.".to_string()
+                }
+            };
+
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            )
+        }
+        _ => return StatusCode::BAD_REQUEST.into_response(),
+    };
+
+    Json(response).into_response()
+}
+
+/// Checks the request was signed by Discord, as it requires of every interactions endpoint.
+fn verify(public_key: &VerifyingKey, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    let (Some(signature), Some(timestamp)) = (
+        header("X-Signature-Ed25519"),
+        header("X-Signature-Timestamp"),
+    ) else {
+        return false;
+    };
+
+    let Some(signature) = hex::decode(signature)
+        .ok()
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+    else {
+        return false;
+    };
+
+    public_key
+        .verify(&[timestamp.as_bytes(), body].concat(), &signature)
+        .is_ok()
+}
+
+async fn handle_notifications(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    command: &CommandInteraction,
+) -> Result<String> {
+    let Some(guild_id) = command.guild_id else {
+        bail!("The notifications command was used outside a server.");
+    };
+
+    let options = command.data.options();
+
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+    }) = options.first()
+    else {
+        bail!("The notifications command was used without a subcommand.");
+    };
+
+    match *name {
+        "subscribe" => {
+            let mut r#type = None;
+            let mut channel_id = None;
+            let mut role_id = None;
+            let mut offset = 0;
+            let mut threaded = false;
+            let mut silent = false;
+            let mut template = None;
+            let mut emoji = None;
+            let mut minimum_interval = None;
+            let mut on_the_hour = false;
+            let mut delivery_mode = DeliveryMode::Lead;
+            let mut countdown = vec![];
+            let mut rich = false;
+
+            for option in options {
+                match (option.name, &option.value) {
+                    ("type", ResolvedValue::Integer(value)) => {
+                        r#type = Some(NotificationType::try_from(*value as i16)?)
+                    }
+                    ("channel", ResolvedValue::Channel(channel)) => channel_id = Some(channel.id),
+                    ("role", ResolvedValue::Role(role)) => role_id = Some(role.id),
+                    ("offset", ResolvedValue::Integer(value)) => offset = *value as i16,
+                    ("thread", ResolvedValue::Boolean(value)) => threaded = *value,
+                    ("silent", ResolvedValue::Boolean(value)) => silent = *value,
+                    ("template", ResolvedValue::String(value)) => {
+                        template = Some(value.to_string())
+                    }
+                    ("emoji", ResolvedValue::String(value)) => {
+                        emoji = Some(value.trim().to_string())
+                    }
+                    ("interval", ResolvedValue::Integer(value)) => {
+                        minimum_interval = Some(*value as i32 * 60)
+                    }
+                    ("on_the_hour", ResolvedValue::Boolean(value)) => on_the_hour = *value,
+                    ("rich", ResolvedValue::Boolean(value)) => rich = *value,
+                    ("delivery", ResolvedValue::String(value)) => {
+                        delivery_mode = match *value {
+                            "start" => DeliveryMode::Start,
+                            "both" => DeliveryMode::Both,
+                            _ => DeliveryMode::Lead,
+                        }
+                    }
+                    ("countdown", ResolvedValue::String(value)) => {
+                        match value
+                            .split(',')
+                            .map(|offset| offset.trim().parse::<i16>())
+                            .collect::<Result<Vec<_>, _>>()
+                        {
+                            Ok(offsets) => countdown = offsets,
+                            Err(_) => {
+                                return Ok("A countdown is minutes separated by commas, such as 1440, 60, 15, 0.".to_string());
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let (Some(r#type), Some(channel_id)) = (r#type, channel_id) else {
+                bail!("The subscribe subcommand was missing options.");
+            };
+
+            let subscription = Subscription {
+                r#type,
+                channel_id,
+                role_id,
+                offset,
+                threaded,
+                silent,
+                template,
+                emoji,
+                minimum_interval,
+                on_the_hour,
+                delivery_mode,
+                countdown,
+                rich,
+                sendable: true,
+                unsendable_reason: None,
+            };
+
+            if let Err(error) = subscription.validate(config) {
+                return Ok(error.to_string());
+            }
+
+            subscribe(pool, guild_id, &subscription).await?;
+
+            Ok(format!(
+                "{} notifications will be sent to <#{channel_id}>, {}.",
+                r#type.name(),
+                describe_mention(role_id)
+            ))
+        }
+        "unsubscribe" => {
+            let Some(ResolvedValue::Integer(value)) = options.first().map(|option| &option.value)
+            else {
+                bail!("The unsubscribe subcommand was missing its type.");
+            };
+
+            let r#type = NotificationType::try_from(*value as i16)?;
+
+            Ok(if unsubscribe(pool, guild_id, r#type).await? {
+                format!("{} notifications will no longer be sent.", r#type.name())
+            } else {
+                format!(
+                    "This server is not subscribed to {} notifications.",
+                    r#type.name()
+                )
+            })
+        }
+        "list" => {
+            let subscriptions = get_subscriptions(pool, guild_id).await?;
+
+            if subscriptions.is_empty() {
+                return Ok("This server has no notifications.".to_string());
+            }
+
+            let paused = get_pause(pool, guild_id).await?.map(|paused_until| {
+                format!(
+                    "Notifications are paused until <t:{}:F>.\n",
+                    paused_until.timestamp()
+                )
+            });
+
+            Ok(paused.unwrap_or_default()
+                + &subscriptions
+                    .iter()
+                    .map(|subscription| {
+                        format!(
+                            "- {}: <#{}>, {}, {}{}{}{}{}{}",
+                            subscription.r#type.name(),
+                            subscription.channel_id,
+                            describe_mention(subscription.role_id),
+                            match subscription.delivery_mode {
+                                _ if !subscription.countdown.is_empty() => {
+                                    describe_countdown(&subscription.countdown)
+                                }
+                                DeliveryMode::Lead => {
+                                    format!("{} minutes in advance", subscription.offset)
+                                }
+                                DeliveryMode::Start => "at the start".to_string(),
+                                DeliveryMode::Both => format!(
+                                    "{} minutes in advance and at the start",
+                                    subscription.offset
+                                ),
+                            },
+                            if subscription.threaded {
+                                ", in threads"
+                            } else {
+                                ""
+                            },
+                            if subscription.silent {
+                                ", silently"
+                            } else {
+                                ""
+                            },
+                            match subscription.minimum_interval {
+                                Some(minimum_interval) => {
+                                    format!(", at most every {} hours", minimum_interval / 60)
+                                }
+                                None => String::new(),
+                            },
+                            if subscription.on_the_hour {
+                                ", on the hour only"
+                            } else {
+                                ""
+                            },
+                            if subscription.rich {
+                                ", with images and details"
+                            } else {
+                                ""
+                            }
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+        }
+        "pause" => {
+            let Some(ResolvedValue::Integer(hours)) = options.first().map(|option| &option.value)
+            else {
+                bail!("The pause subcommand was missing its hours.");
+            };
+
+            let paused_until = Utc::now() + TimeDelta::hours(*hours);
+            pause_guild(pool, guild_id, paused_until).await?;
+
+            Ok(format!(
+                "Notifications are paused until <t:{}:F>.",
+                paused_until.timestamp()
+            ))
+        }
+        "resume" => Ok(if resume_guild(pool, guild_id).await? {
+            "Notifications will be sent again.".to_string()
+        } else {
+            "Notifications are not paused.".to_string()
+        }),
+        "realm-role" => {
+            let mut realm = None;
+            let mut role_id = None;
+            let mut replace = false;
+
+            for option in options {
+                match (option.name, &option.value) {
+                    ("realm", ResolvedValue::String(value)) => realm = Some(*value),
+                    ("role", ResolvedValue::Role(role)) => role_id = Some(role.id),
+                    ("replace", ResolvedValue::Boolean(value)) => replace = *value,
+                    _ => {}
+                }
+            }
+
+            let Some(realm) = realm.filter(|realm| REALMS.contains(realm)) else {
+                bail!("The realm-role subcommand was missing its realm.");
+            };
+
+            let Some(role_id) = role_id else {
+                return Ok(if remove_realm_role(pool, guild_id, realm).await? {
+                    format!(
+                        "Shard eruptions in {realm} will no longer mention a role of their own."
+                    )
+                } else {
+                    format!("Shard eruptions in {realm} do not mention a role of their own.")
+                });
+            };
+
+            set_realm_role(pool, guild_id, realm, &RealmRole { role_id, replace }).await?;
+
+            Ok(format!(
+                "Shard eruptions in {realm} will mention <@&{role_id}>{}.",
+                if replace {
+                    " instead of the notification's role"
+                } else {
+                    " as well as the notification's role"
+                }
+            ))
+        }
+        name => bail!("Unknown notifications subcommand {name}."),
+    }
+}
+
+fn describe_mention(role_id: Option<RoleId>) -> String {
+    match role_id {
+        Some(role_id) => format!("mentioning <@&{role_id}>"),
+        None => "without a mention".to_string(),
+    }
+}
+
+async fn handle_notify_me(
+    pool: &Pool<Postgres>,
+    config: &Config,
+    command: &CommandInteraction,
+) -> Result<String> {
+    let user_id = command.user.id;
+    let options = command.data.options();
+
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(options),
+        ..
+    }) = options.first()
+    else {
+        bail!("The notify-me command was used without a subcommand.");
+    };
+
+    let mut r#type = None;
+    let mut offset = 0;
+
+    for option in options {
+        match (option.name, &option.value) {
+            ("type", ResolvedValue::Integer(value)) => {
+                r#type = Some(NotificationType::try_from(*value as i16)?)
+            }
+            ("offset", ResolvedValue::Integer(value)) => offset = *value as i16,
+            _ => {}
+        }
+    }
+
+    let Some(r#type) = r#type.filter(NotificationType::is_direct_messageable) else {
+        bail!("The notify-me command was missing a valid type.");
+    };
+
+    match *name {
+        "subscribe" => {
+            let user_subscription = UserSubscription {
+                user_id,
+                r#type,
+                offset,
+            };
+
+            if let Err(error) = user_subscription.validate(config) {
+                return Ok(error.to_string());
+            }
+
+            subscribe_user(pool, &user_subscription).await?;
+
+            Ok(format!(
+                "{} notifications will be sent to your direct messages. Make sure you allow direct messages from this app.",
+                r#type.name()
+            ))
+        }
+        "unsubscribe" => Ok(if unsubscribe_user(pool, user_id, r#type).await? {
+            format!(
+                "{} notifications will no longer be sent to your direct messages.",
+                r#type.name()
+            )
+        } else {
+            format!("You are not subscribed to {} notifications.", r#type.name())
+        }),
+        name => bail!("Unknown notify-me subcommand {name}."),
+    }
+}
+
+async fn handle_component(
+    pool: &Pool<Postgres>,
+    component: &ComponentInteraction,
+) -> Result<String> {
+    let Some((r#type, start_time)) = parse_reminder_custom_id(&component.data.custom_id) else {
+        bail!("Unknown component {}.", component.data.custom_id);
+    };
+
+    let Some(start) = DateTime::from_timestamp(start_time, 0).filter(|start| *start > Utc::now())
+    else {
+        return Ok("This has already started!".to_string());
+    };
+
+    let added = add_reminder(
+        pool,
+        &Reminder {
+            user_id: component.user.id,
+            r#type,
+            start,
+            message_url: component.message.link(),
+        },
+    )
+    .await?;
+
+    Ok(if added {
+        format!("You will be reminded in your direct messages <t:{start_time}:R>.")
+    } else {
+        "You already have a reminder for this.".to_string()
+    })
+}
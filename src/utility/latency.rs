@@ -0,0 +1,91 @@
+use super::{
+    alert::alert,
+    constants::{LATENCY_CHECK_INTERVAL, LATENCY_MAXIMUM_SAMPLES},
+};
+use crate::structures::config::Config;
+use serde::Serialize;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::time::sleep;
+
+/// How late each send since the last check was, in milliseconds.
+static SAMPLES: LazyLock<Mutex<Vec<i64>>> = LazyLock::new(|| Mutex::new(Vec::new()));
+
+#[derive(Serialize)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub p50: i64,
+    pub p95: i64,
+    pub p99: i64,
+    pub maximum: i64,
+}
+
+/// Records how many milliseconds after it was due a notification reached Discord.
+pub fn record(latency: i64) {
+    let mut samples = SAMPLES.lock().unwrap();
+
+    // A wedged checker should not grow this without bound.
+    if samples.len() < LATENCY_MAXIMUM_SAMPLES {
+        samples.push(latency);
+    }
+}
+
+/// Summarises the sends since the last check.
+pub fn snapshot() -> Option<LatencySummary> {
+    summarise(SAMPLES.lock().unwrap().clone())
+}
+
+fn summarise(mut samples: Vec<i64>) -> Option<LatencySummary> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    samples.sort_unstable();
+
+    let percentile =
+        |percentile: usize| samples[(samples.len() * percentile).div_ceil(100).max(1) - 1];
+
+    Some(LatencySummary {
+        count: samples.len(),
+        p50: percentile(50),
+        p95: percentile(95),
+        p99: percentile(99),
+        maximum: samples[samples.len() - 1],
+    })
+}
+
+/// Periodically summarises how late sends were, alerting when the 95th percentile exceeds the objective.
+pub async fn check_latency(config: Arc<Config>) {
+    let objective = i64::from(config.latency_objective) * 1000;
+
+    loop {
+        sleep(LATENCY_CHECK_INTERVAL).await;
+
+        let samples = std::mem::take(&mut *SAMPLES.lock().unwrap());
+
+        let Some(summary) = summarise(samples) else {
+            continue;
+        };
+
+        tracing::info!(
+            count = summary.count,
+            p50 = summary.p50,
+            p95 = summary.p95,
+            p99 = summary.p99,
+            maximum = summary.maximum,
+            "Delivery latency"
+        );
+
+        if objective > 0 && summary.p95 > objective {
+            alert(
+                config.alert_webhook_url.as_deref(),
+                &format!(
+                    "95% of {} notifications were delivered within {:.1} seconds of being due, exceeding the {} second objective.",
+                    summary.count,
+                    summary.p95 as f64 / 1000.0,
+                    config.latency_objective
+                ),
+            )
+            .await;
+        }
+    }
+}
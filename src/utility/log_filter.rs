@@ -0,0 +1,39 @@
+use anyhow::{anyhow, Result};
+use std::sync::OnceLock;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// The filter applied when RUST_LOG is unset.
+const DEFAULT_LOG_FILTER: &str = "info";
+
+static HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Creates the filter layer from RUST_LOG, keeping a handle so it can be changed at runtime.
+pub fn layer() -> reload::Layer<EnvFilter, Registry> {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+
+    let (layer, handle) = reload::Layer::new(filter);
+    let _ = HANDLE.set(handle);
+    layer
+}
+
+/// The filter in use, in RUST_LOG syntax.
+pub fn current() -> Result<String> {
+    handle()?
+        .with_current(|filter| filter.to_string())
+        .map_err(|error| anyhow!(error))
+}
+
+/// Replaces the filter, such as "info,caelus_notifications=debug" to debug a delivery issue.
+pub fn set(directives: &str) -> Result<()> {
+    let filter = EnvFilter::try_new(directives)?;
+    handle()?.reload(filter)?;
+    tracing::info!("Log filter changed to {directives}.");
+    Ok(())
+}
+
+fn handle() -> Result<&'static reload::Handle<EnvFilter, Registry>> {
+    HANDLE
+        .get()
+        .ok_or_else(|| anyhow!("The log filter has not been initialised."))
+}
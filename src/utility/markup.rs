@@ -0,0 +1,114 @@
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::America::Los_Angeles;
+
+/// A piece of notification text as written for Discord.
+pub enum Segment<'a> {
+    Text(&'a str),
+    /// A Markdown link, such as `[Daylight Prairie (Cave)](https://...)`.
+    Link {
+        label: &'a str,
+        url: &'a str,
+    },
+    /// A Discord timestamp, such as `<t:1700000000:R>`, and its style.
+    Timestamp {
+        timestamp: i64,
+        style: char,
+    },
+}
+
+/// Splits text into the links and timestamps other platforms cannot show as written, and the text between them.
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut text_start = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+
+        let parsed = parse_link(rest)
+            .map(|(label, url, after)| (Segment::Link { label, url }, after))
+            .or_else(|| {
+                parse_timestamp(rest).map(|(timestamp, style, after)| {
+                    (Segment::Timestamp { timestamp, style }, after)
+                })
+            });
+
+        match parsed {
+            Some((segment, after)) => {
+                if text_start < index {
+                    segments.push(Segment::Text(&text[text_start..index]));
+                }
+
+                segments.push(segment);
+                index = text.len() - after.len();
+                text_start = index;
+            }
+            None => index += rest.chars().next().unwrap().len_utf8(),
+        }
+    }
+
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+
+    segments
+}
+
+/// Writes a Discord timestamp as text. Relative timestamps are written relative to `now`, and others in Sky time.
+pub fn format_timestamp(timestamp: i64, style: char, now: DateTime<Utc>) -> String {
+    let Some(time) = Utc.timestamp_opt(timestamp, 0).single() else {
+        return timestamp.to_string();
+    };
+
+    match style {
+        'R' => {
+            let minutes = (time - now).num_minutes();
+
+            let duration = match minutes.abs() {
+                0 => return "now".to_string(),
+                1 => "a minute".to_string(),
+                2..=59 => format!("{} minutes", minutes.abs()),
+                60..=119 => "an hour".to_string(),
+                _ => format!("{} hours", (minutes.abs() + 30) / 60),
+            };
+
+            if minutes > 0 {
+                format!("in {duration}")
+            } else {
+                format!("{duration} ago")
+            }
+        }
+        't' | 'T' => format!(
+            "{} Sky time",
+            time.with_timezone(&Los_Angeles).format("%H:%M")
+        ),
+        _ => format!(
+            "{} Sky time",
+            time.with_timezone(&Los_Angeles).format("%-d %B %H:%M")
+        ),
+    }
+}
+
+fn parse_link(text: &str) -> Option<(&str, &str, &str)> {
+    let rest = text.strip_prefix('[')?;
+    let (label, rest) = rest.split_once("](")?;
+    let (url, rest) = rest.split_once(')')?;
+
+    if label.contains(['[', ']']) || url.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((label, url, rest))
+}
+
+fn parse_timestamp(text: &str) -> Option<(i64, char, &str)> {
+    let rest = text.strip_prefix("<t:")?;
+    let (inner, rest) = rest.split_once('>')?;
+
+    let (timestamp, style) = match inner.split_once(':') {
+        Some((timestamp, style)) => (timestamp, style.parse().ok()?),
+        None => (inner, 'f'),
+    };
+
+    Some((timestamp.parse().ok()?, style, rest))
+}
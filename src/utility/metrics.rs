@@ -0,0 +1,28 @@
+use std::{
+    collections::BTreeMap,
+    sync::{LazyLock, Mutex},
+};
+
+static COUNTERS: LazyLock<Mutex<BTreeMap<String, u64>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+/// Adds to a named counter.
+pub fn increment_by(name: &str, value: u64) {
+    let mut counters = COUNTERS.lock().unwrap();
+    *counters.entry(name.to_string()).or_default() += value;
+}
+
+/// Adds one to a named counter.
+pub fn increment(name: &str) {
+    increment_by(name, 1);
+}
+
+/// Returns the current value of a counter.
+pub fn get(name: &str) -> u64 {
+    COUNTERS
+        .lock()
+        .unwrap()
+        .get(name)
+        .copied()
+        .unwrap_or_default()
+}
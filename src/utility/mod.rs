@@ -1,3 +1,38 @@
+pub mod admin;
+pub mod alert;
+pub mod atom;
+pub mod broker;
+pub mod channel_cache;
+pub mod channel_circuit;
+pub mod cli;
 pub mod constants;
+pub mod credentials;
+pub mod dashboard;
+pub mod database;
+pub mod discord_clients;
+pub mod dispatcher;
+pub mod email;
+pub mod emoji;
+pub mod error_reporting;
+pub mod event_stream;
+pub mod flavor;
 pub mod functions;
+pub mod graphql;
+pub mod heartbeat;
+pub mod http;
+pub mod image_cache;
+pub mod interactions;
+pub mod latency;
+pub mod log_filter;
+pub mod markup;
+pub mod metrics;
+pub mod permissions;
+pub mod public_api;
+pub mod queue;
+pub mod shard_eruption;
+pub mod slack;
+pub mod social;
+pub mod spirits;
+pub mod stream;
+pub mod template;
 pub mod wind_paths;
@@ -0,0 +1,132 @@
+use super::channel_cache::remember;
+use anyhow::Result;
+use serenity::{
+    all::{Channel, Member, PartialGuild, Permissions},
+    http::{Http, HttpError},
+    model::id::{ChannelId, GuildId, RoleId},
+};
+
+/// Why notifications cannot be sent to a channel Discord says is not there.
+pub const MISSING_CHANNEL: &str = "The channel does not exist or cannot be seen.";
+
+/// The permissions needed to send a notification.
+const REQUIRED_PERMISSIONS: Permissions =
+    Permissions::VIEW_CHANNEL.union(Permissions::SEND_MESSAGES);
+
+/// The permissions needed to send a notification to a thread.
+const REQUIRED_THREAD_PERMISSIONS: Permissions =
+    Permissions::VIEW_CHANNEL.union(Permissions::SEND_MESSAGES_IN_THREADS);
+
+/// A guild as the bot sees it, for checking several of its channels at once.
+pub struct GuildView {
+    guild: PartialGuild,
+    member: Member,
+}
+
+impl GuildView {
+    /// Fetches the guild and the bot's member in it. Returns `None` if the bot is not in the guild.
+    pub async fn fetch(client: &Http, guild_id: GuildId) -> Result<Option<Self>> {
+        let guild = match client.get_guild(guild_id).await {
+            Ok(guild) => guild,
+            Err(error) if is_missing(&error) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let user = client.get_current_user().await?;
+        let member = client.get_member(guild_id, user.id).await?;
+        Ok(Some(Self { guild, member }))
+    }
+
+    /// Describes why notifications cannot be sent to a channel, if they cannot.
+    pub async fn channel_problem(
+        &self,
+        client: &Http,
+        channel_id: ChannelId,
+        role_id: Option<RoleId>,
+    ) -> Result<Option<String>> {
+        let channel = match client.get_channel(channel_id).await {
+            Ok(Channel::Guild(channel)) => channel,
+            Ok(_) => return Ok(Some("The channel is not in a server.".to_string())),
+            Err(error) if is_missing(&error) => return Ok(Some(MISSING_CHANNEL.to_string())),
+            Err(error) => return Err(error.into()),
+        };
+
+        if channel.guild_id != self.guild.id {
+            return Ok(Some("The channel is in another server.".to_string()));
+        }
+
+        let metadata = remember(&channel);
+
+        if let Some(problem) = metadata.problem() {
+            return Ok(Some(problem.to_string()));
+        }
+
+        // Threads have no overwrites of their own, so take their permissions from the channel they were started in.
+        let (permissions, required) = match metadata.parent_id.filter(|_| metadata.is_thread()) {
+            Some(parent_id) => {
+                let parent = match client.get_channel(parent_id).await {
+                    Ok(Channel::Guild(parent)) => parent,
+                    Ok(_) => return Ok(Some("The thread is not in a server.".to_string())),
+                    Err(error) if is_missing(&error) => {
+                        return Ok(Some(
+                            "The thread's channel does not exist or cannot be seen.".to_string(),
+                        ))
+                    }
+                    Err(error) => return Err(error.into()),
+                };
+
+                (
+                    self.guild.user_permissions_in(&parent, &self.member),
+                    REQUIRED_THREAD_PERMISSIONS,
+                )
+            }
+            None => (
+                self.guild.user_permissions_in(&channel, &self.member),
+                REQUIRED_PERMISSIONS,
+            ),
+        };
+
+        let missing = required - permissions;
+
+        if !missing.is_empty() {
+            return Ok(Some(format!(
+                "Missing permissions: {}.",
+                missing.get_permission_names().join(", ")
+            )));
+        }
+
+        let Some(role_id) = role_id else {
+            return Ok(None);
+        };
+
+        // Roles that are not mentionable only ping when the bot may mention everyone.
+        Ok(match self.guild.roles.get(&role_id) {
+            None => Some("The role no longer exists.".to_string()),
+            Some(role) if !role.mentionable && !permissions.mention_everyone() => {
+                Some(format!("The role {} cannot be mentioned.", role.name))
+            }
+            Some(_) => None,
+        })
+    }
+}
+
+/// Describes why notifications cannot be sent to a channel, if they cannot.
+pub async fn channel_problem(
+    client: &Http,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<Option<String>> {
+    match GuildView::fetch(client, guild_id).await? {
+        Some(guild_view) => guild_view.channel_problem(client, channel_id, None).await,
+        None => Ok(Some("The bot is not in the server.".to_string())),
+    }
+}
+
+/// Whether Discord refused a request because the resource is gone or out of the bot's reach.
+pub fn is_missing(error: &serenity::Error) -> bool {
+    matches!(
+        error,
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response))
+            if matches!(response.status_code.as_u16(), 403 | 404)
+    )
+}
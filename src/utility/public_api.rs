@@ -0,0 +1,476 @@
+use super::{
+    atom::atom_feed,
+    constants::{PUBLIC_API_CACHE_TTL, PUBLIC_API_PRUNE_INTERVAL, PUBLIC_API_RATE_LIMIT_WINDOW},
+    email,
+};
+use crate::structures::{
+    config::Config,
+    dispatched_notification::get_dispatched_notifications,
+    email_subscription::{
+        confirm_email, register_email, send_confirmation, unsubscribe_email,
+        update_email_preferences, EmailPreferences,
+    },
+    notification::NotificationType,
+    shard_eruption::get_known_shard_eruption,
+};
+use anyhow::Result;
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RETRY_AFTER},
+        HeaderMap, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::{NaiveDate, Utc};
+use chrono_tz::America::Los_Angeles;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Pool, Postgres};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::{DefaultHasher, Hash, Hasher},
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, time::sleep};
+
+const ATOM: &str = "application/atom+xml";
+const JSON: &str = "application/json";
+
+#[derive(Clone)]
+struct PublicApiState {
+    pool: Pool<Postgres>,
+    config: Arc<Config>,
+    /// Rendered responses, by path.
+    responses: Arc<Mutex<HashMap<String, Arc<CachedResponse>>>>,
+    /// The requests made in the current window, by address.
+    requests: Arc<Mutex<HashMap<IpAddr, RequestWindow>>>,
+}
+
+struct CachedResponse {
+    expires_at: Instant,
+    etag: String,
+    body: String,
+}
+
+struct RequestWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+#[derive(Deserialize)]
+struct EmailRegistration {
+    email: String,
+    #[serde(flatten)]
+    preferences: EmailPreferences,
+}
+
+/// Serves the public API until the listener fails.
+///
+/// It needs no authentication, so responses are cached in memory and each address is limited to a number of requests a minute.
+pub async fn serve(config: Arc<Config>, pool: Pool<Postgres>) -> Result<()> {
+    let Some(address) = config.public_api.address.clone() else {
+        return Ok(());
+    };
+
+    let state = PublicApiState {
+        pool,
+        config,
+        responses: Arc::new(Mutex::new(HashMap::new())),
+        requests: Arc::new(Mutex::new(HashMap::new())),
+    };
+
+    tokio::spawn(prune(state.clone()));
+
+    let mut router = Router::new()
+        .route("/shard-eruptions/today", get(todays_shard_eruption))
+        .route("/shard-eruptions/now", get(shard_eruption_status))
+        .route("/shard-eruptions/:date", get(shard_eruption))
+        .route("/feeds/notifications.atom", get(combined_feed))
+        .route("/feeds/:feed", get(feed));
+
+    if email::is_enabled() {
+        router = router
+            .route("/email-subscriptions", post(register))
+            .route("/email-subscriptions/:token", put(update_preferences))
+            .route("/email-subscriptions/:token/confirm", get(confirm))
+            .route(
+                "/email-subscriptions/:token/unsubscribe",
+                get(unsubscribe_form).post(unsubscribe),
+            );
+    }
+
+    let router = router
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .with_state(state);
+
+    let listener = TcpListener::bind(&address).await?;
+    tracing::info!("Public API listening on {address}.");
+
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Periodically forgets expired responses and the windows of addresses that have stopped making requests.
+async fn prune(state: PublicApiState) {
+    loop {
+        sleep(PUBLIC_API_PRUNE_INTERVAL).await;
+        let now = Instant::now();
+
+        state
+            .responses
+            .lock()
+            .unwrap()
+            .retain(|_, cached| cached.expires_at > now);
+
+        state
+            .requests
+            .lock()
+            .unwrap()
+            .retain(|_, window| now - window.started_at < PUBLIC_API_RATE_LIMIT_WINDOW);
+    }
+}
+
+async fn rate_limit(
+    State(state): State<PublicApiState>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let now = Instant::now();
+
+    let retry_after = {
+        let mut requests = state.requests.lock().unwrap();
+
+        let window = requests.entry(address.ip()).or_insert(RequestWindow {
+            started_at: now,
+            count: 0,
+        });
+
+        // A window that has passed but is yet to be pruned starts again.
+        if now - window.started_at >= PUBLIC_API_RATE_LIMIT_WINDOW {
+            *window = RequestWindow {
+                started_at: now,
+                count: 0,
+            };
+        }
+
+        window.count += 1;
+
+        (window.count > state.config.public_api.rate_limit)
+            .then(|| PUBLIC_API_RATE_LIMIT_WINDOW - (now - window.started_at))
+    };
+
+    match retry_after {
+        Some(retry_after) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.as_secs().max(1).to_string())],
+        )
+            .into_response(),
+        None => next.run(request).await,
+    }
+}
+
+async fn todays_shard_eruption(
+    State(state): State<PublicApiState>,
+    headers: HeaderMap,
+) -> Response {
+    let today = Utc::now().with_timezone(&Los_Angeles).date_naive();
+
+    respond(
+        &state,
+        "/shard-eruptions/today",
+        &headers,
+        JSON,
+        render_shard_eruption(&state, today),
+    )
+    .await
+}
+
+/// Whether a shard eruption has landed right now, where, and when it clears, so consumers need not work it out from the landings.
+async fn shard_eruption_status(
+    State(state): State<PublicApiState>,
+    headers: HeaderMap,
+) -> Response {
+    respond(
+        &state,
+        "/shard-eruptions/now",
+        &headers,
+        JSON,
+        render_shard_eruption_status(&state),
+    )
+    .await
+}
+
+async fn shard_eruption(
+    State(state): State<PublicApiState>,
+    Path(date): Path<NaiveDate>,
+    headers: HeaderMap,
+) -> Response {
+    respond(
+        &state,
+        &format!("/shard-eruptions/{date}"),
+        &headers,
+        JSON,
+        render_shard_eruption(&state, date),
+    )
+    .await
+}
+
+async fn combined_feed(State(state): State<PublicApiState>, headers: HeaderMap) -> Response {
+    respond(
+        &state,
+        "/feeds/notifications.atom",
+        &headers,
+        ATOM,
+        render_feed(&state, None),
+    )
+    .await
+}
+
+/// A feed of one notification type, such as `/feeds/12.atom`.
+async fn feed(
+    State(state): State<PublicApiState>,
+    Path(feed): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(r#type) = feed
+        .strip_suffix(".atom")
+        .and_then(|r#type| r#type.parse::<NotificationType>().ok())
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    respond(
+        &state,
+        &format!("/feeds/{feed}"),
+        &headers,
+        ATOM,
+        render_feed(&state, Some(r#type)),
+    )
+    .await
+}
+
+/// Registers an email address for digests and asks its holder to confirm.
+///
+/// The response is the same whether or not the address was already registered, so it cannot be used to find out who is.
+async fn register(
+    State(state): State<PublicApiState>,
+    Json(registration): Json<EmailRegistration>,
+) -> Response {
+    if let Err(error) = registration.preferences.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response();
+    }
+
+    match register_email(&state.pool, &registration.email, &registration.preferences).await {
+        Ok(Some(token)) => {
+            tokio::spawn(async move {
+                send_confirmation(&state.config, &registration.email, &token).await;
+            });
+
+            StatusCode::ACCEPTED.into_response()
+        }
+        Ok(None) => StatusCode::ACCEPTED.into_response(),
+        Err(error) => match error.downcast::<lettre::address::AddressError>() {
+            Ok(error) => (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response(),
+            Err(error) => {
+                tracing::error!("Failed to register an email address: {error:?}");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        },
+    }
+}
+
+async fn confirm(State(state): State<PublicApiState>, Path(token): Path<String>) -> Response {
+    match confirm_email(&state.pool, &token).await {
+        Ok(true) => "Your Sky digest is confirmed.".into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!("Failed to confirm an email address: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn update_preferences(
+    State(state): State<PublicApiState>,
+    Path(token): Path<String>,
+    Json(preferences): Json<EmailPreferences>,
+) -> Response {
+    if let Err(error) = preferences.validate() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()).into_response();
+    }
+
+    match update_email_preferences(&state.pool, &token, &preferences).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!("Failed to update email preferences: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Mail scanners follow links, so following the unsubscribe link only asks to confirm.
+async fn unsubscribe_form() -> Html<&'static str> {
+    Html(
+        r#"<!doctype html><title>Unsubscribe</title><form method="post"><button>Stop sending me Sky digests</button></form>"#,
+    )
+}
+
+async fn unsubscribe(State(state): State<PublicApiState>, Path(token): Path<String>) -> Response {
+    match unsubscribe_email(&state.pool, &token).await {
+        Ok(true) => "You will not be sent any more Sky digests.".into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            tracing::error!("Failed to unsubscribe an email address: {error:?}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Responds with the cached response for a path, rendering it only if that has expired.
+///
+/// Rendering gives the body and how long it may be served for.
+async fn respond(
+    state: &PublicApiState,
+    path: &str,
+    headers: &HeaderMap,
+    content_type: &'static str,
+    render: impl Future<Output = Result<(String, Duration)>>,
+) -> Response {
+    let now = Instant::now();
+
+    let cached = state
+        .responses
+        .lock()
+        .unwrap()
+        .get(path)
+        .filter(|cached| cached.expires_at > now)
+        .cloned();
+
+    let cached = match cached {
+        Some(cached) => cached,
+        None => {
+            let (body, ttl) = match render.await {
+                Ok(rendered) => rendered,
+                Err(error) => {
+                    tracing::error!("Failed to render {path}: {error:?}");
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            };
+
+            let mut hasher = DefaultHasher::new();
+            body.hash(&mut hasher);
+
+            let cached = Arc::new(CachedResponse {
+                expires_at: now + ttl,
+                etag: format!("\"{:016x}\"", hasher.finish()),
+                body,
+            });
+
+            state
+                .responses
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), Arc::clone(&cached));
+            cached
+        }
+    };
+
+    let cache_control = format!("public, max-age={}", (cached.expires_at - now).as_secs());
+
+    let not_modified = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|etag| etag == "*" || etag.trim_start_matches("W/") == cached.etag)
+        });
+
+    if not_modified {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, cached.etag.clone()), (CACHE_CONTROL, cache_control)],
+        )
+            .into_response();
+    }
+
+    (
+        [
+            (CONTENT_TYPE, content_type.to_string()),
+            (ETAG, cached.etag.clone()),
+            (CACHE_CONTROL, cache_control),
+        ],
+        cached.body.clone(),
+    )
+        .into_response()
+}
+
+/// The public may not cause requests to the wind paths, so only what is already known is served.
+async fn render_shard_eruption(
+    state: &PublicApiState,
+    date: NaiveDate,
+) -> Result<(String, Duration)> {
+    let shard_eruption = get_known_shard_eruption(&state.pool, &state.config, date).await?;
+
+    Ok((
+        serde_json::to_string(&json!({ "date": date, "shard_eruption": shard_eruption }))?,
+        PUBLIC_API_CACHE_TTL,
+    ))
+}
+
+/// Served only until the next landing starts or clears, so the status is never out of date.
+async fn render_shard_eruption_status(state: &PublicApiState) -> Result<(String, Duration)> {
+    let now = Utc::now().with_timezone(&Los_Angeles);
+    let shard_eruption =
+        get_known_shard_eruption(&state.pool, &state.config, now.date_naive()).await?;
+
+    let timestamps = shard_eruption
+        .as_ref()
+        .map(|shard_eruption| shard_eruption.timestamps.as_slice())
+        .unwrap_or_default();
+
+    let landing = timestamps
+        .iter()
+        .find(|dates| dates.start <= now && now < dates.end);
+
+    let next_landing = timestamps.iter().find(|dates| now < dates.start);
+
+    let next_change = landing
+        .map(|dates| dates.end)
+        .or(next_landing.map(|dates| dates.start))
+        .and_then(|change| (change - now).to_std().ok())
+        .unwrap_or(PUBLIC_API_CACHE_TTL);
+
+    Ok((
+        serde_json::to_string(&json!({
+            "active": landing.is_some(),
+            "landing": landing,
+            "next_landing": next_landing,
+            "shard_eruption": shard_eruption,
+        }))?,
+        next_change.min(PUBLIC_API_CACHE_TTL),
+    ))
+}
+
+async fn render_feed(
+    state: &PublicApiState,
+    r#type: Option<NotificationType>,
+) -> Result<(String, Duration)> {
+    let entries = get_dispatched_notifications(&state.pool, r#type).await?;
+    Ok((atom_feed(r#type, &entries), PUBLIC_API_CACHE_TTL))
+}
@@ -0,0 +1,370 @@
+use super::metrics;
+use crate::structures::{config::Backpressure, notification::NotificationNotify};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Mutex,
+    },
+};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+struct QueuedNotification {
+    priority: u8,
+    sequence: u64,
+    notification_notify: NotificationNotify,
+}
+
+impl PartialEq for QueuedNotification {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedNotification {}
+
+impl PartialOrd for QueuedNotification {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedNotification {
+    // Higher priorities first, then first in, first out.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+/// Room set aside in the queue, so notifications can be queued without waiting. Room left unused is freed when dropped.
+pub struct Reservation<'a> {
+    queue: &'a NotificationQueue,
+    permit: SemaphorePermit<'a>,
+}
+
+impl Reservation<'_> {
+    /// How many more notifications there is room for.
+    pub fn len(&self) -> usize {
+        self.permit.num_permits()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Queues a notification in the room set aside.
+    pub fn push(&mut self, notification_notify: NotificationNotify) {
+        self.permit
+            .split(1)
+            .expect("Only as many notifications as reserved are queued.")
+            .forget();
+
+        self.queue.insert(notification_notify);
+    }
+}
+
+/// A bounded queue that hands out the most urgent notification first.
+pub struct NotificationQueue {
+    heap: Mutex<BinaryHeap<QueuedNotification>>,
+    capacity: usize,
+    backpressure: Backpressure,
+    sequence: AtomicU64,
+    /// Permits for free slots.
+    slots: Semaphore,
+    /// Permits for queued notifications.
+    items: Semaphore,
+}
+
+impl NotificationQueue {
+    pub fn new(capacity: usize, backpressure: Backpressure) -> Self {
+        Self {
+            heap: Mutex::new(BinaryHeap::with_capacity(capacity)),
+            capacity,
+            backpressure,
+            sequence: AtomicU64::new(0),
+            slots: Semaphore::new(capacity),
+            items: Semaphore::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many notifications may be queued before the queue is full.
+    pub fn available(&self) -> usize {
+        self.slots.available_permits()
+    }
+
+    /// Sets aside room for up to `count` notifications, or as many as there is room for.
+    ///
+    /// Returns `None` if the queue is full.
+    pub fn reserve(&self, count: usize) -> Option<Reservation<'_>> {
+        let count = count.min(self.available());
+
+        if count == 0 {
+            return None;
+        }
+
+        let permit = self.slots.try_acquire_many(count as u32).ok()?;
+        Some(Reservation {
+            queue: self,
+            permit,
+        })
+    }
+
+    /// Queues a notification according to the backpressure policy.
+    ///
+    /// Returns the notification if it should be spilled to the outbox instead.
+    pub async fn push(
+        &self,
+        mut notification_notify: NotificationNotify,
+    ) -> Option<NotificationNotify> {
+        notification_notify
+            .queued_at
+            .get_or_insert_with(|| chrono::Utc::now().timestamp_millis());
+
+        if let Ok(permit) = self.slots.try_acquire() {
+            permit.forget();
+            self.insert(notification_notify);
+            return None;
+        }
+
+        let notification_notify = match self.backpressure {
+            Backpressure::Block => notification_notify,
+            Backpressure::DropOldest => match self.replace_oldest(notification_notify) {
+                Some(notification_notify) => notification_notify,
+                None => return None,
+            },
+            Backpressure::Spill => return Some(notification_notify),
+        };
+
+        metrics::increment("queue_blocked");
+        self.push_blocking(notification_notify).await;
+        None
+    }
+
+    /// Queues a notification, waiting for a free slot if the queue is full.
+    pub async fn push_blocking(&self, notification_notify: NotificationNotify) {
+        self.slots
+            .acquire()
+            .await
+            .expect("The notification queue is closed.")
+            .forget();
+
+        self.insert(notification_notify);
+    }
+
+    fn insert(&self, notification_notify: NotificationNotify) {
+        self.heap.lock().unwrap().push(QueuedNotification {
+            priority: notification_notify.priority(),
+            sequence: self.sequence.fetch_add(1, AtomicOrdering::Relaxed),
+            notification_notify,
+        });
+
+        self.items.add_permits(1);
+    }
+
+    /// Swaps the oldest queued notification of the same type for this one, keeping the queue the same length.
+    ///
+    /// Returns the notification if there was nothing to replace.
+    fn replace_oldest(
+        &self,
+        notification_notify: NotificationNotify,
+    ) -> Option<NotificationNotify> {
+        let mut heap = self.heap.lock().unwrap();
+        let mut queued_notifications = std::mem::take(&mut *heap).into_vec();
+
+        let oldest = queued_notifications
+            .iter()
+            .enumerate()
+            .filter(|(_, queued_notification)| {
+                queued_notification.notification_notify.r#type == notification_notify.r#type
+            })
+            .min_by_key(|(_, queued_notification)| queued_notification.sequence)
+            .map(|(index, _)| index);
+
+        let Some(index) = oldest else {
+            *heap = queued_notifications.into();
+            return Some(notification_notify);
+        };
+
+        let dropped = queued_notifications.swap_remove(index);
+
+        tracing::warn!(
+            r#type = ?dropped.notification_notify.r#type,
+            start_time = dropped.notification_notify.start_time,
+            "Dropped a queued notification as the queue is full."
+        );
+
+        metrics::increment("queue_dropped");
+
+        queued_notifications.push(QueuedNotification {
+            priority: notification_notify.priority(),
+            sequence: self.sequence.fetch_add(1, AtomicOrdering::Relaxed),
+            notification_notify,
+        });
+
+        *heap = queued_notifications.into();
+        None
+    }
+
+    /// Waits for and removes the most urgent notification.
+    pub async fn pop(&self) -> NotificationNotify {
+        self.items
+            .acquire()
+            .await
+            .expect("The notification queue is closed.")
+            .forget();
+
+        let queued_notification = self
+            .heap
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("A permit guarantees a queued notification.");
+
+        self.slots.add_permits(1);
+        queued_notification.notification_notify
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::notification::NotificationType;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    fn notification_notify(
+        r#type: NotificationType,
+        start_time: i64,
+        time_until_start: u32,
+    ) -> NotificationNotify {
+        NotificationNotify {
+            r#type,
+            start_time,
+            end_time: None,
+            time_until_start,
+            shard_eruption: None,
+            travelling_spirit_name: None,
+            special_visit_entities: None,
+            travelling_spirit_details: None,
+            weekly_digest: None,
+            occurrences: None,
+            recurring_event: None,
+            maintenance_end: None,
+            game_update: None,
+            late: false,
+            queued_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn pops_the_most_urgent_first_then_in_order() {
+        let queue = NotificationQueue::new(4, Backpressure::Block);
+
+        for notification_notify in [
+            notification_notify(NotificationType::Aurora, 1, 0),
+            notification_notify(NotificationType::Aurora, 2, 0),
+            notification_notify(NotificationType::ShardEruptionStrong, 3, 10),
+            notification_notify(NotificationType::ShardEruptionStrong, 4, 0),
+        ] {
+            assert!(queue.push(notification_notify).await.is_none());
+        }
+
+        let mut popped = vec![];
+
+        while !queue.is_empty() {
+            popped.push(queue.pop().await.start_time);
+        }
+
+        // A start outranks a lead of the same type, and equal priorities come out first in, first out.
+        assert_eq!(popped, [4, 3, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn spills_when_full() {
+        let queue = NotificationQueue::new(1, Backpressure::Spill);
+
+        assert!(queue
+            .push(notification_notify(NotificationType::Aurora, 1, 0))
+            .await
+            .is_none());
+
+        let spilled = queue
+            .push(notification_notify(NotificationType::Aurora, 2, 0))
+            .await
+            .expect("A full queue spills.");
+
+        assert_eq!(spilled.start_time, 2);
+        assert!(spilled.queued_at.is_some());
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn drops_the_oldest_of_the_same_type_when_full() {
+        let queue = NotificationQueue::new(2, Backpressure::DropOldest);
+
+        for notification_notify in [
+            notification_notify(NotificationType::Aurora, 1, 0),
+            notification_notify(NotificationType::Grandma, 2, 0),
+            notification_notify(NotificationType::Aurora, 3, 0),
+        ] {
+            assert!(queue.push(notification_notify).await.is_none());
+        }
+
+        assert_eq!(queue.len(), 2);
+        let mut popped = [queue.pop().await.start_time, queue.pop().await.start_time];
+        popped.sort_unstable();
+        assert_eq!(popped, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn blocks_until_there_is_room() {
+        let queue = NotificationQueue::new(1, Backpressure::Block);
+
+        assert!(queue
+            .push(notification_notify(NotificationType::Aurora, 1, 0))
+            .await
+            .is_none());
+
+        let blocked = queue.push(notification_notify(NotificationType::Aurora, 2, 0));
+        tokio::pin!(blocked);
+
+        assert!(timeout(Duration::from_millis(50), &mut blocked)
+            .await
+            .is_err());
+
+        assert_eq!(queue.pop().await.start_time, 1);
+        assert!(blocked.await.is_none());
+        assert_eq!(queue.pop().await.start_time, 2);
+    }
+
+    #[test]
+    fn reserves_only_the_room_there_is() {
+        let queue = NotificationQueue::new(3, Backpressure::Block);
+        let mut reservation = queue.reserve(5).expect("The queue is empty.");
+        assert_eq!(reservation.len(), 3);
+
+        reservation.push(notification_notify(NotificationType::Aurora, 1, 0));
+        assert_eq!(reservation.len(), 2);
+        assert_eq!(queue.available(), 0);
+
+        // Room left unused is freed.
+        drop(reservation);
+        assert_eq!(queue.available(), 2);
+        assert_eq!(queue.len(), 1);
+    }
+}
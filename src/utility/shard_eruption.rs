@@ -0,0 +1,248 @@
+use super::{
+    constants::SkyMap,
+    wind_paths::{ShardEruptionDates, ShardEruptionResponse},
+};
+use chrono::{Datelike, Duration, NaiveDate, TimeZone};
+use chrono_tz::America::Los_Angeles;
+
+/// The amount of candle wax a regular shard eruption rewards.
+const REGULAR_SHARD_ERUPTION_REWARD: f32 = 200.0;
+
+/// Shards land this long after their window opens.
+const SHARD_ERUPTION_LANDING_OFFSET: Duration = Duration::seconds(520);
+
+/// Shards clear up this long after their window opens.
+const SHARD_ERUPTION_DURATION: Duration = Duration::hours(4);
+
+struct ShardEruptionPattern {
+    /// Days of the week (Monday is 1) without a shard eruption.
+    no_shard_weekdays: [u32; 2],
+    interval: Duration,
+    offset: Duration,
+    /// The map for each realm, in realm order.
+    sky_maps: [SkyMap; 5],
+    strong: bool,
+    reward: f32,
+}
+
+fn patterns() -> [ShardEruptionPattern; 5] {
+    [
+        ShardEruptionPattern {
+            no_shard_weekdays: [6, 7],
+            interval: Duration::hours(8),
+            offset: Duration::minutes(110),
+            sky_maps: [
+                SkyMap::ButterflyFields,
+                SkyMap::ForestBrook,
+                SkyMap::IceRink,
+                SkyMap::BrokenTemple,
+                SkyMap::StarlightDesert,
+            ],
+            strong: false,
+            reward: REGULAR_SHARD_ERUPTION_REWARD,
+        },
+        ShardEruptionPattern {
+            no_shard_weekdays: [7, 1],
+            interval: Duration::hours(8),
+            offset: Duration::minutes(130),
+            sky_maps: [
+                SkyMap::KoiPond,
+                SkyMap::Boneyard,
+                SkyMap::IceRink,
+                SkyMap::Battlefield,
+                SkyMap::StarlightDesert,
+            ],
+            strong: false,
+            reward: REGULAR_SHARD_ERUPTION_REWARD,
+        },
+        ShardEruptionPattern {
+            no_shard_weekdays: [1, 2],
+            interval: Duration::hours(6),
+            offset: Duration::minutes(460),
+            sky_maps: [
+                SkyMap::Cave,
+                SkyMap::ForestEnd,
+                SkyMap::VillageOfDreams,
+                SkyMap::Graveyard,
+                SkyMap::JellyfishCove,
+            ],
+            strong: true,
+            reward: 2.0,
+        },
+        ShardEruptionPattern {
+            no_shard_weekdays: [2, 3],
+            interval: Duration::hours(6),
+            offset: Duration::minutes(140),
+            sky_maps: [
+                SkyMap::BirdNest,
+                SkyMap::Treehouse,
+                SkyMap::VillageOfDreams,
+                SkyMap::CrabFields,
+                SkyMap::JellyfishCove,
+            ],
+            strong: true,
+            reward: 2.5,
+        },
+        ShardEruptionPattern {
+            no_shard_weekdays: [3, 4],
+            interval: Duration::hours(6),
+            offset: Duration::minutes(210),
+            sky_maps: [
+                SkyMap::SanctuaryIslands,
+                SkyMap::ElevatedClearing,
+                SkyMap::HermitValley,
+                SkyMap::ForgottenArk,
+                SkyMap::JellyfishCove,
+            ],
+            strong: true,
+            reward: 3.5,
+        },
+    ]
+}
+
+/// Some maps reward more ascended candles than the rest of their pattern.
+fn reward_override(sky_map: &SkyMap) -> Option<f32> {
+    match sky_map {
+        SkyMap::ForestEnd | SkyMap::VillageOfDreams => Some(2.5),
+        SkyMap::Treehouse | SkyMap::JellyfishCove => Some(3.5),
+        _ => None,
+    }
+}
+
+fn slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+/// Calculates the shard eruption for a date without relying on the wind paths.
+///
+/// The map's image is linked from `cdn_url`.
+pub fn calculate_shard_eruption(date: NaiveDate, cdn_url: &str) -> Option<ShardEruptionResponse> {
+    let day = date.day();
+    let weekday = date.weekday().number_from_monday();
+    let strong = day % 2 == 1;
+
+    let pattern_index = if strong {
+        ((day - 1) / 2) % 3 + 2
+    } else {
+        (day / 2) % 2
+    } as usize;
+
+    let pattern = patterns().into_iter().nth(pattern_index)?;
+
+    if pattern.no_shard_weekdays.contains(&weekday) {
+        return None;
+    }
+
+    let sky_map = pattern.sky_maps[((day - 1) % 5) as usize].clone();
+    let realm = sky_map.realm();
+
+    let reward = if pattern.strong {
+        reward_override(&sky_map).unwrap_or(pattern.reward)
+    } else {
+        pattern.reward
+    };
+
+    let start_of_day = Los_Angeles
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()?;
+
+    let timestamps = (0..3)
+        .map(|index| {
+            let window = start_of_day + pattern.offset + pattern.interval * index;
+
+            ShardEruptionDates {
+                start: window + SHARD_ERUPTION_LANDING_OFFSET,
+                end: window + SHARD_ERUPTION_DURATION,
+            }
+        })
+        .collect();
+
+    let url = format!(
+        "{cdn_url}/daily_guides/shard_eruptions/{}/{}.webp",
+        slug(realm),
+        slug(&sky_map.to_string())
+    );
+
+    Some(ShardEruptionResponse {
+        realm: realm.to_string(),
+        sky_map,
+        strong: pattern.strong,
+        reward,
+        timestamps,
+        url,
+        variant: None,
+    })
+}
+
+/// Describes how the wind paths' shard eruption differs from the calculated one, if at all.
+pub fn shard_eruption_mismatch(
+    remote: Option<&ShardEruptionResponse>,
+    local: Option<&ShardEruptionResponse>,
+) -> Option<String> {
+    let (remote, local) = match (remote, local) {
+        (None, None) => return None,
+        (Some(remote), None) => {
+            return Some(format!(
+                "The wind paths report a shard eruption in {} ({}), but none was calculated.",
+                remote.realm, remote.sky_map
+            ))
+        }
+        (None, Some(local)) => {
+            return Some(format!(
+                "The wind paths report no shard eruption, but one was calculated in {} ({}).",
+                local.realm, local.sky_map
+            ))
+        }
+        (Some(remote), Some(local)) => (remote, local),
+    };
+
+    let mut differences = vec![];
+
+    if remote.realm != local.realm || remote.sky_map != local.sky_map {
+        differences.push(format!(
+            "location is {} ({}) instead of {} ({})",
+            remote.realm, remote.sky_map, local.realm, local.sky_map
+        ));
+    }
+
+    if remote.strong != local.strong {
+        differences.push(format!(
+            "strength is {} instead of {}",
+            remote.strong, local.strong
+        ));
+    }
+
+    if remote.reward != local.reward {
+        differences.push(format!(
+            "reward is {} instead of {}",
+            remote.reward, local.reward
+        ));
+    }
+
+    let remote_starts = remote
+        .timestamps
+        .iter()
+        .map(|dates| dates.start.timestamp())
+        .collect::<Vec<_>>();
+
+    let local_starts = local
+        .timestamps
+        .iter()
+        .map(|dates| dates.start.timestamp())
+        .collect::<Vec<_>>();
+
+    if remote_starts != local_starts {
+        differences.push(format!(
+            "landings are {remote_starts:?} instead of {local_starts:?}"
+        ));
+    }
+
+    if differences.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "The wind paths shard eruption differs from the calculated one: {}.",
+            differences.join("; ")
+        ))
+    }
+}
@@ -0,0 +1,158 @@
+use super::markup::{segments, Segment};
+use crate::structures::{
+    config::FlavorText,
+    notification::{NotificationNotify, NotificationType},
+};
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+
+/// Builds the Block Kit message posted to a Slack incoming webhook for a notification.
+///
+/// Shard eruptions and resets get their own layout. Everything else is the notification's text, with Discord's markup rewritten for Slack.
+pub fn slack_message(flavor_text: FlavorText, notification_notify: &NotificationNotify) -> Value {
+    let text = mrkdwn(&notification_notify.with_notes(notification_notify.text(flavor_text)));
+    let section = json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } });
+
+    let blocks = match notification_notify.r#type {
+        NotificationType::ShardEruptionRegular
+        | NotificationType::ShardEruptionStrong
+        | NotificationType::ShardEruptionVariant => {
+            let mut blocks = vec![
+                header(&shard_eruption_heading(notification_notify)),
+                section,
+            ];
+
+            if let Some(shard_eruption) = &notification_notify.shard_eruption {
+                let landings = shard_eruption
+                    .timestamps
+                    .iter()
+                    .map(|dates| {
+                        format!(
+                            "{} to {}",
+                            date(dates.start.timestamp(), "{time}"),
+                            date(dates.end.timestamp(), "{time}")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let sky_map = format!(
+                    "<{}|{}>",
+                    shard_eruption.url,
+                    escape(&shard_eruption.sky_map.to_string())
+                );
+
+                blocks.push(json!({
+                    "type": "section",
+                    "fields": [
+                        field("Realm", &escape(&shard_eruption.realm.to_string())),
+                        field("Map", &sky_map),
+                        field("Reward", &escape(&shard_eruption.reward_text())),
+                        field("Landings", &landings.join("\n")),
+                    ],
+                }));
+            }
+
+            blocks
+        }
+        NotificationType::ShardEruptionEnd => vec![header("Shard eruption clearing up"), section],
+        NotificationType::ShardEruptionCorrection => {
+            vec![header("Shard eruption correction"), section]
+        }
+        NotificationType::NoShardEruption => vec![header("No shard eruption"), section],
+        NotificationType::DailyReset | NotificationType::EyeOfEden => vec![
+            header(
+                if notification_notify.r#type == NotificationType::DailyReset {
+                    "Daily reset"
+                } else {
+                    "Eye of Eden reset"
+                },
+            ),
+            section,
+            json!({
+                "type": "context",
+                "elements": [{
+                    "type": "mrkdwn",
+                    "text": format!(
+                        "Resets {}",
+                        date(notification_notify.start_time, "{date_long_pretty} at {time}")
+                    ),
+                }],
+            }),
+        ],
+        _ => vec![section],
+    };
+
+    json!({ "text": text, "blocks": blocks })
+}
+
+fn shard_eruption_heading(notification_notify: &NotificationNotify) -> String {
+    let variant = notification_notify
+        .shard_eruption
+        .as_ref()
+        .and_then(|shard_eruption| shard_eruption.variant.as_deref());
+
+    match (notification_notify.r#type, variant) {
+        (NotificationType::ShardEruptionVariant, Some(variant)) => {
+            let mut characters = variant.chars();
+
+            match characters.next() {
+                Some(first) => format!(
+                    "{}{} shard eruption",
+                    first.to_uppercase(),
+                    characters.as_str()
+                ),
+                None => "Special shard eruption".to_string(),
+            }
+        }
+        (NotificationType::ShardEruptionStrong, _) => "Strong shard eruption".to_string(),
+        _ => "Regular shard eruption".to_string(),
+    }
+}
+
+fn header(text: &str) -> Value {
+    json!({ "type": "header", "text": { "type": "plain_text", "text": text } })
+}
+
+fn field(name: &str, value: &str) -> Value {
+    json!({ "type": "mrkdwn", "text": format!("*{name}*\n{value}") })
+}
+
+/// Rewrites Discord's links and timestamps in Slack's markup, escaping the rest.
+fn mrkdwn(text: &str) -> String {
+    segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => escape(text),
+            Segment::Link { label, url } => format!("<{url}|{}>", escape(label)),
+            Segment::Timestamp { timestamp, style } => date(
+                timestamp,
+                match style {
+                    'R' => "{ago}",
+                    't' => "{time}",
+                    'T' => "{time_secs}",
+                    'd' => "{date_num}",
+                    'D' => "{date_long}",
+                    _ => "{date_long_pretty} at {time}",
+                },
+            ),
+        })
+        .collect()
+}
+
+/// Slack shows a date in the reader's time zone, or the fallback if it cannot.
+fn date(timestamp: i64, format: &str) -> String {
+    let fallback = Utc
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string());
+
+    format!("<!date^{timestamp}^{format}|{fallback}>")
+}
+
+/// Escapes the characters Slack reserves for its markup.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,259 @@
+use super::{
+    constants::{BLUESKY_MAXIMUM_LENGTH, MASTODON_MAXIMUM_LENGTH},
+    http::CLIENT,
+    markup::{format_timestamp, segments, Segment},
+    metrics,
+};
+use crate::structures::{
+    config::{FlavorText, SocialConfig},
+    notification::{NotificationNotify, NotificationType},
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::{ops::Range, sync::OnceLock};
+
+/// The accounts major events are posted to.
+static ACCOUNTS: OnceLock<Vec<Account>> = OnceLock::new();
+
+enum Account {
+    Mastodon {
+        url: String,
+        token: String,
+    },
+    Bluesky {
+        service: String,
+        identifier: String,
+        password: String,
+    },
+}
+
+/// A notification's text with Discord's markup removed, so it reads well anywhere.
+struct PlainText {
+    text: String,
+    /// The byte ranges of the text that were links, and where they went.
+    links: Vec<(Range<usize>, String)>,
+}
+
+#[derive(Deserialize)]
+struct BlueskySession {
+    #[serde(rename = "accessJwt")]
+    access_jwt: String,
+    did: String,
+}
+
+/// Remembers the configured accounts. Nothing is posted if there are none.
+pub fn connect(config: &SocialConfig) {
+    let mut accounts = vec![];
+
+    if !config.mastodon_url.is_empty() {
+        accounts.push(Account::Mastodon {
+            url: config.mastodon_url.trim_end_matches('/').to_string(),
+            token: config.mastodon_token.clone(),
+        });
+    }
+
+    if !config.bluesky_identifier.is_empty() {
+        accounts.push(Account::Bluesky {
+            service: config.bluesky_service.trim_end_matches('/').to_string(),
+            identifier: config.bluesky_identifier.clone(),
+            password: config.bluesky_password.clone(),
+        });
+    }
+
+    if !accounts.is_empty() {
+        tracing::info!(
+            "Posting major events to {} social accounts.",
+            accounts.len()
+        );
+        ACCOUNTS.get_or_init(|| accounts);
+    }
+}
+
+/// Posts a notification to every account in the background if it is of a major event: a travelling spirit arriving, a season starting, or the first strong shard eruption of the day.
+pub fn post(flavor_text: FlavorText, notification_notify: &NotificationNotify) {
+    let Some(accounts) = ACCOUNTS.get() else {
+        return;
+    };
+
+    if !is_major(notification_notify) {
+        return;
+    }
+
+    let plain_text = plain_text(
+        &notification_notify.with_notes(notification_notify.text(flavor_text)),
+        Utc::now(),
+    );
+
+    // Retries must not post twice, so a post is identified by its occurrence.
+    let idempotency_key = format!(
+        "{:?}-{}",
+        notification_notify.r#type, notification_notify.start_time
+    );
+
+    tokio::spawn(async move {
+        for account in accounts {
+            let result = match account {
+                Account::Mastodon { url, token } => {
+                    post_to_mastodon(url, token, &plain_text, &idempotency_key).await
+                }
+                Account::Bluesky {
+                    service,
+                    identifier,
+                    password,
+                } => post_to_bluesky(service, identifier, password, &plain_text).await,
+            };
+
+            match result {
+                Ok(()) => metrics::increment("social_posts"),
+                Err(error) => {
+                    metrics::increment("social_posts_failed");
+                    tracing::error!("Failed to post a major event: {error:?}");
+                }
+            }
+        }
+    });
+}
+
+fn is_major(notification_notify: &NotificationNotify) -> bool {
+    if notification_notify.time_until_start != 0 {
+        return false;
+    }
+
+    match notification_notify.r#type {
+        NotificationType::TravellingSpirit => true,
+        NotificationType::SeasonalActivity => notification_notify
+            .recurring_event
+            .as_ref()
+            .is_some_and(|recurring_event| recurring_event.first),
+        // A strong shard eruption lands several times, so only the first landing is posted.
+        NotificationType::ShardEruptionStrong => notification_notify
+            .shard_eruption
+            .as_ref()
+            .and_then(|shard_eruption| shard_eruption.timestamps.first())
+            .is_some_and(|dates| dates.start.timestamp() == notification_notify.start_time),
+        _ => false,
+    }
+}
+
+/// Replaces Discord's links and timestamps, which other platforms show as written.
+///
+/// Relative timestamps are written relative to `now`, when the text is posted.
+fn plain_text(text: &str, now: DateTime<Utc>) -> PlainText {
+    let mut plain_text = PlainText {
+        text: String::with_capacity(text.len()),
+        links: vec![],
+    };
+
+    for segment in segments(text) {
+        match segment {
+            Segment::Text(text) => plain_text.text.push_str(text),
+            Segment::Link { label, url } => {
+                let start = plain_text.text.len();
+                plain_text.text.push_str(label);
+
+                plain_text
+                    .links
+                    .push((start..plain_text.text.len(), url.to_string()));
+            }
+            Segment::Timestamp { timestamp, style } => plain_text
+                .text
+                .push_str(&format_timestamp(timestamp, style, now)),
+        }
+    }
+
+    plain_text
+}
+
+/// Shortens text to a number of characters, ending it with an ellipsis if it was too long. Returns the text and how many of its bytes were kept.
+fn truncate(text: &str, maximum_length: usize) -> (String, usize) {
+    if text.chars().count() <= maximum_length {
+        return (text.to_string(), text.len());
+    }
+
+    let (index, _) = text.char_indices().nth(maximum_length - 1).unwrap();
+    (format!("{}…", &text[..index]), index)
+}
+
+/// Mastodon turns addresses into links itself, so links are written out after their text.
+async fn post_to_mastodon(
+    url: &str,
+    token: &str,
+    plain_text: &PlainText,
+    idempotency_key: &str,
+) -> Result<()> {
+    let mut status = String::with_capacity(plain_text.text.len());
+    let mut written = 0;
+
+    for (range, url) in &plain_text.links {
+        status.push_str(&plain_text.text[written..range.end]);
+        status.push_str(&format!(" ({url})"));
+        written = range.end;
+    }
+
+    status.push_str(&plain_text.text[written..]);
+
+    let (status, _) = truncate(&status, MASTODON_MAXIMUM_LENGTH);
+
+    CLIENT
+        .post(format!("{url}/api/v1/statuses"))
+        .bearer_auth(token)
+        .header("Idempotency-Key", idempotency_key)
+        .json(&json!({ "status": status }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
+
+/// Bluesky shows links as rich text over their text, which keeps posts within its shorter limit.
+async fn post_to_bluesky(
+    service: &str,
+    identifier: &str,
+    password: &str,
+    plain_text: &PlainText,
+) -> Result<()> {
+    let session: BlueskySession = CLIENT
+        .post(format!("{service}/xrpc/com.atproto.server.createSession"))
+        .json(&json!({ "identifier": identifier, "password": password }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let (text, kept) = truncate(&plain_text.text, BLUESKY_MAXIMUM_LENGTH);
+
+    let facets = plain_text
+        .links
+        .iter()
+        .filter(|(range, _)| range.end <= kept)
+        .map(|(range, url)| {
+            json!({
+                "index": { "byteStart": range.start, "byteEnd": range.end },
+                "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": url }],
+            })
+        })
+        .collect::<Vec<_>>();
+
+    CLIENT
+        .post(format!("{service}/xrpc/com.atproto.repo.createRecord"))
+        .bearer_auth(session.access_jwt)
+        .json(&json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": {
+                "$type": "app.bsky.feed.post",
+                "text": text,
+                "facets": facets,
+                "createdAt": Utc::now().to_rfc3339(),
+            },
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
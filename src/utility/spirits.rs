@@ -0,0 +1,73 @@
+use super::{error_reporting::report_parse_failure, http::CLIENT};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpiritItem {
+    pub name: String,
+    pub candles: Option<u32>,
+    pub hearts: Option<u32>,
+    pub ascended_candles: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpiritDetails {
+    pub items: Vec<SpiritItem>,
+    // Not part of the upstream response, but kept when spilled to the outbox.
+    #[serde(default)]
+    pub image_url: String,
+}
+
+impl SpiritItem {
+    /// Summarises the item and its cost, such as "Hair (26 candles)".
+    pub fn summary(&self) -> String {
+        let costs = [
+            (self.candles, "candles"),
+            (self.hearts, "hearts"),
+            (self.ascended_candles, "ascended candles"),
+        ]
+        .into_iter()
+        .filter_map(|(amount, currency)| amount.map(|amount| format!("{amount} {currency}")))
+        .collect::<Vec<_>>();
+
+        if costs.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, costs.join(", "))
+        }
+    }
+}
+
+/// Converts a spirit's name into the identifier used by the data source and the CDN.
+fn slug(entity: &str) -> String {
+    entity
+        .to_lowercase()
+        .chars()
+        .filter(|character| character.is_alphanumeric() || character.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub async fn spirit_details(url: &str, cdn_url: &str, entity: &str) -> Option<SpiritDetails> {
+    let slug = slug(entity);
+
+    let response = match CLIENT.get(format!("{url}/spirits/{slug}")).send().await {
+        Ok(response) => response,
+        Err(error) => {
+            tracing::warn!("Failed to fetch the details of {entity}: {error:?}");
+            return None;
+        }
+    };
+
+    match response.json::<SpiritDetails>().await {
+        Ok(mut details) => {
+            details.image_url = format!("{cdn_url}/spirits/{slug}/image.webp");
+            Some(details)
+        }
+        Err(error) => {
+            report_parse_failure(&format!("the details of {entity}"), error);
+            None
+        }
+    }
+}
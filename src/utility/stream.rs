@@ -0,0 +1,200 @@
+use super::{
+    constants::{
+        REDIS_BATCH_SIZE, REDIS_BLOCK, REDIS_CLAIM_HEARTBEAT, REDIS_CLAIM_IDLE,
+        REDIS_RECONNECT_INTERVAL,
+    },
+    discord_clients::DiscordClients,
+    dispatcher::Dispatcher,
+    metrics,
+};
+use crate::structures::{
+    config::{Config, RedisConfig},
+    notification::{prepare_notification_to_send, NotificationNotify},
+};
+use anyhow::Result;
+use redis::{
+    aio::MultiplexedConnection,
+    streams::{
+        StreamAutoClaimOptions, StreamAutoClaimReply, StreamClaimOptions, StreamId,
+        StreamReadOptions, StreamReadReply,
+    },
+    AsyncCommands,
+};
+use sqlx::{Pool, Postgres};
+use tokio::time::{interval, sleep};
+
+/// Publishes scheduled notifications for workers to send.
+pub struct StreamPublisher {
+    connection: MultiplexedConnection,
+    stream: String,
+}
+
+impl StreamPublisher {
+    pub async fn connect(config: &RedisConfig) -> Result<Self> {
+        let connection = redis::Client::open(config.url.as_str())?
+            .get_multiplexed_async_connection()
+            .await?;
+
+        Ok(Self {
+            connection,
+            stream: config.stream.clone(),
+        })
+    }
+
+    pub async fn publish(&self, notification_notify: &NotificationNotify) -> Result<()> {
+        let payload = serde_json::to_string(notification_notify)?;
+
+        let _: String = self
+            .connection
+            .clone()
+            .xadd(&self.stream, "*", &[("payload", payload)])
+            .await?;
+
+        metrics::increment("stream_published");
+        Ok(())
+    }
+}
+
+/// Sends notifications from the stream as part of the consumer group, reconnecting whenever Redis fails.
+pub async fn work(
+    config: &Config,
+    clients: &DiscordClients,
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+) {
+    loop {
+        if let Err(error) = consume(config, clients, pool, dispatcher).await {
+            tracing::error!("Error consuming the notification stream: {error:?}");
+        }
+
+        sleep(REDIS_RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn consume(
+    config: &Config,
+    clients: &DiscordClients,
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+) -> Result<()> {
+    let mut connection = redis::Client::open(config.redis.url.as_str())?
+        .get_multiplexed_async_connection()
+        .await?;
+
+    let created: redis::RedisResult<()> = connection
+        .xgroup_create_mkstream(&config.redis.stream, &config.redis.group, "$")
+        .await;
+
+    if let Err(error) = created {
+        if error.code() != Some("BUSYGROUP") {
+            return Err(error.into());
+        }
+    }
+
+    tracing::info!(
+        consumer = config.redis.consumer,
+        "Consuming the notification stream."
+    );
+
+    loop {
+        // Messages are only acknowledged once sent, so take over those a crashed worker left behind.
+        let claimed: StreamAutoClaimReply = connection
+            .xautoclaim_options(
+                &config.redis.stream,
+                &config.redis.group,
+                &config.redis.consumer,
+                REDIS_CLAIM_IDLE.as_millis() as u64,
+                "0-0",
+                StreamAutoClaimOptions::default().count(REDIS_BATCH_SIZE),
+            )
+            .await?;
+
+        for entry in claimed.claimed {
+            metrics::increment("stream_reclaimed");
+            handle(&mut connection, config, clients, pool, dispatcher, entry).await?;
+        }
+
+        let reply: StreamReadReply = connection
+            .xread_options(
+                &[&config.redis.stream],
+                &[">"],
+                &StreamReadOptions::default()
+                    .group(&config.redis.group, &config.redis.consumer)
+                    .count(REDIS_BATCH_SIZE)
+                    .block(REDIS_BLOCK.as_millis() as usize),
+            )
+            .await?;
+
+        for entry in reply.keys.into_iter().flat_map(|key| key.ids) {
+            handle(&mut connection, config, clients, pool, dispatcher, entry).await?;
+        }
+    }
+}
+
+/// Resets how long a message has been idle, so other workers do not take over one still being sent.
+async fn renew_claim(connection: &mut MultiplexedConnection, config: &Config, id: &str) {
+    let renewed: redis::RedisResult<Vec<String>> = connection
+        .xclaim_options(
+            &config.redis.stream,
+            &config.redis.group,
+            &config.redis.consumer,
+            0,
+            &[id],
+            StreamClaimOptions::default().with_justid(),
+        )
+        .await;
+
+    if let Err(error) = renewed {
+        tracing::warn!(
+            id,
+            "Failed to renew the claim on a stream message: {error:?}"
+        );
+    }
+}
+
+async fn handle(
+    connection: &mut MultiplexedConnection,
+    config: &Config,
+    clients: &DiscordClients,
+    pool: &Pool<Postgres>,
+    dispatcher: &Dispatcher,
+    entry: StreamId,
+) -> Result<()> {
+    let notification_notify = entry
+        .get::<String>("payload")
+        .and_then(|payload| serde_json::from_str::<NotificationNotify>(&payload).ok());
+
+    match notification_notify {
+        Some(notification_notify) => {
+            let send = prepare_notification_to_send(
+                clients,
+                pool,
+                dispatcher,
+                config,
+                notification_notify,
+            );
+
+            tokio::pin!(send);
+            let mut heartbeat = interval(REDIS_CLAIM_HEARTBEAT);
+            // The first tick is immediate, and the message was only just claimed.
+            heartbeat.tick().await;
+
+            loop {
+                tokio::select! {
+                    () = &mut send => break,
+                    _ = heartbeat.tick() => renew_claim(connection, config, &entry.id).await,
+                }
+            }
+
+            metrics::increment("stream_consumed");
+        }
+        // Retrying a malformed message would never succeed, so acknowledge it.
+        None => tracing::error!(id = entry.id, "Discarding a malformed stream message."),
+    }
+
+    let _: u64 = connection
+        .xack(&config.redis.stream, &config.redis.group, &[&entry.id])
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use super::functions::format_list;
+use crate::structures::notification::NotificationNotify;
+use anyhow::{anyhow, bail, Result};
+
+pub const TEMPLATE_MAXIMUM_LENGTH: usize = 1500;
+
+const PLACEHOLDERS: [&str; 11] = [
+    "start", "end", "spirit", "realm", "map", "entities", "reward", "digest", "activity",
+    "version", "variant",
+];
+
+/// Checks a template is well-formed and only uses known placeholders.
+///
+/// Whether a placeholder has a value depends on the notification, so that is only known when rendering.
+pub fn validate_template(template: &str) -> Result<()> {
+    if template.chars().count() > TEMPLATE_MAXIMUM_LENGTH {
+        bail!("Templates may be at most {TEMPLATE_MAXIMUM_LENGTH} characters.");
+    }
+
+    render_with(template, |placeholder| {
+        PLACEHOLDERS
+            .contains(&placeholder)
+            .then(String::new)
+            .ok_or_else(|| anyhow!("Unknown placeholder {{{placeholder}}}."))
+    })
+    .map(|_| ())
+}
+
+/// Renders a template for a notification, failing if it uses a placeholder the notification has no value for.
+pub fn render_template(template: &str, notification_notify: &NotificationNotify) -> Result<String> {
+    render_with(template, |placeholder| {
+        let value = match placeholder {
+            "start" => Some(format!("<t:{}:R>", notification_notify.start_time)),
+            "end" => notification_notify
+                .end_time
+                .map(|end_time| format!("<t:{end_time}:R>")),
+            "spirit" => notification_notify.travelling_spirit_name.clone(),
+            "realm" => notification_notify
+                .shard_eruption
+                .as_ref()
+                .map(|shard_eruption| shard_eruption.realm.clone()),
+            "map" => notification_notify
+                .shard_eruption
+                .as_ref()
+                .map(|shard_eruption| shard_eruption.sky_map.to_string()),
+            "entities" => notification_notify
+                .special_visit_entities
+                .as_ref()
+                .map(|entities| format_list(entities)),
+            "reward" => notification_notify
+                .shard_eruption
+                .as_ref()
+                .map(|shard_eruption| shard_eruption.reward_text()),
+            "digest" => notification_notify.weekly_digest.clone(),
+            "activity" => notification_notify
+                .recurring_event
+                .as_ref()
+                .map(|recurring_event| recurring_event.name.clone()),
+            "variant" => notification_notify
+                .shard_eruption
+                .as_ref()
+                .and_then(|shard_eruption| shard_eruption.variant.clone()),
+            "version" => notification_notify
+                .game_update
+                .as_ref()
+                .map(|game_update| game_update.version.clone()),
+            _ => bail!("Unknown placeholder {{{placeholder}}}."),
+        };
+
+        value.ok_or_else(|| {
+            anyhow!(
+                "{{{placeholder}}} has no value for {:?}.",
+                notification_notify.r#type
+            )
+        })
+    })
+}
+
+/// Replaces every `{placeholder}` with its value. `{{` and `}}` escape literal braces.
+fn render_with(template: &str, mut value: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut characters = template.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '{' if characters.peek() == Some(&'{') => {
+                characters.next();
+                rendered.push('{');
+            }
+            '}' if characters.peek() == Some(&'}') => {
+                characters.next();
+                rendered.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+
+                loop {
+                    match characters.next() {
+                        Some('}') => break,
+                        Some('{') | None => bail!("Unclosed placeholder."),
+                        Some(character) => placeholder.push(character),
+                    }
+                }
+
+                if placeholder.is_empty() {
+                    bail!("Empty placeholder.");
+                }
+
+                rendered.push_str(&value(&placeholder)?);
+            }
+            '}' => bail!("Unmatched }}."),
+            character => rendered.push(character),
+        }
+    }
+
+    Ok(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::notification::NotificationType;
+
+    fn notification_notify() -> NotificationNotify {
+        NotificationNotify {
+            r#type: NotificationType::TravellingSpirit,
+            start_time: 1_700_000_000,
+            end_time: None,
+            time_until_start: 15,
+            shard_eruption: None,
+            travelling_spirit_name: Some("Sassy Drifter".to_string()),
+            special_visit_entities: None,
+            travelling_spirit_details: None,
+            weekly_digest: None,
+            occurrences: None,
+            recurring_event: None,
+            maintenance_end: None,
+            game_update: None,
+            late: false,
+            queued_at: None,
+        }
+    }
+
+    #[test]
+    fn renders_placeholders_and_escaped_braces() {
+        assert_eq!(
+            render_template("{{{spirit}}} arrives {start}!", &notification_notify()).unwrap(),
+            "{Sassy Drifter} arrives <t:1700000000:R>!"
+        );
+    }
+
+    #[test]
+    fn rejects_placeholders_without_a_value() {
+        let error = render_template("Ends {end}.", &notification_notify()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "{end} has no value for TravellingSpirit."
+        );
+    }
+
+    #[test]
+    fn validates_known_placeholders_only() {
+        assert!(validate_template("{spirit} in {realm} at {map}").is_ok());
+
+        assert_eq!(
+            validate_template("{spirt}").unwrap_err().to_string(),
+            "Unknown placeholder {spirt}."
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in ["{start", "{}", "start}", "{sta{rt}"] {
+            assert!(validate_template(template).is_err(), "{template}");
+        }
+
+        assert!(validate_template(&"a".repeat(TEMPLATE_MAXIMUM_LENGTH + 1)).is_err());
+    }
+}
@@ -1,15 +1,22 @@
-use super::constants::SkyMap;
+use super::{
+    constants::{SkyMap, WIND_PATHS_MAXIMUM_ATTEMPTS, WIND_PATHS_TIMEOUT},
+    error_reporting::report_parse_failure,
+    http::CLIENT,
+};
+use anyhow::Result;
 use chrono::{DateTime, Utc};
 use chrono_tz::{America::Los_Angeles, Tz};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::sleep;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ShardEruptionRawDates {
     start: DateTime<Utc>,
     end: DateTime<Utc>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct ShardEruptionRawResponse {
     pub realm: String,
     pub sky_map: SkyMap,
@@ -17,15 +24,18 @@ pub struct ShardEruptionRawResponse {
     pub reward: f32,
     pub timestamps: Vec<ShardEruptionRawDates>,
     pub url: String,
+    pub variant: Option<String>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ShardEruptionDates {
+    #[serde(with = "los_angeles")]
     pub start: DateTime<Tz>,
+    #[serde(with = "los_angeles")]
     pub end: DateTime<Tz>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct ShardEruptionResponse {
     pub realm: String,
     pub sky_map: SkyMap,
@@ -33,18 +43,36 @@ pub struct ShardEruptionResponse {
     pub reward: f32,
     pub timestamps: Vec<ShardEruptionDates>,
     pub url: String,
+    /// A special kind of shard eruption with different rewards, such as "memory".
+    pub variant: Option<String>,
 }
 
-pub async fn shard_eruption(url: &String) -> Option<ShardEruptionResponse> {
-    let data = reqwest::get(format!("{url}/shard-eruption"))
-        .await
-        .expect("Failed to fetch the shard eruption.")
-        .json::<Option<ShardEruptionRawResponse>>()
-        .await
-        .expect("Failed to parse the shard eruption.");
+impl ShardEruptionResponse {
+    /// Describes the reward, which is ascended candles for strong shard eruptions and candle wax otherwise.
+    pub fn reward_text(&self) -> String {
+        if self.strong {
+            format!("{} ascended candles", self.reward)
+        } else {
+            format!("{} candle wax", self.reward)
+        }
+    }
+
+    /// Notes the variant, if any, to follow the rest of a message.
+    pub fn variant_note(&self) -> String {
+        self.variant
+            .as_ref()
+            .map(|variant| format!(" It is a {variant} shard eruption!"))
+            .unwrap_or_default()
+    }
+}
+
+impl From<ShardEruptionRawResponse> for ShardEruptionResponse {
+    fn from(raw_data: ShardEruptionRawResponse) -> Self {
+        if let SkyMap::Other(ref sky_map) = raw_data.sky_map {
+            tracing::warn!("Received an unknown shard eruption map: {sky_map}.");
+        }
 
-    if let Some(raw_data) = data {
-        Some(ShardEruptionResponse {
+        Self {
             realm: raw_data.realm,
             sky_map: raw_data.sky_map,
             strong: raw_data.strong,
@@ -58,8 +86,66 @@ pub async fn shard_eruption(url: &String) -> Option<ShardEruptionResponse> {
                 })
                 .collect(),
             url: raw_data.url,
-        })
-    } else {
-        None
+            variant: raw_data.variant,
+        }
+    }
+}
+
+/// Fetches today's shard eruption, retrying with backoff. `None` means there is no shard eruption today.
+pub async fn shard_eruption(url: &String) -> Result<Option<ShardEruptionRawResponse>> {
+    let mut attempt = 1;
+
+    loop {
+        match fetch_shard_eruption(url).await {
+            Ok(data) => return Ok(data),
+            Err(error) if attempt < WIND_PATHS_MAXIMUM_ATTEMPTS => {
+                tracing::warn!(
+                    "Failed to fetch the shard eruption (attempt {attempt} of {WIND_PATHS_MAXIMUM_ATTEMPTS}): {error:?}"
+                );
+
+                sleep(Duration::from_secs(2u64.pow(attempt))).await;
+                attempt += 1;
+            }
+            Err(error) => {
+                if error
+                    .downcast_ref::<reqwest::Error>()
+                    .is_some_and(reqwest::Error::is_decode)
+                {
+                    report_parse_failure("the shard eruption", &error);
+                }
+
+                return Err(error);
+            }
+        }
+    }
+}
+
+async fn fetch_shard_eruption(url: &String) -> Result<Option<ShardEruptionRawResponse>> {
+    let data = CLIENT
+        .get(format!("{url}/shard-eruption"))
+        .timeout(WIND_PATHS_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Option<ShardEruptionRawResponse>>()
+        .await?;
+
+    Ok(data)
+}
+
+/// Stores Los Angeles times as UTC, as chrono cannot deserialise arbitrary time zones.
+mod los_angeles {
+    use chrono::{DateTime, Utc};
+    use chrono_tz::{America::Los_Angeles, Tz};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(date: &DateTime<Tz>, serializer: S) -> Result<S::Ok, S::Error> {
+        date.with_timezone(&Utc).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Tz>, D::Error> {
+        DateTime::<Utc>::deserialize(deserializer).map(|date| date.with_timezone(&Los_Angeles))
     }
 }